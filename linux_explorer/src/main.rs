@@ -1,15 +1,12 @@
 extern crate terminal_termion;
 extern crate periphery_flex;
 extern crate periphery_linux;
-extern crate csv;
+extern crate data_recorder;
+extern crate fusion;
 extern crate chrono;
 
-use std::time::*;
 use std::path::Path;
-use std::thread;
-use std::sync::{Arc, Mutex};
-
-use chrono::*;
+use std::sync::Arc;
 
 use periphery_flex::core::*;
 use periphery_flex::core::bus::logger::*;
@@ -19,15 +16,25 @@ use periphery_flex::core::cli::*;
 use periphery_flex::*;
 use periphery_linux::*;
 
-fn detect(i2c_busses: &Vec<(PeripheryBusCliState, Logger<LinuxI2CBus<StdSystemApi>>)>, devices: &mut Vec<Box<Device + Send + Sync + 'static>>) {
+mod streams;
+use streams::*;
+
+use data_recorder::*;
+use fusion::*;
+
+fn detect(i2c_busses: &Vec<(PeripheryBusCliState, Logger<LinuxI2CBus<StdSystemApi>>)>, devices: &mut Vec<Arc<IdentifiedDevice>>, aliases: &Vec<(String, String)>) {
 	devices.clear();
 	
 	for bus in i2c_busses {
-		devices.extend(devices_detect_all(bus.1.clone()));
+		devices.extend(devices_detect_all(bus.1.clone()).into_iter().map(Arc::new));
+	}
+
+	for &(ref path, ref alias) in aliases {
+		set_alias(devices, path, alias);
 	}
 
 	for device in devices.iter() {
-		println!("Detected device: {}", device.description());
+		println!("Detected device: {} [{}]", device.description(), device.get_device_id().unwrap());
 		match device.init_after_detection() {
 			Ok(false) => (),
 			Ok(true) => {
@@ -42,6 +49,22 @@ fn detect(i2c_busses: &Vec<(PeripheryBusCliState, Logger<LinuxI2CBus<StdSystemAp
 	println!("Detected {} devices.", devices.len());
 }
 
+/// Returns the full path of the renamed device. Devices can't be renamed while their
+/// data streams are being polled.
+fn set_alias(devices: &mut Vec<Arc<IdentifiedDevice>>, path: &str, alias: &str) -> Result<String, PeripheryError> {
+	if devices.iter().any(|d| d.get_device_id().map(|id| id.matches(alias) && !id.matches(path)).unwrap_or(false)) {
+		return Err(PeripheryError::UnsupportedFieldValue);
+	}
+
+	match devices.iter_mut().find(|d| d.get_device_id().map(|id| id.matches(path)).unwrap_or(false)) {
+		Some(device) => {
+			Arc::get_mut(device).ok_or(PeripheryError::LockingError)?.set_alias(alias)?;
+			Ok(device.get_device_id().unwrap().get_path())
+		},
+		None => Err(PeripheryError::DeviceNotFound)
+	}
+}
+
 fn main() {
     use terminal_termion::*;	
 
@@ -57,127 +80,22 @@ fn main() {
     println!("Detected {} I2C busses", i2c_busses.len());
 
 	let mut devices = vec![];
+	let mut aliases = vec![];
 
 	let mut term = TerminalTermion::new();	
 
-	detect(&i2c_busses, &mut devices);
+	detect(&i2c_busses, &mut devices, &aliases);
     
 	let mut prompt = PromptBuffer::new(options);
 	prompt.print_prompt(&mut term);
 
-	#[derive(Copy, Clone, Debug, PartialEq)]
-	enum PollingOutput {
-		Screen,
-		Csv
-	}
-
-	// data polling thread
-	struct CurrentPolling {
-		device: Box<Device + Send + Sync>,
-		poller: Box<DataStreamPoller + Send + Sync>,
-		output: PollingOutput
-
-	}
-	let current_polling: Option<CurrentPolling> = None;
-	let mut current_polling = Arc::new(Mutex::new(current_polling));
-	let mut polling_thread = {
-
-		let mut current_polling = current_polling.clone();		
-		thread::spawn(move|| {
-			let mut new_poll = true;
-			let mut csv_output = None;
-			let mut n = 1;
-			let mut started = Instant::now();
-
-			loop {
-				let mut sleep_for_ms: u32 = 100;
-
-				if let Ok(mut current_polling) = current_polling.lock() {
-					if let Some(ref mut current_polling) = *current_polling {
-						let device = &current_polling.device;
-						let mut poller = &mut current_polling.poller;
-
-						if new_poll {
-							n = 1;
-							started = Instant::now();
-							let local: DateTime<Local> = Local::now();
-
-							// do something, like open a file
-							if current_polling.output == PollingOutput::Csv {
-								let file_path = format!("{}_{}.csv", device.id(), local.format("%F %X"));
-								let mut wtr = csv::Writer::from_path(&file_path).unwrap();
-								let mut header = vec!["num".to_string(), "ms".to_string()];
-								for l in &poller.get_info().labels {
-									header.push(l.to_string());
-								}
-								wtr.write_record(&header);
-								csv_output = Some(wtr);
-								println!("Writing CSV to {}", file_path);
-							}
-						}
-						new_poll = false;
-					
-						if let Ok(polled) = poller.poll() {
-							
-							match current_polling.output {
-								PollingOutput::Screen => {
-									for result in &polled {
-										println!("{:?}", result);
-										n += 1;
-									}
-								},
-								PollingOutput::Csv => {
-									if let Some(ref mut wtr) = csv_output {
-										let elapsed = started.elapsed();
-										let nanos = elapsed.subsec_nanos() as u64;
-										let ms = (1000*1000*1000 * elapsed.as_secs() + nanos)/(1000 * 1000);
-
-										for result in &polled {
-											let mut row = vec![n.to_string(), ms.to_string()];
-											match result {
-												&DataStreamPolled::F32 { ref data } => {
-													for v in data {
-														row.push(v.to_string());
-													}
-												},
-												&DataStreamPolled::Strings { ref data } => {
-													for v in data {
-														row.push(v.to_string());
-													}
-												}
-											}
-											
-											wtr.write_record(&row);
-											n += 1;
-										}
-									}
-								}
-							}
-						}
-
-						sleep_for_ms = poller.get_info().poll_every_ms as u32;
-					} else {
-						new_poll = true;
-
-						// cleanup						
-						if let Some(csv_output) = csv_output.take() {
-							// anything?
-							println!("Captured {} data points.", n);
-						}
-					}
-				}
-
-				thread::sleep_ms(sleep_for_ms);
-			}
-		})
-	};
+	let mut stream_manager = StreamManager::new();
 
 	loop {
 		match term.read() {
 			Ok(key) => {
 
                 let mut exit = false;
-				let mut stop_polling = false;
 
                 let prompt_result = prompt.handle_key(key, &mut term, |mut m| {
 
@@ -187,7 +105,48 @@ fn main() {
 					}
 
 					if let Some(mut ctx) = m.command("detect") {
-						detect(&i2c_busses, &mut devices);
+						for stopped in stream_manager.stop_all() {
+							write!(ctx.get_terminal(), "Stopped data stream {}.\r\n", stopped.id);
+							if let Some(e) = stopped.recording_error {
+								write!(ctx.get_terminal(), "Error finishing the recording: {}\r\n", e);
+							}
+						}
+						detect(&i2c_busses, &mut devices, &aliases);
+					}
+
+					if let Some(mut ctx) = m.command("playback ") {
+						match Recording::open_binary(ctx.get_args().trim()) {
+							Ok(recording) => {
+								let device = PlaybackDevice::new(recording, PlaybackSpeed::RealTime).with_looping();
+								let n = devices.iter().filter(|d| d.get_device_id().map(|id| id.bus == "playback").unwrap_or(false)).count();
+								let id = DeviceId::new("playback", Some(&n.to_string()), &device.id());
+								write!(ctx.get_terminal(), "Added {}: {}\r\n", id, device.description());
+								devices.push(Arc::new(IdentifiedDevice::new(id, Box::new(device))));
+							},
+							Err(e) => { write!(ctx.get_terminal(), "Error opening the recording: {}\r\n", e); }
+						}
+					}
+
+					if let Some(mut ctx) = m.command("inventory") {
+						for device in &devices {
+							write!(ctx.get_terminal(), "{}\r\n{}\r\n", device.id(), device.info());
+						}
+					}
+
+					if let Some(mut ctx) = m.command("alias ") {
+						let args: Vec<String> = ctx.get_args().split_whitespace().map(|s| s.to_string()).collect();
+						if args.len() != 2 {
+							ctx.get_terminal().print_line("Usage: alias <device path> <alias>");
+						} else {
+							match set_alias(&mut devices, &args[0], &args[1]) {
+								Ok(path) => {
+									aliases.retain(|a: &(String, String)| a.0 != path);
+									write!(ctx.get_terminal(), "Device {} is now known as {}.\r\n", path, args[1]);
+									aliases.push((path, args[1].clone()));
+								},
+								Err(e) => { write!(ctx.get_terminal(), "Error: {:?}\r\n", e); }
+							}
+						}
 					}
 
 					for bus in &mut i2c_busses {
@@ -201,66 +160,92 @@ fn main() {
 
 					// data streams
 					{
-						if let Ok(c) = current_polling.lock() {
-							if c.is_some() {
-								let cmd = format!("data_stream/stop");
-								if let Some(mut ctx) = m.command(&cmd) {
-									stop_polling = true;
+						if let Some(mut ctx) = m.command("data_stream/list") {
+							for stream in stream_manager.list() {
+								write!(ctx.get_terminal(), "{}: {} {}, every {} ms, {:?}, {} samples, {} errors\r\n",
+									stream.id, stream.device, stream.stream, stream.poll_every_ms, stream.output, stream.samples, stream.errors);
+							}
+						}
+
+						for stream in stream_manager.list() {
+							let cmd = format!("data_stream/{}/stop", stream.id);
+							if let Some(mut ctx) = m.command(&cmd) {
+								if let Ok(stopped) = stream_manager.stop(stream.id) {
+									write!(ctx.get_terminal(), "Stopped data stream {}, {} samples.\r\n", stopped.id, stopped.samples);
+									if let Some(e) = stopped.recording_error {
+										write!(ctx.get_terminal(), "Error finishing the recording: {}\r\n", e);
+									}
 								}
 							}
 						}
 
+						if let Some(mut ctx) = m.command("data_stream/stop") {
+							for stopped in stream_manager.stop_all() {
+								write!(ctx.get_terminal(), "Stopped data stream {}, {} samples.\r\n", stopped.id, stopped.samples);
+								if let Some(e) = stopped.recording_error {
+									write!(ctx.get_terminal(), "Error finishing the recording: {}\r\n", e);
+								}
+							}
+						}
 
-						let mut device_start_polling = None;
+						let mut start_polling = None;
 
-						for (i, device) in devices.iter().enumerate() {
-							if let Some(data_streams) = device.get_data_streams() {
-								for data_stream in data_streams.get_stream_infos() {
-									let cmd = format!("data_stream/{}/{}/info", device.id(), data_stream.cli_id);
-									if let Some(mut ctx) = m.command(&cmd) {
-										write!(ctx.get_terminal(), "{:?}\r\n", data_stream);
+						for device in devices.iter() {
+							let data_streams = FusionDataStreams::new(&**device);
+							for data_stream in data_streams.get_stream_infos() {
+								let cmd = format!("data_stream/{}/{}/info", device.id(), data_stream.cli_id);
+								if let Some(mut ctx) = m.command(&cmd) {
+									write!(ctx.get_terminal(), "{:?}\r\n", data_stream);
+								}
+
+								let cmd = format!("data_stream/{}/{}/poll", device.id(), data_stream.cli_id);
+								if let Some(mut ctx) = m.command(&cmd) {
+									if let Ok(poller) = data_streams.get_poller(data_stream.id) {
+										start_polling = Some((device.clone(), poller, PollingOutput::Screen));
 									}
+								}
 
-									let cmd = format!("data_stream/{}/{}/poll", device.id(), data_stream.cli_id);
-									if let Some(mut ctx) = m.command(&cmd) {
-										if let Ok(poller) = data_streams.get_poller(data_stream.id) {
-											device_start_polling = Some((i, poller, PollingOutput::Screen));
-										}
+								let cmd = format!("data_stream/{}/{}/csv", device.id(), data_stream.cli_id);
+								if let Some(mut ctx) = m.command(&cmd) {
+									if let Ok(poller) = data_streams.get_poller(data_stream.id) {
+										start_polling = Some((device.clone(), poller, PollingOutput::Record(RecordingFormat::Csv)));
 									}
+								}
 
-									let cmd = format!("data_stream/{}/{}/csv", device.id(), data_stream.cli_id);
-									if let Some(mut ctx) = m.command(&cmd) {
-										if let Ok(poller) = data_streams.get_poller(data_stream.id) {
-											device_start_polling = Some((i, poller, PollingOutput::Csv));
+								let cmd = format!("data_stream/{}/{}/record ", device.id(), data_stream.cli_id);
+								if let Some(mut ctx) = m.command(&cmd) {
+									match ctx.get_args().trim().parse() {
+										Ok(format) => {
+											if let Ok(poller) = data_streams.get_poller(data_stream.id) {
+												start_polling = Some((device.clone(), poller, PollingOutput::Record(format)));
+											}
+										},
+										Err(_) => {
+											let formats: Vec<_> = RecordingFormat::all().iter().map(|f| f.get_extension()).collect();
+											write!(ctx.get_terminal(), "Usage: record <{}>\r\n", formats.join("|"));
 										}
 									}
 								}
 							}
 						}
 
-						if let Some(device_start_polling) = device_start_polling {
-							if let Ok(mut current_polling) = current_polling.lock() {
-								if current_polling.is_none() {
-									let device = devices.swap_remove(device_start_polling.0);
-									let poller = device_start_polling.1;
-									let info = poller.get_info();
+						if let Some((device, poller, output)) = start_polling {
+							let info = poller.get_info();
+							let device_id = device.id().to_string();
 
-									write!(m.get_terminal(), "Starting polling device {}, data stream {}, polling every {} ms, data labels {:?}!\r\n",
-										device.id(),
+							match stream_manager.start(device, poller, output) {
+								Ok(id) => {
+									write!(m.get_terminal(), "Started data stream {}, polling device {}, data stream {}, every {} ms, data labels {:?}.\r\n",
+										id,
+										device_id,
 										info.cli_id,
 										info.poll_every_ms,
-										info.labels
+										info.get_labels()
 									);
-									
-									*current_polling = Some(CurrentPolling {
-										device: device,
-										poller: poller,
-										output: device_start_polling.2
-									});
-								}
+								},
+								Err(e) => { write!(m.get_terminal(), "Error starting the data stream: {:?}\r\n", e); }
 							}
 						}
-
 					}
 
 				});
@@ -269,29 +254,17 @@ fn main() {
 
 				match prompt_result {
 					Some(PromptEvent::Break) => {
-						if let Ok(c) = current_polling.lock() {
-							if c.is_some() {
-								stop_polling = true;
-							} else {
-								break;
-							}
+						let stopped = stream_manager.stop_all();
+						if stopped.len() == 0 {
+							break;
+						}
+						for e in stopped.iter().filter_map(|s| s.recording_error.as_ref()) {
+							println!("Error finishing the recording: {}", e);
 						}
+						println!("Stopped {} data streams!", stopped.len());
 					},
 					_ => ()
 				}
-
-				if stop_polling {
-					if let Ok(mut current_polling) = current_polling.lock() {
-						if let Some(current_polling) = current_polling.take() {
-							println!("Stopped polling device {}!", current_polling.device.id());
-							devices.push(current_polling.device);
-							continue;
-						}
-					}
-				}
-
-
-
 			},
 			Err(_) => {
 				break;
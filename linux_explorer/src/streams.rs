@@ -0,0 +1,215 @@
+//! Polls several device data streams at once, each at its own rate and in its own
+//! thread, so a slow device doesn't hold up the other streams or the CLI. The devices
+//! are shared with the CLI, so they stay usable while they are being polled.
+
+use std::time::{Duration, Instant};
+use std::thread::{self, JoinHandle};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, RecvTimeoutError};
+
+use chrono::{DateTime, Local};
+use data_recorder::*;
+
+use periphery_flex::core::*;
+use periphery_flex::core::prelude::v1::*;
+use periphery_linux::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PollingOutput {
+	Screen,
+	Record(RecordingFormat)
+}
+
+/// Updated by the polling thread, read by `data_stream/list`.
+#[derive(Default)]
+struct StreamCounters {
+	samples: AtomicUsize,
+	errors: AtomicUsize
+}
+
+/// Owned by the polling thread of the stream.
+struct PolledStream {
+	id: usize,
+	device: Arc<IdentifiedDevice>,
+	poller: Box<DataStreamPoller + Send + Sync>,
+	recorder: Option<Box<DataStreamRecorder + Send>>,
+	counters: Arc<StreamCounters>
+}
+
+impl PolledStream {
+	fn poll(&mut self, clock: &SystemApiClock) {
+		let polled = match self.poller.poll(&*self.device, clock) {
+			Ok(polled) => polled,
+			Err(_) => {
+				self.counters.errors.fetch_add(1, Ordering::SeqCst);
+				return;
+			}
+		};
+
+		match self.recorder {
+			Some(ref mut recorder) => {
+				for result in &polled {
+					if recorder.record(result).is_err() {
+						self.counters.errors.fetch_add(1, Ordering::SeqCst);
+					}
+					self.counters.samples.fetch_add(1, Ordering::SeqCst);
+				}
+			},
+			None => {
+				for result in &polled {
+					println!("[{}] {} {:?}", self.id, result.timestamp_us, result.values);
+					self.counters.samples.fetch_add(1, Ordering::SeqCst);
+				}
+			}
+		}
+	}
+
+	/// Polls until the stream is stopped, then flushes the recording.
+	fn run(mut self, poll_every: Duration, stop: Receiver<()>) -> io::Result<()> {
+		let system = StdSystemApi;
+		let clock = system.get_clock().expect("The system clock is required for polling");
+
+		let mut next_poll = Instant::now();
+		loop {
+			self.poll(clock);
+
+			// keep the rate, but don't try to catch up after a slow poll
+			next_poll += poll_every;
+			let now = Instant::now();
+			if next_poll < now {
+				next_poll = now;
+			}
+
+			match stop.recv_timeout(next_poll - now) {
+				Err(RecvTimeoutError::Timeout) => (),
+				_ => break
+			}
+		}
+
+		match self.recorder {
+			Some(ref mut recorder) => recorder.flush(),
+			None => Ok(())
+		}
+	}
+}
+
+/// Summary of a running stream, for `data_stream/list`.
+#[derive(Clone, Debug)]
+pub struct StreamStatus {
+	pub id: usize,
+	pub device: String,
+	pub stream: String,
+	pub poll_every_ms: usize,
+	pub output: PollingOutput,
+	pub samples: usize,
+	pub errors: usize,
+	/// Set when a stopped stream couldn't finish its recording.
+	pub recording_error: Option<String>
+}
+
+struct RunningStream {
+	id: usize,
+	device: String,
+	info: DataStream,
+	output: PollingOutput,
+	counters: Arc<StreamCounters>,
+	stop: Sender<()>,
+	thread: JoinHandle<io::Result<()>>
+}
+
+pub struct StreamManager {
+	streams: Vec<RunningStream>,
+	next_id: usize
+}
+
+impl StreamManager {
+	pub fn new() -> Self {
+		StreamManager {
+			streams: vec![],
+			next_id: 1
+		}
+	}
+
+	/// Starts the polling thread of the stream, returns the id of the new stream.
+	pub fn start(&mut self, device: Arc<IdentifiedDevice>, poller: Box<DataStreamPoller + Send + Sync>, output: PollingOutput) -> Result<usize, PeripheryError> {
+		let info = poller.get_info();
+		let id = self.next_id;
+
+		let recorder = match output {
+			PollingOutput::Record(format) => {
+				let local: DateTime<Local> = Local::now();
+				let file_path = format!("{}_{}_{}.{}", device.id().replace("/", "_"), info.cli_id, local.format("%F %X"), format.get_extension());
+				let header = RecordingHeader::new(&device.id(), info.clone(), StdSystemApi.get_timestamp_us());
+				let recorder = format.create_file(&file_path, &header).map_err(|_| PeripheryError::WriteError)?;
+				println!("Recording to {}", file_path);
+				Some(recorder)
+			},
+			PollingOutput::Screen => None
+		};
+
+		let counters = Arc::new(StreamCounters::default());
+		let stream = PolledStream {
+			id: id,
+			device: device.clone(),
+			poller: poller,
+			recorder: recorder,
+			counters: counters.clone()
+		};
+
+		let poll_every = Duration::from_millis(info.poll_every_ms as u64);
+		let (stop, stopped) = mpsc::channel();
+		let thread = thread::spawn(move|| stream.run(poll_every, stopped));
+
+		self.streams.push(RunningStream {
+			id: id,
+			device: device.id().to_string(),
+			info: info,
+			output: output,
+			counters: counters,
+			stop: stop,
+			thread: thread
+		});
+		self.next_id += 1;
+
+		Ok(id)
+	}
+
+	/// Waits for the stream's current poll to finish. The other streams keep polling.
+	pub fn stop(&mut self, id: usize) -> Result<StreamStatus, PeripheryError> {
+		let idx = self.streams.iter().position(|s| s.id == id).ok_or(PeripheryError::DataNotAvailable)?;
+		let stream = self.streams.remove(idx);
+
+		let _ = stream.stop.send(());
+		let mut status = Self::status(&stream);
+		status.recording_error = match stream.thread.join() {
+			Ok(Ok(())) => None,
+			Ok(Err(e)) => Some(e.to_string()),
+			Err(_) => Some("The polling thread panicked".to_string())
+		};
+
+		Ok(status)
+	}
+
+	pub fn stop_all(&mut self) -> Vec<StreamStatus> {
+		let ids: Vec<_> = self.list().iter().map(|s| s.id).collect();
+		ids.into_iter().filter_map(|id| self.stop(id).ok()).collect()
+	}
+
+	pub fn list(&self) -> Vec<StreamStatus> {
+		self.streams.iter().map(Self::status).collect()
+	}
+
+	fn status(stream: &RunningStream) -> StreamStatus {
+		StreamStatus {
+			id: stream.id,
+			device: stream.device.clone(),
+			stream: stream.info.cli_id.to_string(),
+			poll_every_ms: stream.info.poll_every_ms,
+			output: stream.output,
+			samples: stream.counters.samples.load(Ordering::SeqCst),
+			errors: stream.counters.errors.load(Ordering::SeqCst),
+			recording_error: None
+		}
+	}
+}
@@ -0,0 +1,291 @@
+//! Parsing of the chip and the per-register attributes.
+
+use proc_macro2::{Span, TokenStream};
+use syn::{self, Attribute, Ident, Lit, Meta, NestedMeta, Type};
+use syn::spanned::Spanned;
+
+/// Arguments of the `#[register_map(...)]` and `#[command_map(...)]` attributes.
+pub struct ChipArgs {
+	pub core_path: TokenStream
+}
+
+pub fn parse_chip_args(args: &[NestedMeta]) -> syn::Result<ChipArgs> {
+	let mut core_path = quote! { ::periphery_core };
+
+	for arg in args {
+		match *arg {
+			NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("core_path") => {
+				let path = lit_str(&nv.lit)?;
+				core_path = syn::parse_str::<syn::Path>(&path)
+					.map_err(|_| syn::Error::new(nv.lit.span(), "Expected a path to the periphery_core crate"))
+					.map(|p| quote! { #p })?;
+			},
+			_ => {
+				return Err(syn::Error::new(arg.span(), "Unknown argument, expected `core_path = \"...\"`"));
+			}
+		}
+	}
+
+	Ok(ChipArgs {
+		core_path
+	})
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Access {
+	ReadOnly,
+	WriteOnly,
+	ReadWrite
+}
+
+impl Access {
+	pub fn readable(&self) -> bool {
+		*self != Access::WriteOnly
+	}
+
+	pub fn writable(&self) -> bool {
+		*self != Access::ReadOnly
+	}
+}
+
+pub struct RegisterDef {
+	pub name: Ident,
+	pub ty: Type,
+	pub address: u8,
+	pub size_bytes: usize,
+	pub access: Access,
+	pub reset: Option<Vec<u8>>,
+	pub volatile: bool,
+	pub fields: bool,
+	pub docs: Vec<Attribute>,
+	pub doc: Option<String>
+}
+
+pub struct CommandDef {
+	pub name: Ident,
+	pub ty: Type,
+	pub code: u8,
+	pub size_bytes: usize,
+	pub docs: Vec<Attribute>,
+	pub doc: Option<String>
+}
+
+/// The named fields of the chip struct, without any generics.
+pub fn chip_fields(input: &syn::ItemStruct) -> syn::Result<Vec<&syn::Field>> {
+	if !input.generics.params.is_empty() {
+		return Err(syn::Error::new(input.generics.span(), "The chip struct is generated with its own lifetime and bus parameter, remove the generics"));
+	}
+
+	match input.fields {
+		syn::Fields::Named(ref fields) => Ok(fields.named.iter().collect()),
+		_ => Err(syn::Error::new(input.span(), "Expected a struct with named fields"))
+	}
+}
+
+pub fn parse_register(field: &syn::Field) -> syn::Result<RegisterDef> {
+	let name = field.ident.clone().ok_or_else(|| syn::Error::new(field.span(), "Expected a named field"))?;
+	let nested = find_attribute(field, "register")?;
+
+	let mut address = None;
+	let mut size_bytes = None;
+	let mut access = None;
+	let mut reset = None;
+	let mut volatile = false;
+	let mut fields = false;
+
+	for meta in &nested {
+		match *meta {
+			NestedMeta::Meta(Meta::NameValue(ref nv)) => {
+				let key = path_name(&nv.path);
+				match key.as_str() {
+					"address" => { address = Some(lit_int::<u8>(&nv.lit)?); },
+					"size" => { size_bytes = Some(lit_int::<usize>(&nv.lit)?); },
+					"access" => {
+						access = Some(match lit_str(&nv.lit)?.as_str() {
+							"ro" => Access::ReadOnly,
+							"wo" => Access::WriteOnly,
+							"rw" => Access::ReadWrite,
+							_ => { return Err(syn::Error::new(nv.lit.span(), "Expected \"ro\", \"wo\" or \"rw\"")); }
+						});
+					},
+					"reset" => { reset = Some((parse_reset(&nv.lit)?, nv.lit.span())); },
+					_ => { return Err(syn::Error::new(nv.path.span(), format!("Unknown register attribute `{}`", key))); }
+				}
+			},
+			NestedMeta::Meta(Meta::Path(ref path)) => {
+				let key = path_name(path);
+				match key.as_str() {
+					"volatile" => { volatile = true; },
+					"fields" => { fields = true; },
+					_ => { return Err(syn::Error::new(path.span(), format!("Unknown register flag `{}`", key))); }
+				}
+			},
+			_ => {
+				return Err(syn::Error::new(meta.span(), "Expected `key = value` or a flag"));
+			}
+		}
+	}
+
+	let address = address.ok_or_else(|| syn::Error::new(field.span(), format!("Register `{}` is missing its address", name)))?;
+	let size_bytes = size_bytes.unwrap_or(1);
+	if size_bytes == 0 {
+		return Err(syn::Error::new(field.span(), "Registers have to be at least one byte long"));
+	}
+
+	let reset = match reset {
+		Some((reset, span)) => Some(fit_reset(reset, size_bytes, span)?),
+		None => None
+	};
+
+	let (docs, doc) = parse_docs(&field.attrs);
+
+	Ok(RegisterDef {
+		name,
+		ty: field.ty.clone(),
+		address,
+		size_bytes,
+		access: access.unwrap_or(Access::ReadWrite),
+		reset,
+		volatile,
+		fields,
+		docs,
+		doc
+	})
+}
+
+pub fn parse_command(field: &syn::Field) -> syn::Result<CommandDef> {
+	let name = field.ident.clone().ok_or_else(|| syn::Error::new(field.span(), "Expected a named field"))?;
+	let nested = find_attribute(field, "command")?;
+
+	let mut code = None;
+	let mut size_bytes = None;
+
+	for meta in &nested {
+		match *meta {
+			NestedMeta::Meta(Meta::NameValue(ref nv)) => {
+				let key = path_name(&nv.path);
+				match key.as_str() {
+					"code" => { code = Some(lit_int::<u8>(&nv.lit)?); },
+					"size" => { size_bytes = Some(lit_int::<usize>(&nv.lit)?); },
+					_ => { return Err(syn::Error::new(nv.path.span(), format!("Unknown command attribute `{}`", key))); }
+				}
+			},
+			_ => {
+				return Err(syn::Error::new(meta.span(), "Expected `code = ...` or `size = ...`"));
+			}
+		}
+	}
+
+	let code = code.ok_or_else(|| syn::Error::new(field.span(), format!("Command `{}` is missing its code", name)))?;
+	let (docs, doc) = parse_docs(&field.attrs);
+
+	Ok(CommandDef {
+		name,
+		ty: field.ty.clone(),
+		code,
+		size_bytes: size_bytes.unwrap_or(0),
+		docs,
+		doc
+	})
+}
+
+fn find_attribute(field: &syn::Field, name: &str) -> syn::Result<Vec<NestedMeta>> {
+	let mut found = None;
+
+	for attr in &field.attrs {
+		if !attr.path.is_ident(name) { continue; }
+		if found.is_some() {
+			return Err(syn::Error::new(attr.span(), format!("Duplicate `#[{}(...)]` attribute", name)));
+		}
+
+		match attr.parse_meta()? {
+			Meta::List(list) => { found = Some(list.nested.into_iter().collect()); },
+			m => { return Err(syn::Error::new(m.span(), format!("Expected `#[{}(...)]`", name))); }
+		}
+	}
+
+	found.ok_or_else(|| syn::Error::new(field.span(), format!("Missing the `#[{}(...)]` attribute", name)))
+}
+
+/// The doc comments, and their text for the register map.
+fn parse_docs(attrs: &[Attribute]) -> (Vec<Attribute>, Option<String>) {
+	let mut docs = vec![];
+	let mut lines = vec![];
+
+	for attr in attrs {
+		if !attr.path.is_ident("doc") { continue; }
+		docs.push(attr.clone());
+
+		if let Ok(Meta::NameValue(nv)) = attr.parse_meta() {
+			if let Lit::Str(s) = nv.lit {
+				lines.push(s.value().trim().to_string());
+			}
+		}
+	}
+
+	let doc = if !lines.is_empty() { Some(lines.join("\n")) } else { None };
+	(docs, doc)
+}
+
+fn path_name(path: &syn::Path) -> String {
+	path.get_ident().map(|i| i.to_string()).unwrap_or_default()
+}
+
+fn lit_str(lit: &Lit) -> syn::Result<String> {
+	match *lit {
+		Lit::Str(ref s) => Ok(s.value()),
+		_ => Err(syn::Error::new(lit.span(), "Expected a string"))
+	}
+}
+
+fn lit_int<N>(lit: &Lit) -> syn::Result<N> where N: ::std::str::FromStr, N::Err: ::std::fmt::Display {
+	match *lit {
+		Lit::Int(ref i) => i.base10_parse::<N>(),
+		_ => Err(syn::Error::new(lit.span(), "Expected an integer"))
+	}
+}
+
+enum Reset {
+	Integer(u64),
+	Bytes(Vec<u8>)
+}
+
+fn parse_reset(lit: &Lit) -> syn::Result<Reset> {
+	match *lit {
+		Lit::Int(ref i) => Ok(Reset::Integer(i.base10_parse::<u64>()?)),
+		Lit::Str(ref s) => {
+			let mut bytes = vec![];
+			for b in s.value().split(|c: char| c.is_whitespace() || c == ',').filter(|b| !b.is_empty()) {
+				let parsed = if b.starts_with("0x") || b.starts_with("0X") {
+					u8::from_str_radix(&b[2..], 16)
+				} else {
+					b.parse::<u8>()
+				};
+				bytes.push(parsed.map_err(|_| syn::Error::new(lit.span(), format!("Invalid reset byte `{}`", b)))?);
+			}
+			Ok(Reset::Bytes(bytes))
+		},
+		_ => Err(syn::Error::new(lit.span(), "Expected an integer or a string of bytes"))
+	}
+}
+
+/// Integers are stored with the most significant byte first on the bus.
+fn fit_reset(reset: Reset, size_bytes: usize, span: Span) -> syn::Result<Vec<u8>> {
+	match reset {
+		Reset::Integer(v) => {
+			if size_bytes < 8 && (v >> (size_bytes * 8)) != 0 {
+				return Err(syn::Error::new(span, format!("The reset value doesn't fit into {} bytes", size_bytes)));
+			}
+			if size_bytes > 8 {
+				return Err(syn::Error::new(span, "Use a string of bytes for the reset value of registers longer than 8 bytes"));
+			}
+			Ok((0..size_bytes).rev().map(|i| (v >> (i * 8)) as u8).collect())
+		},
+		Reset::Bytes(bytes) => {
+			if bytes.len() != size_bytes {
+				return Err(syn::Error::new(span, format!("Expected {} reset bytes, got {}", size_bytes, bytes.len())));
+			}
+			Ok(bytes)
+		}
+	}
+}
@@ -0,0 +1,174 @@
+//! Expansion of `#[command_map]`.
+
+use proc_macro2::TokenStream;
+use syn::{self, NestedMeta};
+use syn::spanned::Spanned;
+
+use attrs::*;
+
+pub fn expand(args: &[NestedMeta], input: &syn::ItemStruct) -> syn::Result<TokenStream> {
+	let args = parse_chip_args(args)?;
+	let fields = chip_fields(input)?;
+
+	let mut commands = vec![];
+	for field in fields {
+		let command = parse_command(field)?;
+		if commands.iter().any(|c: &CommandDef| c.name == command.name) {
+			return Err(syn::Error::new(field.span(), format!("Duplicate command `{}`", command.name)));
+		}
+		commands.push(command);
+	}
+	if commands.is_empty() {
+		return Err(syn::Error::new(input.span(), "The chip has no commands"));
+	}
+
+	let krate = &args.core_path;
+	let chip = &input.ident;
+	let vis = &input.vis;
+	let attrs = &input.attrs;
+	let chip_name = chip.to_string();
+
+	let command_bus = quote! { #krate::bus::device_bus::device::DeviceCommandBus };
+	let map = quote! { #krate::bus::device_bus::register_map };
+
+	let accessors = commands.iter().map(|c| {
+		let name = &c.name;
+		let ty = &c.ty;
+		let docs = &c.docs;
+		let code = c.code;
+		let size_bytes = c.size_bytes;
+
+		quote! {
+			#(#docs)*
+			#[inline]
+			pub fn #name(&self) -> #krate::bus::device_bus::commands::ChipCommand<#ty, B> {
+				#krate::bus::device_bus::commands::ChipCommand {
+					cmd: #code,
+					arg_size_bytes: #size_bytes,
+					command_bus: &self.command_bus,
+					_arg_type: #krate::prelude::v1::PhantomData
+				}
+			}
+		}
+	});
+
+	let map_entries = commands.iter().map(|c| {
+		let name = c.name.to_string();
+		let ty = &c.ty;
+		let ty_name = quote!(#ty).to_string();
+		let code = c.code;
+		let size_bytes = c.size_bytes;
+		let doc = match c.doc {
+			Some(ref doc) => quote! { Some(#doc) },
+			None => quote! { None }
+		};
+
+		quote! {
+			map.commands.push(#map::CommandMapEntry {
+				name: #name,
+				command: #code,
+				arg_size_bytes: #size_bytes,
+				arg_ty: #ty_name,
+				doc: #doc
+			});
+		}
+	});
+
+	let cli = commands.iter().map(|c| {
+		let name = &c.name;
+		let exec_cmd = format!("command/{}/execute", name);
+		let exec_args_cmd = format!("command/{}/execute_args ", name);
+
+		let args = if c.size_bytes > 0 {
+			quote! {
+				if let Some(mut ctx) = exec.command(&#exec_args_cmd) {
+					let args = ctx.get_args().to_string();
+					self.#name().execute_args_from_u8(ctx.get_terminal(), &args);
+				}
+			}
+		} else {
+			quote! {}
+		};
+
+		quote! {
+			if let Some(mut ctx) = exec.command(&#exec_cmd) {
+				match self.#name().execute() {
+					Ok(_) => { write!(ctx.get_terminal(), "Command executed.\r\n"); }
+					Err(e) => { write!(ctx.get_terminal(), "Error: {:?}\r\n", e); }
+				};
+			}
+
+			#args
+		}
+	});
+
+	Ok(quote! {
+		#(#attrs)*
+		#[derive(Clone)]
+		#vis struct #chip<'a, B: 'a> where B: #command_bus {
+			command_bus: &'a B
+		}
+
+		impl<'a, B> #chip<'a, B> where B: #command_bus {
+			#[inline]
+			pub fn new(command_bus: &'a B) -> Self {
+				#chip {
+					command_bus
+				}
+			}
+
+			#(#accessors)*
+
+			/// Description of all the commands.
+			pub fn register_map() -> #map::RegisterMap {
+				let mut map = #map::RegisterMap::new(#chip_name);
+				#(#map_entries)*
+				map
+			}
+		}
+
+		impl<'a, B> #krate::device::BusCommandsCli for #chip<'a, B> where B: #command_bus {
+			fn commands_cli<'b>(&self, exec: &mut #krate::terminal_cli::PrefixedExecutor) {
+				use #krate::terminal_cli::*;
+
+				#(#cli)*
+			}
+
+			fn register_map(&self) -> #map::RegisterMap {
+				#chip::<B>::register_map()
+			}
+		}
+	})
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn expand_str(input: &str) -> syn::Result<String> {
+		let input: syn::ItemStruct = syn::parse_str(input).unwrap();
+		expand(&[], &input).map(|t| t.to_string())
+	}
+
+	#[test]
+	fn test_expand_commands() {
+		let code = expand_str(r#"
+			pub struct TestCommands {
+				#[command(code = 0x81, size = 1)]
+				set_contrast: u8,
+				/// Turn the display on
+				#[command(code = 0xAF)]
+				display_on: EmptyReg
+			}
+		"#).unwrap();
+
+		assert!(code.contains("pub fn set_contrast"));
+		assert!(code.contains("\"command/set_contrast/execute_args \""));
+		assert!(!code.contains("\"command/display_on/execute_args \""));
+		assert!(code.contains("doc : Some (\"Turn the display on\")"));
+
+		assert!(expand_str("pub struct C { #[command(size = 1)] set_contrast: u8 }").is_err());
+		assert!(expand_str("pub struct C { #[command(code = 0x81, args = 1)] set_contrast: u8 }").is_err());
+	}
+}
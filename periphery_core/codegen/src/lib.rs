@@ -0,0 +1,75 @@
+//! Attribute macros that generate the register and command chips of the device drivers,
+//! with per-register attributes. The generated code uses the runtime types of
+//! `periphery_core`, `RegisterAddress` and `ChipCommand`.
+//!
+//! ```ignore
+//! #[register_map]
+//! pub struct Bmp280Registers {
+//!     /// Factory calibration of the sensor
+//!     #[register(address = 0x88, size = 24, access = "ro")]
+//!     calibration_coefficients: CalibrationCoefficients,
+//!
+//!     #[register(address = 0xF4, reset = 0x00, fields)]
+//!     control_measurement: ControlMeasurementRegister,
+//!
+//!     #[register(address = 0xF3, access = "ro", volatile, fields)]
+//!     status: StatusRegister
+//! }
+//!
+//! #[command_map]
+//! pub struct Ssd1306Commands {
+//!     #[command(code = 0x81, size = 1)]
+//!     set_contrast: u8
+//! }
+//! ```
+//!
+//! Register attributes:
+//!
+//! * `address`, required
+//! * `size` in bytes, defaults to 1
+//! * `access`, `"ro"`, `"wo"` or `"rw"` (the default)
+//! * `reset`, the power-on value, either an integer or a string of bytes in bus order
+//!   like `"0x00 0x1F"`
+//! * `volatile`, the register is changed by the chip itself or has side effects when
+//!   read, like FIFOs and clear-on-read interrupt flags. Left out of the snapshots and
+//!   the restores, and never cached by `CachedRegisterBus`
+//! * `fields`, the type implements `RegisterFields`, enables the field editing commands
+//!   and the field layout in the register map
+//!
+//! Generated code refers to `::periphery_core`. Use `#[register_map(core_path = "...")]`
+//! when the crate is known under a different path.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+#[macro_use]
+extern crate syn;
+
+mod attrs;
+mod registers;
+mod commands;
+
+use proc_macro::TokenStream;
+
+#[proc_macro_attribute]
+pub fn register_map(args: TokenStream, input: TokenStream) -> TokenStream {
+	let args = parse_macro_input!(args as syn::AttributeArgs);
+	let input = parse_macro_input!(input as syn::ItemStruct);
+
+	match registers::expand(&args, &input) {
+		Ok(tokens) => tokens.into(),
+		Err(e) => e.to_compile_error().into()
+	}
+}
+
+#[proc_macro_attribute]
+pub fn command_map(args: TokenStream, input: TokenStream) -> TokenStream {
+	let args = parse_macro_input!(args as syn::AttributeArgs);
+	let input = parse_macro_input!(input as syn::ItemStruct);
+
+	match commands::expand(&args, &input) {
+		Ok(tokens) => tokens.into(),
+		Err(e) => e.to_compile_error().into()
+	}
+}
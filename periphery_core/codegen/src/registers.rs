@@ -0,0 +1,337 @@
+//! Expansion of `#[register_map]`.
+
+use proc_macro2::TokenStream;
+use syn::{self, NestedMeta};
+use syn::spanned::Spanned;
+
+use attrs::*;
+
+pub fn expand(args: &[NestedMeta], input: &syn::ItemStruct) -> syn::Result<TokenStream> {
+	let args = parse_chip_args(args)?;
+	let fields = chip_fields(input)?;
+
+	let mut registers = vec![];
+	for field in fields {
+		let register = parse_register(field)?;
+		if registers.iter().any(|r: &RegisterDef| r.name == register.name) {
+			return Err(syn::Error::new(field.span(), format!("Duplicate register `{}`", register.name)));
+		}
+		registers.push(register);
+	}
+	if registers.is_empty() {
+		return Err(syn::Error::new(input.span(), "The chip has no registers"));
+	}
+
+	let krate = &args.core_path;
+	let chip = &input.ident;
+	let vis = &input.vis;
+	let attrs = &input.attrs;
+	let chip_name = chip.to_string();
+
+	let device_bus = quote! { #krate::bus::device_bus::device::DeviceRegisterBus };
+	let regs = quote! { #krate::bus::device_bus::registers };
+	let snapshot = quote! { #krate::bus::device_bus::snapshot };
+	let map = quote! { #krate::bus::device_bus::register_map };
+	let fmt = quote! { #krate::prelude::v1::fmt };
+
+	let accessors = registers.iter().map(|r| {
+		let name = &r.name;
+		let ty = &r.ty;
+		let docs = &r.docs;
+		let address = r.address;
+		let size_bytes = r.size_bytes;
+		let access = access_type(krate, r.access);
+
+		quote! {
+			#(#docs)*
+			#[inline]
+			pub fn #name(&self) -> #regs::RegisterAddress<#ty, B, #access> {
+				#regs::RegisterAddress {
+					address: #address,
+					size_bytes: #size_bytes,
+					register_bus: &self.register_bus,
+					_register_type: #krate::prelude::v1::PhantomData,
+					_access: #krate::prelude::v1::PhantomData
+				}
+			}
+		}
+	});
+
+	let snapshot_reads = registers.iter().filter(|r| r.access.readable() && !r.volatile).map(|r| {
+		let name = r.name.to_string();
+		let address = r.address;
+		let size_bytes = r.size_bytes;
+
+		quote! {
+			snapshot.read_register(#name, #address, #size_bytes, self.register_bus);
+		}
+	});
+
+	let restore_writes = registers.iter().filter(|r| r.access.writable() && !r.volatile).map(|r| {
+		let name = r.name.to_string();
+		let address = r.address;
+		let size_bytes = r.size_bytes;
+
+		quote! {
+			if let Some(value) = snapshot.get_value(#name) {
+				if value.len() != #size_bytes {
+					return Err(#krate::PeripheryError::RegisterSizeMismatch);
+				}
+				self.register_bus.write_to_register(#address, value)?;
+				written += 1;
+			}
+		}
+	});
+
+	let map_entries = registers.iter().map(|r| {
+		let name = r.name.to_string();
+		let ty = &r.ty;
+		let ty_name = quote!(#ty).to_string();
+		let address = r.address;
+		let size_bytes = r.size_bytes;
+		let access = access_name(r.access);
+		let volatile = r.volatile;
+
+		let fields = if r.fields {
+			quote! { #map::register_field_map::<#ty>(#size_bytes) }
+		} else {
+			quote! { None }
+		};
+		let reset = match r.reset {
+			Some(ref reset) => quote! { Some(vec![#(#reset),*]) },
+			None => quote! { None }
+		};
+		let doc = match r.doc {
+			Some(ref doc) => quote! { Some(#doc) },
+			None => quote! { None }
+		};
+
+		quote! {
+			map.registers.push(#map::RegisterMapEntry {
+				name: #name,
+				address: #address,
+				size_bytes: #size_bytes,
+				access: #access,
+				ty: #ty_name,
+				fields: #fields,
+				reset: #reset,
+				volatile: #volatile,
+				doc: #doc
+			});
+		}
+	});
+
+	let display_lines = registers.iter().map(|r| {
+		let ty = &r.ty;
+		let line = format!("Register {} [0x{:X}; {}] => {}: {}", access_name(r.access), r.address, r.size_bytes, r.name, quote!(#ty));
+
+		quote! {
+			write!(f, "{}\r\n", #line)?;
+		}
+	});
+
+	let cli = registers.iter().map(|r| {
+		let name = &r.name;
+		let read_cmd = format!("register/{}/read", name);
+		let write_cmd = format!("register/{}/write ", name);
+		let get_cmd = format!("register/{}/get ", name);
+		let set_cmd = format!("register/{}/set ", name);
+
+		let read = if r.access.readable() {
+			quote! {
+				if let Some(mut ctx) = exec.command(&#read_cmd) {
+					self.#name().read_to_debug_string(ctx.get_terminal());
+				}
+			}
+		} else {
+			quote! {}
+		};
+
+		let write = if r.access.writable() {
+			quote! {
+				if let Some(mut ctx) = exec.command(&#write_cmd) {
+					let args = ctx.get_args().to_string();
+					self.#name().write_from_u8(ctx.get_terminal(), &args);
+				}
+			}
+		} else {
+			quote! {}
+		};
+
+		let get = if r.fields && r.access.readable() {
+			quote! {
+				if let Some(mut ctx) = exec.command(&#get_cmd) {
+					let args = ctx.get_args().to_string();
+					self.#name().get_field_cli(ctx.get_terminal(), &args);
+				}
+			}
+		} else {
+			quote! {}
+		};
+
+		let set = if r.fields && r.access.writable() {
+			quote! {
+				if let Some(mut ctx) = exec.command(&#set_cmd) {
+					let args = ctx.get_args().to_string();
+					self.#name().set_fields_cli(ctx.get_terminal(), &args);
+				}
+			}
+		} else {
+			quote! {}
+		};
+
+		quote! { #read #write #get #set }
+	});
+
+	Ok(quote! {
+		#(#attrs)*
+		#[derive(Clone)]
+		#vis struct #chip<'a, B: 'a> where B: #device_bus {
+			register_bus: &'a B
+		}
+
+		impl<'a, B> #chip<'a, B> where B: #device_bus {
+			#[inline]
+			pub fn new(register_bus: &'a B) -> Self {
+				#chip {
+					register_bus
+				}
+			}
+
+			#(#accessors)*
+
+			/// Read the raw contents of all the readable registers, except the volatile ones.
+			pub fn snapshot(&self) -> #snapshot::RegisterSnapshot {
+				let mut snapshot = #snapshot::RegisterSnapshot::new(#chip_name);
+				#(#snapshot_reads)*
+				snapshot
+			}
+
+			/// Write the writable registers found in the snapshot back to the chip. Volatile
+			/// registers are skipped.
+			pub fn restore(&self, snapshot: &#snapshot::RegisterSnapshot) -> Result<usize, #krate::PeripheryError> {
+				let mut written = 0;
+				#(#restore_writes)*
+				Ok(written)
+			}
+
+			/// Description of all the registers, with their reset values.
+			pub fn register_map() -> #map::RegisterMap {
+				let mut map = #map::RegisterMap::new(#chip_name);
+				#(#map_entries)*
+				map
+			}
+
+			/// Power-on values of the registers that declare them.
+			pub fn reset_snapshot() -> #snapshot::RegisterSnapshot {
+				Self::register_map().reset_snapshot()
+			}
+		}
+
+		impl<'a, B> #snapshot::RegisterSnapshotChip for #chip<'a, B> where B: #device_bus {
+			fn snapshot(&self) -> #snapshot::RegisterSnapshot {
+				#chip::snapshot(self)
+			}
+
+			fn restore(&self, snapshot: &#snapshot::RegisterSnapshot) -> Result<usize, #krate::PeripheryError> {
+				#chip::restore(self, snapshot)
+			}
+		}
+
+		impl<'a, B> #krate::prelude::v1::Display for #chip<'a, B> where B: #device_bus {
+			fn fmt(&self, f: &mut #fmt::Formatter) -> #fmt::Result {
+				write!(f, "Chip {}\r\n", #chip_name)?;
+				#(#display_lines)*
+				Ok(())
+			}
+		}
+
+		impl<'a, B> #krate::device::RegisterBusCli for #chip<'a, B> where B: #device_bus {
+			fn registers_cli<'b>(&self, exec: &mut #krate::terminal_cli::PrefixedExecutor) {
+				use #krate::terminal_cli::*;
+				use #regs::{RegisterAddressCli, RegisterAddressFieldsCli};
+
+				if let Some(mut ctx) = exec.command(&"list_registers") {
+					write!(ctx.get_terminal(), "{}", &self);
+				}
+
+				#snapshot::register_snapshot_cli(self, exec);
+
+				#(#cli)*
+			}
+
+			fn register_map(&self) -> #map::RegisterMap {
+				#chip::<B>::register_map()
+			}
+		}
+	})
+}
+
+fn access_type(krate: &TokenStream, access: Access) -> TokenStream {
+	match access {
+		Access::ReadOnly => quote! { #krate::bus::device_bus::registers::ReadOnly },
+		Access::WriteOnly => quote! { #krate::bus::device_bus::registers::WriteOnly },
+		Access::ReadWrite => quote! { #krate::bus::device_bus::registers::ReadWrite }
+	}
+}
+
+fn access_name(access: Access) -> &'static str {
+	match access {
+		Access::ReadOnly => "ro",
+		Access::WriteOnly => "wo",
+		Access::ReadWrite => "rw"
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn expand_str(input: &str) -> syn::Result<String> {
+		let input: syn::ItemStruct = syn::parse_str(input).unwrap();
+		expand(&[], &input).map(|t| t.to_string())
+	}
+
+	#[test]
+	fn test_expand_registers() {
+		let code = expand_str(r#"
+			pub struct TestRegisters {
+				/// Chip identification
+				#[register(address = 0xD0, access = "ro")]
+				id: u8,
+				#[register(address = 0xF4, reset = 0x27, fields)]
+				control: ControlRegister,
+				#[register(address = 0x88, size = 2, reset = "0x01 0x02", volatile)]
+				data: DataRegister
+			}
+		"#).unwrap();
+
+		assert!(code.contains("pub fn id"));
+		assert!(code.contains("RegisterAddress < u8 , B , :: periphery_core :: bus :: device_bus :: registers :: ReadOnly >"));
+		assert!(code.contains("reset : Some (vec ! [39u8])"));
+		assert!(code.contains("reset : Some (vec ! [1u8 , 2u8])"));
+		assert!(code.contains("volatile : true"));
+		assert!(code.contains("doc : Some (\"Chip identification\")"));
+		assert!(code.contains("register_field_map :: < ControlRegister > (1usize)"));
+		assert!(code.contains("\"register/control/set \""));
+		// read-only registers have no write command
+		assert!(!code.contains("\"register/id/write \""));
+		// volatile registers are neither snapshotted nor restored
+		assert!(code.contains("read_register (\"control\""));
+		assert!(code.contains("get_value (\"control\")"));
+		assert!(!code.contains("read_register (\"data\""));
+		assert!(!code.contains("get_value (\"data\")"));
+	}
+
+	#[test]
+	fn test_expand_errors() {
+		assert!(expand_str("pub struct R { #[register(size = 1)] id: u8 }").is_err());
+		assert!(expand_str("pub struct R { #[register(address = 0xD0, access = \"readonly\")] id: u8 }").is_err());
+		assert!(expand_str("pub struct R { #[register(address = 0xD0, reset = 0x100)] id: u8 }").is_err());
+		assert!(expand_str("pub struct R { #[register(address = 0xD0, size = 2, reset = \"0x01\")] id: u16 }").is_err());
+		assert!(expand_str("pub struct R { #[register(address = 0xD0, cached)] id: u8 }").is_err());
+		assert!(expand_str("pub struct R { id: u8 }").is_err());
+		assert!(expand_str("pub struct R<B> { #[register(address = 0xD0)] id: u8 }").is_err());
+	}
+}
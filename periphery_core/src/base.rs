@@ -14,6 +14,7 @@ pub enum PeripheryError {
 	ReadParseError,	
 	BufferLengthError,
 	UnsupportedFieldValue,
+	UnknownRegisterField,
 	RegisterSizeMismatch,
 	RegisterOversized,
 	WriteError,
@@ -22,8 +23,11 @@ pub enum PeripheryError {
 	CalculationError,
 	MeasurementOverflow,
 	MeasurementNotReady,
+	/// The device was put to sleep, it has to be woken up first.
+	DeviceAsleep,
 
 	BusOperationError,
+	UnexpectedBusTransaction,
 
 	LockingError,
 	
@@ -0,0 +1,289 @@
+//! Register bus wrapper with shadow copies of configuration registers. Reads of
+//! shadowed registers are served from memory, so a read-modify-write becomes a
+//! single bus write.
+
+use prelude::v1::*;
+use base::*;
+use bus::*;
+use bus::device_bus::register_map::*;
+
+use std::sync::{Arc, Mutex};
+
+#[derive(Copy, Clone, Debug)]
+struct CachedRegister {
+    address: u8,
+    self_clearing_bits: u8
+}
+
+/// The last known value of a register.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShadowRegister {
+    pub address: u8,
+    pub value: Vec<u8>
+}
+
+#[derive(Clone)]
+pub struct CachedRegisterBus<B> where B: DeviceRegisterBus {
+    bus: B,
+    cached: Vec<CachedRegister>,
+    invalidate_on_write: Vec<u8>,
+    volatile: Vec<u8>,
+    shadows: Arc<Mutex<Vec<ShadowRegister>>>
+}
+
+impl<B> CachedRegisterBus<B> where B: DeviceRegisterBus {
+    /// Wrap the bus. No registers are cached until they are explicitly enabled.
+    pub fn new(bus: B) -> Self {
+        CachedRegisterBus {
+            bus: bus,
+            cached: vec![],
+            invalidate_on_write: vec![],
+            volatile: vec![],
+            shadows: Arc::new(Mutex::new(vec![]))
+        }
+    }
+
+    /// Keep a shadow copy of this register. Only registers that are changed exclusively
+    /// by the host should be cached, volatile registers are ignored.
+    pub fn cache_register(&mut self, address: u8) -> &mut Self {
+        self.cache_register_self_clearing(address, 0)
+    }
+
+    /// Keep a shadow copy of this register. The `self_clearing_bits` are cleared by the
+    /// chip after a write, for example "start" or "flush" triggers, and are never stored
+    /// in the shadow copy.
+    pub fn cache_register_self_clearing(&mut self, address: u8, self_clearing_bits: u8) -> &mut Self {
+        self.cached.retain(|r| r.address != address);
+        if self.volatile.contains(&address) {
+            return self;
+        }
+
+        self.cached.push(CachedRegister {
+            address: address,
+            self_clearing_bits: self_clearing_bits
+        });
+        self
+    }
+
+    /// The registers marked as volatile in the chip's map are changed by the chip itself
+    /// and are never cached, even if they were already enabled.
+    pub fn volatile_registers(&mut self, map: &RegisterMap) -> &mut Self {
+        for register in map.registers.iter().filter(|r| r.volatile) {
+            self.volatile.push(register.address);
+            self.cached.retain(|r| r.address != register.address);
+            self.invalidate_register(register.address);
+        }
+        self
+    }
+
+    /// Any write to this register, usually a software reset, drops all the shadow copies.
+    pub fn invalidate_on_write(&mut self, address: u8) -> &mut Self {
+        self.invalidate_on_write.push(address);
+        self
+    }
+
+    /// Drop all the shadow copies. The next read of every register will go to the bus.
+    pub fn invalidate(&self) {
+        if let Ok(mut shadows) = self.shadows.lock() {
+            shadows.clear();
+        }
+    }
+
+    pub fn invalidate_register(&self, address: u8) {
+        if let Ok(mut shadows) = self.shadows.lock() {
+            shadows.retain(|s| s.address != address);
+        }
+    }
+
+    /// Seed the shadow copy with a known value, for example the reset value of a
+    /// write-only register.
+    pub fn set_shadow(&self, address: u8, value: &[u8]) {
+        if let Some(cached) = self.get_cached(address) {
+            self.store(cached, value);
+        }
+    }
+
+    /// All the currently known shadow copies.
+    pub fn get_shadows(&self) -> Vec<ShadowRegister> {
+        match self.shadows.lock() {
+            Ok(shadows) => shadows.clone(),
+            Err(_) => vec![]
+        }
+    }
+
+    pub fn get_bus(&self) -> &B {
+        &self.bus
+    }
+
+    fn get_cached(&self, address: u8) -> Option<CachedRegister> {
+        self.cached.iter().find(|r| r.address == address).cloned()
+    }
+
+    fn store(&self, cached: CachedRegister, value: &[u8]) {
+        let value: Vec<u8> = value.iter().map(|b| b & !cached.self_clearing_bits).collect();
+
+        if let Ok(mut shadows) = self.shadows.lock() {
+            if let Some(shadow) = shadows.iter_mut().find(|s| s.address == cached.address) {
+                shadow.value = value;
+                return;
+            }
+
+            shadows.push(ShadowRegister {
+                address: cached.address,
+                value: value
+            });
+        }
+    }
+}
+
+impl<B> DeviceRegisterShadow for CachedRegisterBus<B> where B: DeviceRegisterBus {
+    fn read_shadow(&self, register: u8, data: &mut [u8]) -> Result<(), PeripheryError> {
+        let shadows = self.shadows.lock().map_err(|_| PeripheryError::LockingError)?;
+
+        match shadows.iter().find(|s| s.address == register) {
+            Some(shadow) if shadow.value.len() == data.len() => {
+                data.copy_from_slice(&shadow.value);
+                Ok(())
+            },
+            Some(_) => Err(PeripheryError::RegisterSizeMismatch),
+            None => Err(PeripheryError::DataNotAvailable)
+        }
+    }
+}
+
+impl<B> DeviceRegisterBus for CachedRegisterBus<B> where B: DeviceRegisterBus {
+    fn read_from_register(&self, register: u8, data: &mut [u8]) -> Result<(), PeripheryError> {
+        let cached = self.get_cached(register);
+
+        if cached.is_some() {
+            if let Ok(_) = self.read_shadow(register, data) {
+                return Ok(());
+            }
+        }
+
+        self.bus.read_from_register(register, data)?;
+
+        if let Some(cached) = cached {
+            self.store(cached, data);
+        }
+
+        Ok(())
+    }
+
+    fn write_to_register(&self, register: u8, data: &[u8]) -> Result<(), PeripheryError> {
+        match self.bus.write_to_register(register, data) {
+            Ok(_) => (),
+            Err(e) => {
+                // the state of the chip is unknown now
+                self.invalidate_register(register);
+                return Err(e);
+            }
+        }
+
+        if self.invalidate_on_write.contains(&register) {
+            self.invalidate();
+        }
+
+        if let Some(cached) = self.get_cached(register) {
+            self.store(cached, data);
+        }
+
+        Ok(())
+    }
+}
+
+impl<B> Display for CachedRegisterBus<B> where B: DeviceRegisterBus {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for shadow in self.get_shadows() {
+            try!(write!(f, "Shadow register [0x{:X}]: {:?}\r\n", shadow.address, shadow.value));
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingRegisterBus {
+        reads: AtomicUsize,
+        writes: AtomicUsize
+    }
+
+    impl DeviceRegisterBus for CountingRegisterBus {
+        fn read_from_register(&self, register: u8, data: &mut [u8]) -> Result<(), PeripheryError> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            for b in data.iter_mut() {
+                *b = 0xF0;
+            }
+            Ok(())
+        }
+
+        fn write_to_register(&self, register: u8, data: &[u8]) -> Result<(), PeripheryError> {
+            self.writes.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_cached_register_bus() {
+        let mut bus = CachedRegisterBus::new(CountingRegisterBus { reads: AtomicUsize::new(0), writes: AtomicUsize::new(0) });
+        bus.cache_register_self_clearing(0x06, 0b0100_0001).invalidate_on_write(0x0C);
+
+        let mut data = [0];
+        bus.read_from_register(0x06, &mut data).unwrap();
+        bus.read_from_register(0x06, &mut data).unwrap();
+        assert_eq!(1, bus.get_bus().reads.load(Ordering::SeqCst));
+        assert_eq!([0xF0], data);
+
+        // self clearing bits are not kept
+        bus.write_to_register(0x06, &[0b0100_0011]).unwrap();
+        bus.read_from_register(0x06, &mut data).unwrap();
+        assert_eq!([0b0000_0010], data);
+        assert_eq!(1, bus.get_bus().reads.load(Ordering::SeqCst));
+
+        // uncached registers always hit the bus
+        bus.read_from_register(0x07, &mut data).unwrap();
+        assert_eq!(2, bus.get_bus().reads.load(Ordering::SeqCst));
+
+        // a reset drops the shadows
+        bus.write_to_register(0x0C, &[0x01]).unwrap();
+        assert_eq!(0, bus.get_shadows().len());
+        assert!(bus.read_shadow(0x06, &mut data).is_err());
+        bus.read_from_register(0x06, &mut data).unwrap();
+        assert_eq!(3, bus.get_bus().reads.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_cached_register_bus_volatile() {
+        let mut map = RegisterMap::new("Test");
+        map.registers.push(RegisterMapEntry {
+            name: "interrupt",
+            address: 0x42,
+            size_bytes: 1,
+            access: "ro",
+            ty: "u8",
+            fields: None,
+            reset: None,
+            volatile: true,
+            doc: None
+        });
+
+        let mut bus = CachedRegisterBus::new(CountingRegisterBus { reads: AtomicUsize::new(0), writes: AtomicUsize::new(0) });
+        bus.cache_register(0x42).cache_register(0x06).volatile_registers(&map);
+        // enabling it later is ignored as well
+        bus.cache_register(0x42);
+
+        let mut data = [0];
+        bus.read_from_register(0x42, &mut data).unwrap();
+        bus.read_from_register(0x42, &mut data).unwrap();
+        assert_eq!(2, bus.get_bus().reads.load(Ordering::SeqCst));
+        assert!(bus.read_shadow(0x42, &mut data).is_err());
+
+        bus.read_from_register(0x06, &mut data).unwrap();
+        bus.read_from_register(0x06, &mut data).unwrap();
+        assert_eq!(3, bus.get_bus().reads.load(Ordering::SeqCst));
+    }
+}
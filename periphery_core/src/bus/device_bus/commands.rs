@@ -6,6 +6,8 @@ use bus::*;
 use register::*;
 use system::*;
 
+use terminal_cli::*;
+
 pub struct ChipCommand<'a, T, B: 'a> where B: DeviceCommandBus {
 	pub cmd: u8,
 	pub arg_size_bytes: usize,
@@ -37,43 +39,6 @@ impl<'a, T, B: 'a> ChipCommand<'a, T, B> where B: DeviceCommandBus, T: Default {
 }
 
 impl<'a, T, B: 'a> ChipCommand<'a, T, B> where B: DeviceCommandBus, T: Register {
-	/*
-	pub fn execute_args_from_u8(&self, t: &mut CharacterTerminalWriter, input: &str) -> fmt::Result {		
-		match ::buspirate::parse_u8_array(input) {
-			Ok(a) => {
-				if a.len() != self.arg_size_bytes as usize {
-					return write!(t, "Expected {} bytes, parsed {:?}!\r\n", self.arg_size_bytes, a);
-				} else {
-					try!(write!(t, "Raw bytes: {:?}.", a));
-
-					match T::from_register_value(&a) {
-						Ok(arg) => {
-							try!(write!(t, " Parsed argument: {:?} ", a));
-
-							match self.execute_args(arg) {
-								Ok(_) => {
-									try!(write!(t, " Executed.\r\n"));
-								},
-								Err(e) => {
-									try!(write!(t, " Error executing: {:?}\r\n", e));
-								}
-							}
-						},
-						Err(e) => {
-							try!(write!(t, " Error parsing value: {:?}\r\n", e));
-						}
-					}
-
-					return Ok(());
-				}
-			},
-			Err(e) => {
-				write!(t, "Error parsing byte arguments: {:?}\r\n", e)
-			}
-		}
-	}
-	*/
-
 	pub fn execute_args(&self, args: T) -> Result<(), PeripheryError> {
 		let mut buf = vec![0; self.arg_size_bytes + 1];
 		buf[0] = self.cmd;
@@ -131,110 +96,116 @@ impl<'a, T, B: 'a> ChipCommand<'a, T, B> where B: DeviceCommandBus, T: Register
 	
 }
 
-#[macro_export]
-macro_rules! commands {
-    (
-    	$(#[$chip_docs:meta])*
-    	chip $chip: ident
-    	{$
-    		(
-    			$(#[$attr:meta])*
-    			command [$address: expr; $size_bytes: expr] => $name: ident : $T: ty
-    		),+
-    	}
-
-    ) => (
-
-		$(#[$chip_docs:meta])*
-		#[derive(Clone)]
-		pub struct $chip<'a, B: 'a> where B: DeviceCommandBus {
-			command_bus: &'a B
-		}
+impl<'a, T, B: 'a> ChipCommand<'a, T, B> where B: DeviceCommandBus, T: Register + Debug {
+	/// Parse the argument from a list of bytes, as accepted by the register write
+	/// command (`0x40` or `0, 127`), and execute the command with it.
+	pub fn execute_args_from_u8(&self, t: &mut CharacterTerminalWriter, input: &str) -> fmt::Result {
+		let mut echo = String::new();
+		self.execute_args_from_str(&mut echo, input)?;
+		write!(t, "{}", echo)
+	}
 
-		impl<'a, B> $chip<'a, B> where B: DeviceCommandBus {
-			#[inline]
-			pub fn new(command_bus: &'a B) -> Self {
-				$chip {
-					command_bus: command_bus
+	/// Same as `execute_args_from_u8`, the echo goes to any writer.
+	pub fn execute_args_from_str<W: FmtWrite>(&self, t: &mut W, input: &str) -> fmt::Result {
+		match ::periphery_buspirate_parser::parse_u8_array(input) {
+			Ok(a) => {
+				if a.len() != self.arg_size_bytes {
+					return write!(t, "Expected {} bytes, parsed {:?}!\r\n", self.arg_size_bytes, a);
 				}
-			}
 
-			$(
-				$(#[$attr])*
-				#[inline]				
-				pub fn $name(&self) -> ChipCommand<$T, B> {
-					ChipCommand {
-						cmd: $address,
-						arg_size_bytes: $size_bytes,
-						command_bus: &self.command_bus,
-						_arg_type: PhantomData::<$T>
-					}
-				}
-			)+			
-		}
+				try!(write!(t, "Raw bytes: {:?}.", a));
 
+				match T::from_register_value(&a) {
+					Ok(arg) => {
+						try!(write!(t, " Parsed argument: {:?}.", arg));
 
-		impl<'a, B> BusCommandsCli for $chip<'a, B> where B: DeviceCommandBus {
-			fn commands_cli<'b>(&self, exec: &mut periphery_core::terminal_cli::PrefixedExecutor) {
-    			//use $crate::base::*;
-    			//use $crate::buspirate::*;
-				use periphery_core::terminal_cli::*;
-    			
-				/*
-				let cmd = format!("{}/list_commands", prefix);
-				if let Some(mut ctx) = exec.command(&cmd) {
-					write!(ctx.get_terminal(), "{}", &self);
+						match self.execute_args(arg) {
+							Ok(_) => {
+								try!(write!(t, " Executed.\r\n"));
+							},
+							Err(e) => {
+								try!(write!(t, " Error executing: {:?}\r\n", e));
+							}
+						}
+					},
+					Err(e) => {
+						try!(write!(t, " Error parsing value: {:?}\r\n", e));
+					}
 				}
-				*/
 
-				$(
-					let name = stringify!($name);
+				Ok(())
+			},
+			Err(e) => {
+				write!(t, "Error parsing byte arguments: {:?}\r\n", e)
+			}
+		}
+	}
+}
 
-					let exec_cmd = format!("command/{}/execute", name);
-					if let Some(mut ctx) = exec.command(&exec_cmd) {
-						let res = self.$name().execute();
-						match res {
-							Ok(_) => { write!(ctx.get_terminal(), "Command executed.\r\n"); }
-							Err(e) => { write!(ctx.get_terminal(), "Error: {:?}", e); }
-						};
-					}
 
-					/*
-					let exec_cmd_args = format!("{}/command/{}/execute_args ", prefix, name);
-					if let Some(mut ctx) = exec.command(&exec_cmd_args) {
-						let args = ctx.get_args().to_string();
-						let args = args.trim();
-						let res = self.$name().execute_args_from_u8(ctx.get_terminal(), &args);
-						match res {
-							Ok(_) => { write!(ctx.get_terminal(), "Command executed.\r\n"); }
-							Err(e) => { write!(ctx.get_terminal(), "Error: {:?}", e); }
-						};
-					}
-					*/
+#[cfg(test)]
+mod tests {
+	use super::*;
 
-					/*
-					let write_cmd = format!("{}/commands/execute_args ", prefix, name);
-					if let Some(mut ctx) = exec.command(&write_cmd) {
-						let args = ctx.get_args().to_string();
-						&self.$name().write_from_u8(ctx.get_terminal(), &args);
-					}
-					*/
-				)+
+	#[derive(Copy, Clone, Debug, Default, PartialEq)]
+	struct AddressingRange {
+		start: u8,
+		end: u8
+	}
 
-				/*
+	impl Register for AddressingRange {
+		fn to_register_value(&self, output: &mut [u8]) -> Result<(), PeripheryError> {
+			if output.len() != 2 {
+				return Err(PeripheryError::RegisterSizeMismatch);
+			}
+			output[0] = self.start;
+			output[1] = self.end;
+			Ok(())
+		}
 
-				match () {
-					#[not(cfg(feature="debug_registers"))]
-					() => (),
-					#[cfg(feature="debug_registers")]
-					() => {
-						
-					}
-				} 
-				*/   			
-    			
-    		}
-		}		
+		fn from_register_value(value: &[u8]) -> Result<Self, PeripheryError> {
+			if value.len() != 2 {
+				return Err(PeripheryError::RegisterSizeMismatch);
+			}
+			Ok(AddressingRange { start: value[0], end: value[1] })
+		}
+	}
+
+	fn command<'a, T>(bus: &'a ExpectationBus, cmd: u8, arg_size_bytes: usize) -> ChipCommand<'a, T, ExpectationBus> {
+		ChipCommand {
+			cmd: cmd,
+			arg_size_bytes: arg_size_bytes,
+			command_bus: bus,
+			_arg_type: PhantomData
+		}
+	}
 
-    )
+	#[test]
+	fn test_execute_args_from_str() {
+		let bus = ExpectationBus::new();
+		let set_contrast = command::<u8>(&bus, 0x81, 1);
+		let column_address = command::<AddressingRange>(&bus, 0x21, 2);
+
+		bus.expect_command(&[0x81, 0x40])
+		   .expect_command(&[0x21, 0x00, 0x7F]);
+
+		let mut echo = String::new();
+		set_contrast.execute_args_from_str(&mut echo, "0x40").unwrap();
+		assert_eq!("Raw bytes: [64]. Parsed argument: 64. Executed.\r\n", echo);
+
+		let mut echo = String::new();
+		column_address.execute_args_from_str(&mut echo, "0, 127").unwrap();
+		assert_eq!("Raw bytes: [0, 127]. Parsed argument: AddressingRange { start: 0, end: 127 }. Executed.\r\n", echo);
+		bus.done();
+
+		// nothing is sent with the wrong number of bytes or an unparsable argument
+		let mut echo = String::new();
+		set_contrast.execute_args_from_str(&mut echo, "0, 127").unwrap();
+		assert_eq!("Expected 1 bytes, parsed [0, 127]!\r\n", echo);
+
+		let mut echo = String::new();
+		column_address.execute_args_from_str(&mut echo, "0, x").unwrap();
+		assert!(echo.starts_with("Error parsing byte arguments"));
+		bus.done();
+	}
 }
@@ -10,6 +10,13 @@ pub trait DeviceRegisterBus : Send + Sync {
     
 }
 
+/// A register bus that keeps shadow copies of the register values it has written.
+pub trait DeviceRegisterShadow {
+    /// Copy the shadowed register value into `data`. Fails with `DataNotAvailable` if the
+    /// register isn't shadowed or its value isn't known yet.
+    fn read_shadow(&self, register: u8, data: &mut [u8]) -> Result<(), PeripheryError>;
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct DeviceRegisterBusNotImplemented;
 impl DeviceRegisterBus for DeviceRegisterBusNotImplemented {
@@ -0,0 +1,343 @@
+//! Scripted device bus for driver tests. The test lists the register reads and writes,
+//! commands and data transfers the driver is expected to perform, together with the
+//! responses of the chip. Any deviation fails the bus operation and is reported with
+//! a transaction diff by `done()`.
+//!
+//! ```ignore
+//! let bus = ExpectationBus::new();
+//! bus.expect_write(0xF4, &[0x33])
+//!    .expect_write(0xF5, &[0x04]);
+//!
+//! let sensor = Bmp280 { bus: bus.clone() };
+//! sensor.init_after_detection().unwrap();
+//!
+//! bus.done();
+//! ```
+
+use prelude::v1::*;
+use base::*;
+use bus::*;
+use system::*;
+
+use std::sync::Mutex;
+
+/// A transaction the driver is expected to perform, with the chip's response.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Transaction {
+	RegisterRead { register: u8, response: Vec<u8> },
+	RegisterWrite { register: u8, data: Vec<u8> },
+	Command { data: Vec<u8> },
+	Transmit { data: Vec<u8> },
+	Receive { response: Vec<u8> }
+}
+
+/// A transaction that was actually performed by the driver.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BusAccess {
+	RegisterRead { register: u8, len: usize },
+	RegisterWrite { register: u8, data: Vec<u8> },
+	Command { data: Vec<u8> },
+	Transmit { data: Vec<u8> },
+	Receive { len: usize }
+}
+
+impl Transaction {
+	pub fn matches(&self, access: &BusAccess) -> bool {
+		match (self, access) {
+			(&Transaction::RegisterRead { register, ref response }, &BusAccess::RegisterRead { register: r, len }) => {
+				register == r && response.len() == len
+			},
+			(&Transaction::RegisterWrite { register, ref data }, &BusAccess::RegisterWrite { register: r, data: ref d }) => {
+				register == r && data == d
+			},
+			(&Transaction::Command { ref data }, &BusAccess::Command { data: ref d }) => data == d,
+			(&Transaction::Transmit { ref data }, &BusAccess::Transmit { data: ref d }) => data == d,
+			(&Transaction::Receive { ref response }, &BusAccess::Receive { len }) => response.len() == len,
+			_ => false
+		}
+	}
+}
+
+impl Display for Transaction {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match *self {
+			Transaction::RegisterRead { register, ref response } => write!(f, "read [0x{:X}; {}] => {:X?}", register, response.len(), response),
+			Transaction::RegisterWrite { register, ref data } => write!(f, "write [0x{:X}; {}] <= {:X?}", register, data.len(), data),
+			Transaction::Command { ref data } => write!(f, "command {:X?}", data),
+			Transaction::Transmit { ref data } => write!(f, "transmit {:X?}", data),
+			Transaction::Receive { ref response } => write!(f, "receive [{}] => {:X?}", response.len(), response)
+		}
+	}
+}
+
+impl Display for BusAccess {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match *self {
+			BusAccess::RegisterRead { register, len } => write!(f, "read [0x{:X}; {}]", register, len),
+			BusAccess::RegisterWrite { register, ref data } => write!(f, "write [0x{:X}; {}] <= {:X?}", register, data.len(), data),
+			BusAccess::Command { ref data } => write!(f, "command {:X?}", data),
+			BusAccess::Transmit { ref data } => write!(f, "transmit {:X?}", data),
+			BusAccess::Receive { len } => write!(f, "receive [{}]", len)
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+struct Expectation {
+	transaction: Transaction,
+	error: Option<PeripheryError>
+}
+
+#[derive(Default)]
+struct ExpectationState {
+	expected: Vec<Expectation>,
+	performed: Vec<BusAccess>,
+	failed: bool
+}
+
+/// Implements `DeviceRegisterBus`, `DeviceCommandBus` and `DeviceDataTransfer`. Clones
+/// share the same script, so the test can keep one to verify the transactions after
+/// the driver has taken ownership of the bus.
+#[derive(Clone)]
+pub struct ExpectationBus {
+	state: Arc<Mutex<ExpectationState>>
+}
+
+impl ExpectationBus {
+	pub fn new() -> Self {
+		ExpectationBus {
+			state: Arc::new(Mutex::new(ExpectationState::default()))
+		}
+	}
+
+	pub fn expect(&self, transaction: Transaction) -> &Self {
+		if let Ok(mut state) = self.state.lock() {
+			state.expected.push(Expectation {
+				transaction: transaction,
+				error: None
+			});
+		}
+		self
+	}
+
+	/// The chip responds with `response`, its length is the expected read length.
+	pub fn expect_read(&self, register: u8, response: &[u8]) -> &Self {
+		self.expect(Transaction::RegisterRead { register: register, response: response.into() })
+	}
+
+	pub fn expect_write(&self, register: u8, data: &[u8]) -> &Self {
+		self.expect(Transaction::RegisterWrite { register: register, data: data.into() })
+	}
+
+	pub fn expect_command(&self, data: &[u8]) -> &Self {
+		self.expect(Transaction::Command { data: data.into() })
+	}
+
+	pub fn expect_transmit(&self, data: &[u8]) -> &Self {
+		self.expect(Transaction::Transmit { data: data.into() })
+	}
+
+	pub fn expect_receive(&self, response: &[u8]) -> &Self {
+		self.expect(Transaction::Receive { response: response.into() })
+	}
+
+	/// The last expected transaction is matched, but the bus returns this error.
+	pub fn fail_with(&self, error: PeripheryError) -> &Self {
+		if let Ok(mut state) = self.state.lock() {
+			if let Some(last) = state.expected.last_mut() {
+				last.error = Some(error);
+			}
+		}
+		self
+	}
+
+	/// All the transactions performed so far.
+	pub fn get_performed(&self) -> Vec<BusAccess> {
+		match self.state.lock() {
+			Ok(state) => state.performed.clone(),
+			Err(_) => vec![]
+		}
+	}
+
+	/// Check that exactly the expected transactions were performed, in order. The error
+	/// contains the diff between the expected and the performed transactions.
+	pub fn verify(&self) -> Result<(), String> {
+		let state = self.state.lock().map_err(|_| "The expectation bus lock is poisoned".to_string())?;
+
+		let complete = state.performed.len() == state.expected.len();
+		if complete && !state.failed {
+			return Ok(());
+		}
+
+		let mut report = String::new();
+		let _ = write!(report, "Bus transactions don't match the expectations, {} expected, {} performed:\r\n", state.expected.len(), state.performed.len());
+
+		for i in 0..max(state.expected.len(), state.performed.len()) {
+			match (state.expected.get(i), state.performed.get(i)) {
+				(Some(e), Some(p)) if e.transaction.matches(p) => {
+					let _ = write!(report, "  #{:<3}   {}\r\n", i, e.transaction);
+				},
+				(Some(e), Some(p)) => {
+					let _ = write!(report, "  #{:<3} - {}\r\n", i, e.transaction);
+					let _ = write!(report, "  #{:<3} + {}\r\n", i, p);
+				},
+				(Some(e), None) => {
+					let _ = write!(report, "  #{:<3} - {}\r\n", i, e.transaction);
+				},
+				(None, Some(p)) => {
+					let _ = write!(report, "  #{:<3} + {}\r\n", i, p);
+				},
+				(None, None) => ()
+			}
+		}
+
+		Err(report)
+	}
+
+	/// Panics with the transaction diff if the driver deviated from the script or didn't
+	/// perform all the expected transactions.
+	pub fn done(&self) {
+		if let Err(report) = self.verify() {
+			panic!("{}", report);
+		}
+	}
+
+	/// Match the next expected transaction. Returns the response of the chip.
+	fn perform(&self, access: BusAccess) -> Result<Vec<u8>, PeripheryError> {
+		let mut state = self.state.lock().map_err(|_| PeripheryError::LockingError)?;
+
+		let idx = state.performed.len();
+		state.performed.push(access.clone());
+
+		// after the first deviation the rest of the script is meaningless
+		if state.failed {
+			return Err(PeripheryError::UnexpectedBusTransaction);
+		}
+
+		let expectation = match state.expected.get(idx) {
+			Some(e) if e.transaction.matches(&access) => e.clone(),
+			_ => {
+				state.failed = true;
+				return Err(PeripheryError::UnexpectedBusTransaction);
+			}
+		};
+
+		if let Some(error) = expectation.error {
+			return Err(error);
+		}
+
+		match expectation.transaction {
+			Transaction::RegisterRead { response, .. } | Transaction::Receive { response } => Ok(response),
+			_ => Ok(vec![])
+		}
+	}
+}
+
+impl DeviceRegisterBus for ExpectationBus {
+	fn read_from_register(&self, register: u8, data: &mut [u8]) -> Result<(), PeripheryError> {
+		let response = self.perform(BusAccess::RegisterRead { register: register, len: data.len() })?;
+		data.copy_from_slice(&response);
+		Ok(())
+	}
+
+	fn write_to_register(&self, register: u8, data: &[u8]) -> Result<(), PeripheryError> {
+		self.perform(BusAccess::RegisterWrite { register: register, data: data.into() })?;
+		Ok(())
+	}
+}
+
+impl DeviceCommandBus for ExpectationBus {
+	fn execute_command(&self, data: &[u8]) -> Result<(), PeripheryError> {
+		self.perform(BusAccess::Command { data: data.into() })?;
+		Ok(())
+	}
+}
+
+impl DeviceDataTransfer for ExpectationBus {
+	fn transmit(&self, data: &[u8]) -> Result<(), PeripheryError> {
+		self.perform(BusAccess::Transmit { data: data.into() })?;
+		Ok(())
+	}
+
+	fn receive(&self, data: &mut [u8]) -> Result<(), PeripheryError> {
+		let response = self.perform(BusAccess::Receive { len: data.len() })?;
+		data.copy_from_slice(&response);
+		Ok(())
+	}
+}
+
+
+/// The system of a driver under test, without sleep or debug output. Its clock always
+/// reads 1000 us, for pollers that timestamp their samples.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct TestSystemApi;
+
+impl SystemApi for TestSystemApi { }
+
+impl SystemApiClock for TestSystemApi {
+	fn get_timestamp_us(&self) -> u64 {
+		1000
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_expectation_bus() {
+		let bus = ExpectationBus::new();
+		bus.expect_read(0xD0, &[0x58])
+		   .expect_write(0xF4, &[0x33])
+		   .expect_command(&[0xAF])
+		   .expect_receive(&[0x01, 0x02])
+		   .expect_transmit(&[0x40, 0x00]).fail_with(PeripheryError::WriteError);
+
+		let mut id = [0];
+		bus.read_from_register(0xD0, &mut id).unwrap();
+		assert_eq!([0x58], id);
+		bus.write_to_register(0xF4, &[0x33]).unwrap();
+		bus.execute_command(&[0xAF]).unwrap();
+		let mut data = [0; 2];
+		bus.receive(&mut data).unwrap();
+		assert_eq!([0x01, 0x02], data);
+		assert!(bus.transmit(&[0x40, 0x00]).is_err());
+
+		bus.done();
+	}
+
+	#[test]
+	fn test_expectation_bus_deviation() {
+		let bus = ExpectationBus::new();
+		bus.expect_write(0xF4, &[0x33])
+		   .expect_write(0xF5, &[0x04]);
+
+		bus.write_to_register(0xF4, &[0x33]).unwrap();
+		assert!(bus.write_to_register(0xF5, &[0x00]).is_err());
+
+		let report = bus.verify().unwrap_err();
+		assert!(report.contains("  #1   - write [0xF5; 1] <= [4]"));
+		assert!(report.contains("  #1   + write [0xF5; 1] <= [0]"));
+
+		// a read of the wrong length
+		let bus = ExpectationBus::new();
+		bus.expect_read(0x88, &[0; 24]);
+		let mut data = [0; 2];
+		assert!(bus.read_from_register(0x88, &mut data).is_err());
+		assert!(bus.verify().is_err());
+
+		// missing transactions
+		let bus = ExpectationBus::new();
+		bus.expect_command(&[0xAE]);
+		assert!(bus.verify().is_err());
+	}
+
+	#[test]
+	#[should_panic]
+	fn test_expectation_bus_done() {
+		let bus = ExpectationBus::new();
+		bus.expect_command(&[0xAE]);
+		bus.execute_command(&[0xAF]);
+		bus.done();
+	}
+}
@@ -1,5 +1,6 @@
-//! Traits for a particular device connected to a bus. Macros and abstractions to deal
-//! with registers or commands for a device.
+//! Traits for a particular device connected to a bus. Abstractions to deal with
+//! registers or commands for a device, the chips themselves are generated by the
+//! `#[register_map]` and `#[command_map]` attributes of `periphery_core_codegen`.
 
 use prelude::v1::*;
 use base::*;
@@ -9,6 +10,12 @@ use system::*;
 
 pub mod commands;
 pub mod registers;
+pub mod snapshot;
+pub mod register_map;
+#[cfg(feature = "std")]
+pub mod cached;
+#[cfg(feature = "std")]
+pub mod expectations;
 
 pub mod device;
 
@@ -0,0 +1,388 @@
+//! A machine-readable description of a chip's registers and commands, generated from
+//! the `#[register_map]` and `#[command_map]` definitions. Exported as JSON for documentation,
+//! logic-analyzer decoders and external tools.
+
+use prelude::v1::*;
+use base::*;
+use register::*;
+use register_fields::*;
+use bus::device_bus::snapshot::*;
+use utils::json::JsonStr;
+
+/// A single field of a register.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisterFieldMap {
+	pub name: &'static str,
+	pub kind: RegisterFieldKind,
+	/// Bits used by the field, for every byte of the register in bus order.
+	pub mask: Vec<u8>
+}
+
+impl RegisterFieldMap {
+	/// Bit positions of the field, counting from the least significant bit of the
+	/// last byte on the bus. Sorted from the most significant bit.
+	pub fn bits(&self) -> Vec<usize> {
+		let mut ret = vec![];
+		let len = self.mask.len();
+		for (i, b) in self.mask.iter().enumerate() {
+			for bit in (0..8).rev() {
+				if b & (1 << bit) != 0 {
+					ret.push((len - 1 - i) * 8 + bit);
+				}
+			}
+		}
+		ret
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisterMapEntry {
+	pub name: &'static str,
+	pub address: u8,
+	pub size_bytes: usize,
+	pub access: &'static str,
+	pub ty: &'static str,
+	/// Only available for registers annotated with `fields`.
+	pub fields: Option<Vec<RegisterFieldMap>>,
+	/// Value after a power-on reset, in bus order.
+	pub reset: Option<Vec<u8>>,
+	/// Changed by the chip itself, shouldn't be cached.
+	pub volatile: bool,
+	pub doc: Option<&'static str>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandMapEntry {
+	pub name: &'static str,
+	pub command: u8,
+	pub arg_size_bytes: usize,
+	pub arg_ty: &'static str,
+	pub doc: Option<&'static str>
+}
+
+/// Registers and commands of a chip.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisterMap {
+	pub chip: Cow<'static, str>,
+	pub registers: Vec<RegisterMapEntry>,
+	pub commands: Vec<CommandMapEntry>
+}
+
+impl RegisterMap {
+	pub fn new<C: Into<Cow<'static, str>>>(chip: C) -> Self {
+		RegisterMap {
+			chip: chip.into(),
+			registers: vec![],
+			commands: vec![]
+		}
+	}
+
+	/// Combine the register and the command maps of a single device.
+	pub fn merge(&mut self, other: RegisterMap) {
+		if self.chip.len() == 0 {
+			self.chip = other.chip;
+		} else if other.chip.len() > 0 && self.chip != other.chip {
+			self.chip = format!("{}+{}", self.chip, other.chip).into();
+		}
+		self.registers.extend(other.registers);
+		self.commands.extend(other.commands);
+	}
+
+	/// Reset values of the registers that have them, as a snapshot. Diff it with a
+	/// snapshot of the chip to find the registers changed since the power-on.
+	pub fn reset_snapshot(&self) -> RegisterSnapshot {
+		let mut snapshot = RegisterSnapshot::new(self.chip.clone());
+		for r in &self.registers {
+			if let Some(ref reset) = r.reset {
+				snapshot.registers.push(RegisterSnapshotEntry {
+					name: r.name.into(),
+					address: r.address,
+					size_bytes: r.size_bytes,
+					value: Some(reset.clone())
+				});
+			}
+		}
+		snapshot
+	}
+
+	pub fn to_json(&self) -> String {
+		let mut s = String::new();
+		self.write_json(&mut s).ok();
+		s
+	}
+
+	pub fn write_json<W: FmtWrite>(&self, w: &mut W) -> fmt::Result {
+		try!(write!(w, "{{\"chip\":{},\"registers\":[", JsonStr(&self.chip)));
+		for (i, r) in self.registers.iter().enumerate() {
+			if i > 0 { try!(write!(w, ",")); }
+			try!(write!(w, "{{\"name\":{},\"address\":{},\"size_bytes\":{},\"access\":{},\"type\":{}",
+				JsonStr(r.name), r.address, r.size_bytes, JsonStr(r.access), JsonStr(r.ty)));
+
+			if r.volatile {
+				try!(write!(w, ",\"volatile\":true"));
+			}
+			if let Some(ref reset) = r.reset {
+				try!(write!(w, ",\"reset\":["));
+				for (j, b) in reset.iter().enumerate() {
+					if j > 0 { try!(write!(w, ",")); }
+					try!(write!(w, "{}", b));
+				}
+				try!(write!(w, "]"));
+			}
+			if let Some(doc) = r.doc {
+				try!(write!(w, ",\"doc\":{}", JsonStr(doc)));
+			}
+
+			if let Some(ref fields) = r.fields {
+				try!(write!(w, ",\"fields\":["));
+				for (j, f) in fields.iter().enumerate() {
+					if j > 0 { try!(write!(w, ",")); }
+					try!(write_field_json(w, f));
+				}
+				try!(write!(w, "]"));
+			}
+
+			try!(write!(w, "}}"));
+		}
+		try!(write!(w, "],\"commands\":["));
+		for (i, c) in self.commands.iter().enumerate() {
+			if i > 0 { try!(write!(w, ",")); }
+			try!(write!(w, "{{\"name\":{},\"command\":{},\"arg_size_bytes\":{},\"arg_type\":{}",
+				JsonStr(c.name), c.command, c.arg_size_bytes, JsonStr(c.arg_ty)));
+			if let Some(doc) = c.doc {
+				try!(write!(w, ",\"doc\":{}", JsonStr(doc)));
+			}
+			try!(write!(w, "}}"));
+		}
+		write!(w, "]}}")
+	}
+}
+
+fn write_field_json<W: FmtWrite>(w: &mut W, f: &RegisterFieldMap) -> fmt::Result {
+	try!(write!(w, "{{\"name\":{},\"kind\":{},\"mask\":[", JsonStr(f.name), JsonStr(f.kind.name())));
+	for (i, b) in f.mask.iter().enumerate() {
+		if i > 0 { try!(write!(w, ",")); }
+		try!(write!(w, "{}", b));
+	}
+	try!(write!(w, "],\"bits\":["));
+	for (i, b) in f.bits().iter().enumerate() {
+		if i > 0 { try!(write!(w, ",")); }
+		try!(write!(w, "{}", b));
+	}
+	try!(write!(w, "]"));
+
+	match f.kind {
+		RegisterFieldKind::Unsigned { bits } | RegisterFieldKind::Signed { bits } => {
+			try!(write!(w, ",\"width\":{}", bits));
+		},
+		RegisterFieldKind::Enum { ref variants } => {
+			try!(write!(w, ",\"values\":["));
+			for (i, &(ref name, value)) in variants.iter().enumerate() {
+				if i > 0 { try!(write!(w, ",")); }
+				try!(write!(w, "{{\"name\":{},\"value\":{}}}", JsonStr(name), value));
+			}
+			try!(write!(w, "]"));
+		},
+		RegisterFieldKind::Bool => ()
+	}
+
+	write!(w, "}}")
+}
+
+/// Discover the bit layout of every field by setting it to a few values and looking
+/// at which bits of the packed register change. Returns `None` if the register
+/// can't be unpacked from all zeros.
+pub fn register_field_map<T>(size_bytes: usize) -> Option<Vec<RegisterFieldMap>> where T: Register + RegisterFields {
+	let zeros = vec![0; size_bytes];
+	let base = match T::from_register_value(&zeros) {
+		Ok(r) => r,
+		Err(_) => { return None; }
+	};
+	let mut base_packed = vec![0; size_bytes];
+	if base.to_register_value(&mut base_packed).is_err() {
+		return None;
+	}
+
+	let mut ret = vec![];
+	for name in T::field_names() {
+		let kind = match base.get_field_kind(name) {
+			Ok(k) => k,
+			Err(_) => { continue; }
+		};
+
+		let mut mask = vec![0; size_bytes];
+		let mut packed = vec![0; size_bytes];
+		for value in kind.probe_values() {
+			let mut r = match T::from_register_value(&base_packed) {
+				Ok(r) => r,
+				Err(_) => { continue; }
+			};
+			if r.set_field(name, &value).is_err() { continue; }
+			if r.to_register_value(&mut packed).is_err() { continue; }
+
+			for i in 0..size_bytes {
+				mask[i] |= packed[i] ^ base_packed[i];
+			}
+		}
+
+		ret.push(RegisterFieldMap {
+			name: *name,
+			kind: kind,
+			mask: mask
+		});
+	}
+
+	Some(ret)
+}
+
+/// Bits that are kept by the packed register type, but aren't covered by any of its
+/// named fields. Every bit is set on its own, the ones that survive the unpacking and
+/// packing belong to a field of the type. Empty when the field table is complete,
+/// `None` if the field layout can't be discovered.
+pub fn unmapped_register_bits<T>(size_bytes: usize) -> Option<Vec<usize>> where T: Register + RegisterFields {
+	let fields = match register_field_map::<T>(size_bytes) {
+		Some(fields) => fields,
+		None => { return None; }
+	};
+
+	let mut mapped = vec![0; size_bytes];
+	for f in &fields {
+		for i in 0..size_bytes {
+			mapped[i] |= f.mask[i];
+		}
+	}
+
+	let mut ret = vec![];
+	let mut packed = vec![0; size_bytes];
+	for i in 0..size_bytes {
+		for bit in (0..8).rev() {
+			let mut raw = vec![0; size_bytes];
+			raw[i] = 1 << bit;
+
+			// not a valid value of an enum field, can't tell
+			let r = match T::from_register_value(&raw) {
+				Ok(r) => r,
+				Err(_) => { continue; }
+			};
+			if r.to_register_value(&mut packed).is_err() { continue; }
+
+			if (packed[i] & !mapped[i]) & (1 << bit) != 0 {
+				ret.push((size_bytes - 1 - i) * 8 + bit);
+			}
+		}
+	}
+
+	Some(ret)
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Debug, Copy, Clone, PartialEq)]
+	struct SplitRegister {
+		high: u8,
+		enabled: bool
+	}
+
+	impl Register for SplitRegister {
+		fn to_register_value(&self, output: &mut [u8]) -> Result<(), PeripheryError> {
+			output[0] = (self.high << 4) | (if self.enabled { 1 } else { 0 });
+			Ok(())
+		}
+
+		fn from_register_value(value: &[u8]) -> Result<Self, PeripheryError> {
+			Ok(SplitRegister { high: value[0] >> 4, enabled: value[0] & 1 == 1 })
+		}
+	}
+
+	impl RegisterFields for SplitRegister {
+		fn field_names() -> &'static [&'static str] {
+			&["high", "enabled"]
+		}
+
+		fn get_field(&self, field: &str) -> Result<String, PeripheryError> {
+			Err(PeripheryError::NotImplemented)
+		}
+
+		fn set_field(&mut self, field: &str, value: &str) -> Result<(), PeripheryError> {
+			match field {
+				"high" => { self.high = u8::parse_field(value)? & 0x0F; },
+				"enabled" => { self.enabled = bool::parse_field(value)?; },
+				_ => { return Err(PeripheryError::UnknownRegisterField); }
+			}
+			Ok(())
+		}
+
+		fn get_field_kind(&self, field: &str) -> Result<RegisterFieldKind, PeripheryError> {
+			match field {
+				"high" => Ok(RegisterFieldKind::Unsigned { bits: 4 }),
+				"enabled" => Ok(RegisterFieldKind::Bool),
+				_ => Err(PeripheryError::UnknownRegisterField)
+			}
+		}
+	}
+
+	/// The same layout, with the `enabled` field missing from the field table.
+	#[derive(Debug, Copy, Clone, PartialEq)]
+	struct IncompleteRegister(SplitRegister);
+
+	impl Register for IncompleteRegister {
+		fn to_register_value(&self, output: &mut [u8]) -> Result<(), PeripheryError> {
+			self.0.to_register_value(output)
+		}
+
+		fn from_register_value(value: &[u8]) -> Result<Self, PeripheryError> {
+			Ok(IncompleteRegister(SplitRegister::from_register_value(value)?))
+		}
+	}
+
+	impl RegisterFields for IncompleteRegister {
+		fn field_names() -> &'static [&'static str] {
+			&["high"]
+		}
+
+		fn get_field(&self, field: &str) -> Result<String, PeripheryError> {
+			self.0.get_field(field)
+		}
+
+		fn set_field(&mut self, field: &str, value: &str) -> Result<(), PeripheryError> {
+			self.0.set_field(field, value)
+		}
+
+		fn get_field_kind(&self, field: &str) -> Result<RegisterFieldKind, PeripheryError> {
+			self.0.get_field_kind(field)
+		}
+	}
+
+	#[test]
+	fn test_unmapped_register_bits() {
+		assert_eq!(Some(vec![]), unmapped_register_bits::<SplitRegister>(1));
+		assert_eq!(Some(vec![0]), unmapped_register_bits::<IncompleteRegister>(1));
+	}
+
+	#[test]
+	fn test_register_field_map() {
+		let fields = register_field_map::<SplitRegister>(1).unwrap();
+		assert_eq!(2, fields.len());
+		assert_eq!(vec![0xF0], fields[0].mask);
+		assert_eq!(vec![7, 6, 5, 4], fields[0].bits());
+		assert_eq!(vec![0], fields[1].bits());
+
+		let mut map = RegisterMap::new("Test\"Chip");
+		map.registers.push(RegisterMapEntry {
+			name: "split", address: 0x10, size_bytes: 1, access: "rw", ty: "SplitRegister", fields: Some(fields),
+			reset: Some(vec![0x01]), volatile: false, doc: None
+		});
+		map.commands.push(CommandMapEntry { name: "reset", command: 0xB6, arg_size_bytes: 0, arg_ty: "EmptyReg", doc: None });
+
+		let reset = map.reset_snapshot();
+		assert_eq!(Some(&[0x01][..]), reset.get_value("split"));
+
+		assert_eq!(
+			"{\"chip\":\"Test\\\"Chip\",\"registers\":[{\"name\":\"split\",\"address\":16,\"size_bytes\":1,\"access\":\"rw\",\"type\":\"SplitRegister\",\"reset\":[1],\"fields\":[{\"name\":\"high\",\"kind\":\"unsigned\",\"mask\":[240],\"bits\":[7,6,5,4],\"width\":4},{\"name\":\"enabled\",\"kind\":\"bool\",\"mask\":[1],\"bits\":[0]}]}],\"commands\":[{\"name\":\"reset\",\"command\":182,\"arg_size_bytes\":0,\"arg_type\":\"EmptyReg\"}]}",
+			map.to_json()
+		);
+	}
+}
@@ -4,30 +4,113 @@ use prelude::v1::*;
 use base::*;
 use bus::*;
 use register::*;
+use register_fields::*;
 use system::*;
 
-pub struct RegisterAddress<'a, T, B: 'a> where B: DeviceRegisterBus {
+pub struct RegisterAddress<'a, T, B: 'a, A = ReadWrite> where B: DeviceRegisterBus {
 	pub address: u8,
 	pub size_bytes: usize,
 	pub register_bus: &'a B,
-	pub _register_type: PhantomData<T>
+	pub _register_type: PhantomData<T>,
+	pub _access: PhantomData<A>
 }
 
+/// Access mode of a register, enforced at compile time.
+pub trait RegisterAccess {
+	const READABLE: bool;
+	const WRITABLE: bool;
+	const NAME: &'static str;
+}
+
+/// Access modes that allow reading the register.
+pub trait RegisterReadable: RegisterAccess { }
+/// Access modes that allow writing to the register.
+pub trait RegisterWritable: RegisterAccess { }
+
+/// Status and data registers.
+#[derive(Copy, Clone, Debug)]
+pub struct ReadOnly;
+impl RegisterAccess for ReadOnly {
+	const READABLE: bool = true;
+	const WRITABLE: bool = false;
+	const NAME: &'static str = "ro";
+}
+impl RegisterReadable for ReadOnly { }
+
+/// Command and reset registers.
+#[derive(Copy, Clone, Debug)]
+pub struct WriteOnly;
+impl RegisterAccess for WriteOnly {
+	const READABLE: bool = false;
+	const WRITABLE: bool = true;
+	const NAME: &'static str = "wo";
+}
+impl RegisterWritable for WriteOnly { }
+
+/// Configuration registers.
+#[derive(Copy, Clone, Debug)]
+pub struct ReadWrite;
+impl RegisterAccess for ReadWrite {
+	const READABLE: bool = true;
+	const WRITABLE: bool = true;
+	const NAME: &'static str = "rw";
+}
+impl RegisterReadable for ReadWrite { }
+impl RegisterWritable for ReadWrite { }
+
 #[derive(Clone, Debug)]
 pub struct RegisterParseError {
-	buffer: [u8; 32],
-	size: usize
+	buffer: Vec<u8>
 }
 
 impl RegisterParseError {
 	pub fn get_buffer(&self) -> &[u8] {
-		&self.buffer[..self.size]
+		&self.buffer
+	}
+}
+
+/// Registers up to this size are read and written through a stack buffer,
+/// larger ones through a heap allocated buffer.
+pub const REGISTER_STACK_BUFFER_SIZE: usize = 32;
+
+/// Scratch buffer for a single register transfer.
+pub enum RegisterBuffer {
+	Stack { buffer: [u8; REGISTER_STACK_BUFFER_SIZE], size: usize },
+	Heap(Vec<u8>)
+}
+
+impl RegisterBuffer {
+	pub fn new(size_bytes: usize) -> Self {
+		if size_bytes <= REGISTER_STACK_BUFFER_SIZE {
+			RegisterBuffer::Stack { buffer: [0; REGISTER_STACK_BUFFER_SIZE], size: size_bytes }
+		} else {
+			RegisterBuffer::Heap(vec![0; size_bytes])
+		}
+	}
+}
+
+impl Deref for RegisterBuffer {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		match *self {
+			RegisterBuffer::Stack { ref buffer, size } => &buffer[..size],
+			RegisterBuffer::Heap(ref buffer) => buffer
+		}
 	}
 }
 
+impl DerefMut for RegisterBuffer {
+	fn deref_mut(&mut self) -> &mut [u8] {
+		match *self {
+			RegisterBuffer::Stack { ref mut buffer, size } => &mut buffer[..size],
+			RegisterBuffer::Heap(ref mut buffer) => buffer
+		}
+	}
+}
 
 
-impl<'a, T, B> RegisterAddress<'a, T, B> where T: Register, B: DeviceRegisterBus {
+impl<'a, T, B, A> RegisterAddress<'a, T, B, A> where T: Register, B: DeviceRegisterBus, A: RegisterReadable {
 	pub fn read_into_buffer(&self, buffer: &mut[u8]) -> Result<(), PeripheryError> {
 		if buffer.len() != self.size_bytes {
 			return Err(PeripheryError::RegisterSizeMismatch);
@@ -38,46 +121,54 @@ impl<'a, T, B> RegisterAddress<'a, T, B> where T: Register, B: DeviceRegisterBus
 		Ok(())
 	}
 
-	pub fn read_with_parse_error_raw(&self) -> Result<T, (Option<RegisterParseError>, PeripheryError)> {
-		let mut buff = [0; 32];
-		if self.size_bytes > buff.len() {
-			return Err((None, PeripheryError::RegisterOversized));
-		}		
-		let deserialized = {
-			let mut register_buffer = &mut buff [..self.size_bytes as usize];
-			match self.read_into_buffer(register_buffer) {
-				Ok(_) => (),
-				Err(e) => {
-					return Err((None, e));
-				}
-			}
+	/// Read and parse the register using a caller-provided buffer. The buffer has to be
+	/// at least as large as the register.
+	pub fn read_with_buffer(&self, buffer: &mut [u8]) -> Result<T, (Option<RegisterParseError>, PeripheryError)> {
+		if buffer.len() < self.size_bytes {
+			return Err((None, PeripheryError::BufferLengthError));
+		}
 
-			T::from_register_value(register_buffer)
-		};
-		
-		match deserialized {
+		let register_buffer = &mut buffer[..self.size_bytes];
+		if let Err(e) = self.read_into_buffer(register_buffer) {
+			return Err((None, e));
+		}
+
+		match T::from_register_value(register_buffer) {
 			Ok(v) => Ok(v),
 			Err(e) => {
-				Err((Some(RegisterParseError { buffer: buff, size: self.size_bytes }), e))
+				Err((Some(RegisterParseError { buffer: register_buffer.to_vec() }), e))
 			}
 		}
 	}
 
+	pub fn read_with_parse_error_raw(&self) -> Result<T, (Option<RegisterParseError>, PeripheryError)> {
+		let mut buffer = RegisterBuffer::new(self.size_bytes);
+		self.read_with_buffer(&mut buffer)
+	}
+
 	pub fn read(&self) -> Result<T, PeripheryError> {
 		match self.read_with_parse_error_raw() {
 			Ok(v) => Ok(v),
 			Err((_, e)) => Err(e)
 		}
 	}
+}
 
-	pub fn write(&self, value: &T) -> Result<(), PeripheryError> {
-		let mut buff = [0; 32];
-		if buff.len() < self.size_bytes {
-			return Err(PeripheryError::RegisterSizeMismatch);
+impl<'a, T, B, A> RegisterAddress<'a, T, B, A> where T: Register, B: DeviceRegisterBus, A: RegisterWritable {
+	/// Serialize and write the register using a caller-provided buffer. The buffer has to be
+	/// at least as large as the register.
+	pub fn write_with_buffer(&self, value: &T, buffer: &mut [u8]) -> Result<(), PeripheryError> {
+		if buffer.len() < self.size_bytes {
+			return Err(PeripheryError::BufferLengthError);
 		}
-		let mut buff = &mut buff [0..self.size_bytes as usize];
-		try!(value.to_register_value(buff));
-		self.register_bus.write_to_register(self.address, buff)
+		let buffer = &mut buffer[..self.size_bytes];
+		try!(value.to_register_value(buffer));
+		self.register_bus.write_to_register(self.address, buffer)
+	}
+
+	pub fn write(&self, value: &T) -> Result<(), PeripheryError> {
+		let mut buffer = RegisterBuffer::new(self.size_bytes);
+		self.write_with_buffer(value, &mut buffer)
 	}
 
 	pub fn write_raw(&self, value: &[u8]) -> Result<(), PeripheryError> {
@@ -86,7 +177,9 @@ impl<'a, T, B> RegisterAddress<'a, T, B> where T: Register, B: DeviceRegisterBus
 		}
 		self.register_bus.write_to_register(self.address, value)
 	}
+}
 
+impl<'a, T, B, A> RegisterAddress<'a, T, B, A> where T: Register, B: DeviceRegisterBus, A: RegisterReadable + RegisterWritable {
 	pub fn modify<F: Fn(&mut T) -> ()>(&self, action: F) -> Result<T, PeripheryError> {
 		let mut r = self.read()?;
 		action(&mut r);
@@ -95,144 +188,250 @@ impl<'a, T, B> RegisterAddress<'a, T, B> where T: Register, B: DeviceRegisterBus
 	}
 }
 
+impl<'a, T, B, A> RegisterAddress<'a, T, B, A> where T: Register, B: DeviceRegisterBus + DeviceRegisterShadow, A: RegisterWritable {
+	/// Modify the register starting from its shadow copy, without reading it from the bus.
+	/// Also works for write-only registers, once their value is known to the bus.
+	pub fn modify_shadow<F: Fn(&mut T) -> ()>(&self, action: F) -> Result<T, PeripheryError> {
+		let mut buffer = RegisterBuffer::new(self.size_bytes);
+		self.register_bus.read_shadow(self.address, &mut buffer)?;
+		let mut r = T::from_register_value(&buffer)?;
+		action(&mut r);
+		self.write_with_buffer(&r, &mut buffer)?;
+		Ok(r)
+	}
+}
+
 use terminal_cli::*;
 
 pub trait RegisterAddressCli {
+	fn is_readable(&self) -> bool;
+	fn is_writable(&self) -> bool;
 	fn read_to_debug_string(&self, t: &mut CharacterTerminalWriter) -> fmt::Result;
 	fn write_from_u8(&self, t: &mut CharacterTerminalWriter, input: &str) -> fmt::Result;
 }
 
-impl<'a, T, B> RegisterAddressCli for RegisterAddress<'a, T, B> where T: Register + Debug + Display, B: DeviceRegisterBus {
-	fn read_to_debug_string(&self, t: &mut CharacterTerminalWriter) -> fmt::Result {
-		match self.read_with_parse_error_raw() {
-			Ok(v) => {
-				write!(t, "{}\r\n", v)
-			},
-			Err((Some(parse_error), e)) => {
-				write!(t, "Error parsing: {:?} Raw register value: {:?}\r\n", e, parse_error.get_buffer())
-			},
-			Err((None, e)) => {
-				write!(t, "Error reading: {:?}\r\n", e)
-			}
+fn read_to_debug_string<'a, T, B, A>(register: &RegisterAddress<'a, T, B, A>, t: &mut CharacterTerminalWriter) -> fmt::Result
+	where T: Register + Debug + Display, B: DeviceRegisterBus, A: RegisterReadable
+{
+	match register.read_with_parse_error_raw() {
+		Ok(v) => {
+			write!(t, "{}\r\n", v)
+		},
+		Err((Some(parse_error), e)) => {
+			write!(t, "Error parsing: {:?} Raw register value: {:?}\r\n", e, parse_error.get_buffer())
+		},
+		Err((None, e)) => {
+			write!(t, "Error reading: {:?}\r\n", e)
 		}
 	}
-	
-	fn write_from_u8(&self, t: &mut CharacterTerminalWriter, input: &str) -> fmt::Result {		
-		match ::periphery_buspirate_parser::parse_u8_array(input) {
-			Ok(a) => {
-				if a.len() != self.size_bytes as usize {
-					return write!(t, "Expected {} bytes!\r\n", self.size_bytes);
-				} else {
-					try!(write!(t, "Raw bytes: {:?}.", a));
-
-					match self.register_bus.write_to_register(self.address, &a) {
-						Ok(_) => {
-							try!(write!(t, " Written.\r\n"));
-						},
-						Err(e) => {
-							try!(write!(t, " Error writing to register: {:?}\r\n", e));
-						}
-					}
+}
 
-					return Ok(())
+fn write_from_u8<'a, T, B, A>(register: &RegisterAddress<'a, T, B, A>, t: &mut CharacterTerminalWriter, input: &str) -> fmt::Result
+	where T: Register, B: DeviceRegisterBus, A: RegisterWritable
+{
+	match ::periphery_buspirate_parser::parse_u8_array(input) {
+		Ok(a) => {
+			if a.len() != register.size_bytes as usize {
+				return write!(t, "Expected {} bytes!\r\n", register.size_bytes);
+			} else {
+				try!(write!(t, "Raw bytes: {:?}.", a));
+
+				match register.write_raw(&a) {
+					Ok(_) => {
+						try!(write!(t, " Written.\r\n"));
+					},
+					Err(e) => {
+						try!(write!(t, " Error writing to register: {:?}\r\n", e));
+					}
 				}
-			},
-			Err(e) => {
-				write!(t, "Error parsing byte arguments: {:?}\r\n", e)
+
+				return Ok(())
 			}
+		},
+		Err(e) => {
+			write!(t, "Error parsing byte arguments: {:?}\r\n", e)
 		}
 	}
 }
 
+impl<'a, T, B> RegisterAddressCli for RegisterAddress<'a, T, B, ReadWrite> where T: Register + Debug + Display, B: DeviceRegisterBus {
+	fn is_readable(&self) -> bool { true }
+	fn is_writable(&self) -> bool { true }
 
+	fn read_to_debug_string(&self, t: &mut CharacterTerminalWriter) -> fmt::Result {
+		read_to_debug_string(self, t)
+	}
 
+	fn write_from_u8(&self, t: &mut CharacterTerminalWriter, input: &str) -> fmt::Result {
+		write_from_u8(self, t, input)
+	}
+}
 
+impl<'a, T, B> RegisterAddressCli for RegisterAddress<'a, T, B, ReadOnly> where T: Register + Debug + Display, B: DeviceRegisterBus {
+	fn is_readable(&self) -> bool { true }
+	fn is_writable(&self) -> bool { false }
 
+	fn read_to_debug_string(&self, t: &mut CharacterTerminalWriter) -> fmt::Result {
+		read_to_debug_string(self, t)
+	}
+
+	fn write_from_u8(&self, t: &mut CharacterTerminalWriter, input: &str) -> fmt::Result {
+		write!(t, "The register is read-only.\r\n")
+	}
+}
+
+impl<'a, T, B> RegisterAddressCli for RegisterAddress<'a, T, B, WriteOnly> where T: Register + Debug + Display, B: DeviceRegisterBus {
+	fn is_readable(&self) -> bool { false }
+	fn is_writable(&self) -> bool { true }
+
+	fn read_to_debug_string(&self, t: &mut CharacterTerminalWriter) -> fmt::Result {
+		write!(t, "The register is write-only.\r\n")
+	}
 
-#[macro_export]
-macro_rules! registers {
-    (
-    	$(#[$chip_docs:meta])*
-    	chip $chip: ident
-    	{$
-    		(
-    			$(#[$attr:meta])*
-    			register [$address: expr; $size_bytes: expr] => $name: ident : $T: ty
-    		),+
-    	}
+	fn write_from_u8(&self, t: &mut CharacterTerminalWriter, input: &str) -> fmt::Result {
+		write_from_u8(self, t, input)
+	}
+}
 
-    ) => (
+/// Named field access from the CLI, for registers whose type implements `RegisterFields`.
+pub trait RegisterAddressFieldsCli {
+	fn get_field_cli(&self, t: &mut CharacterTerminalWriter, field: &str) -> fmt::Result;
+	fn set_fields_cli(&self, t: &mut CharacterTerminalWriter, assignments: &str) -> fmt::Result;
+}
 
-		$(#[$chip_docs:meta])*
-		#[derive(Clone)]
-		pub struct $chip<'a, B: 'a> where B: DeviceRegisterBus {
-			register_bus: &'a B
+fn get_field_cli<'a, T, B, A>(register: &RegisterAddress<'a, T, B, A>, t: &mut CharacterTerminalWriter, field: &str) -> fmt::Result
+	where T: Register + RegisterFields, B: DeviceRegisterBus, A: RegisterReadable
+{
+	let field = field.trim();
+	match register.read() {
+		Ok(r) => {
+			match r.get_field(field) {
+				Ok(v) => write!(t, "{} = {}\r\n", field, v),
+				Err(e) => write!(t, "Unknown field {}, expected one of {:?}\r\n", field, T::field_names())
+			}
+		},
+		Err(e) => {
+			write!(t, "Error reading: {:?}\r\n", e)
 		}
+	}
+}
 
-		impl<'a, B> $chip<'a, B> where B: DeviceRegisterBus {
-			#[inline]
-			pub fn new(register_bus: &'a B) -> Self {
-				$chip {
-					register_bus: register_bus
-				}
+fn set_fields_cli<'a, T, B, A>(register: &RegisterAddress<'a, T, B, A>, t: &mut CharacterTerminalWriter, assignments: &str) -> fmt::Result
+	where T: Register + RegisterFields + Display, B: DeviceRegisterBus, A: RegisterReadable + RegisterWritable
+{
+	let mut r = match register.read() {
+		Ok(r) => r,
+		Err(e) => { return write!(t, "Error reading: {:?}\r\n", e); }
+	};
+
+	match set_register_fields(&mut r, assignments) {
+		Ok(_) => (),
+		Err(PeripheryError::UnknownRegisterField) => {
+			return write!(t, "Unknown field, expected one of {:?}\r\n", T::field_names());
+		},
+		Err(e) => {
+			return write!(t, "Error parsing field assignments \"{}\": {:?}\r\n", assignments.trim(), e);
+		}
+	}
+
+	match register.write(&r) {
+		Ok(_) => write!(t, "Written.\r\n{}\r\n", r),
+		Err(e) => write!(t, "Error writing to register: {:?}\r\n", e)
+	}
+}
+
+impl<'a, T, B> RegisterAddressFieldsCli for RegisterAddress<'a, T, B, ReadWrite> where T: Register + RegisterFields + Display, B: DeviceRegisterBus {
+	fn get_field_cli(&self, t: &mut CharacterTerminalWriter, field: &str) -> fmt::Result {
+		get_field_cli(self, t, field)
+	}
+
+	fn set_fields_cli(&self, t: &mut CharacterTerminalWriter, assignments: &str) -> fmt::Result {
+		set_fields_cli(self, t, assignments)
+	}
+}
+
+impl<'a, T, B> RegisterAddressFieldsCli for RegisterAddress<'a, T, B, ReadOnly> where T: Register + RegisterFields, B: DeviceRegisterBus {
+	fn get_field_cli(&self, t: &mut CharacterTerminalWriter, field: &str) -> fmt::Result {
+		get_field_cli(self, t, field)
+	}
+
+	fn set_fields_cli(&self, t: &mut CharacterTerminalWriter, assignments: &str) -> fmt::Result {
+		write!(t, "The register is read-only.\r\n")
+	}
+}
+
+impl<'a, T, B> RegisterAddressFieldsCli for RegisterAddress<'a, T, B, WriteOnly> where T: Register + RegisterFields, B: DeviceRegisterBus {
+	fn get_field_cli(&self, t: &mut CharacterTerminalWriter, field: &str) -> fmt::Result {
+		write!(t, "The register is write-only.\r\n")
+	}
+
+	fn set_fields_cli(&self, t: &mut CharacterTerminalWriter, assignments: &str) -> fmt::Result {
+		write!(t, "The register is write-only, its other fields are unknown.\r\n")
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct EchoRegisterBus;
+	impl DeviceRegisterBus for EchoRegisterBus {
+		fn read_from_register(&self, register: u8, data: &mut [u8]) -> Result<(), PeripheryError> {
+			for (i, b) in data.iter_mut().enumerate() {
+				*b = register.wrapping_add(i as u8);
 			}
+			Ok(())
+		}
 
-			$(
-				$(#[$attr])*
-				#[inline]
-				pub fn $name(&self) -> registers::RegisterAddress<$T, B> {
-					registers::RegisterAddress {
-						address: $address,
-						size_bytes: $size_bytes,
-						register_bus: &self.register_bus,
-						_register_type: PhantomData::<$T>
-					}
+		fn write_to_register(&self, register: u8, data: &[u8]) -> Result<(), PeripheryError> {
+			for (i, b) in data.iter().enumerate() {
+				if *b != register.wrapping_add(i as u8) {
+					return Err(PeripheryError::WriteError);
 				}
-			)+			
-		}
-
-		impl<'a, B> Display for $chip<'a, B> where B: DeviceRegisterBus {
-			fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-				try!(write!(f, "Chip {}\r\n", stringify!($chip)));
-				$(
-					try!(write!(f, "Register [0x{address:X}; {size_bytes}] => {name}: {ty}\r\n", 
-						        address = $address,
-						        size_bytes = $size_bytes,
-						        name = stringify!($name),
-						        ty = stringify!($T)
-						));
-				)+
-				Ok(())
 			}
+			Ok(())
 		}
-		
-		impl<'a, B> RegisterBusCli for $chip<'a, B> where B: DeviceRegisterBus {
-			fn registers_cli<'b>(&self, exec: &mut $crate::terminal_cli::PrefixedExecutor) {
-    			use $crate::terminal_cli::*;
-				use $crate::bus::device_bus::registers::*;
-    			
-				if let Some(mut ctx) = exec.command(&"list_registers") {
-					write!(ctx.get_terminal(), "{}", &self);
-				}
+	}
 
-				$(
-					let name = stringify!($name);
+	struct Fifo(Vec<u8>);
+	impl Register for Fifo {
+		fn to_register_value(&self, output: &mut [u8]) -> Result<(), PeripheryError> {
+			if output.len() != self.0.len() {
+				return Err(PeripheryError::RegisterSizeMismatch);
+			}
+			output.copy_from_slice(&self.0);
+			Ok(())
+		}
 
-					let read_cmd = format!("register/{}/read", name);
-					if let Some(mut ctx) = exec.command(&read_cmd) {
-						&self.$name().read_to_debug_string(ctx.get_terminal());
-					}
-				
-					let write_cmd = format!("register/{}/write ", name);
-					if let Some(mut ctx) = exec.command(&write_cmd) {
-						let args = ctx.get_args().to_string();
-						&self.$name().write_from_u8(ctx.get_terminal(), &args);
-					}
-				)+		
-    			
-    		}
+		fn from_register_value(value: &[u8]) -> Result<Self, PeripheryError> {
+			Ok(Fifo(value.to_vec()))
 		}
+	}
 
-    )
-}
+	#[test]
+	fn test_oversized_register() {
+		let bus = EchoRegisterBus;
+		let reg = RegisterAddress {
+			address: 0xFC,
+			size_bytes: 128,
+			register_bus: &bus,
+			_register_type: PhantomData::<Fifo>,
+			_access: PhantomData::<ReadWrite>
+		};
+
+		let fifo = reg.read().unwrap();
+		assert_eq!(128, fifo.0.len());
+		assert_eq!(0xFC, fifo.0[0]);
+		assert_eq!(0x7B, fifo.0[127]);
+		reg.write(&fifo).unwrap();
 
+		let mut buffer = [0; 256];
+		let fifo = reg.read_with_buffer(&mut buffer).unwrap();
+		assert_eq!(128, fifo.0.len());
+		reg.write_with_buffer(&fifo, &mut buffer).unwrap();
+
+		let mut small_buffer = [0; 16];
+		assert!(reg.read_with_buffer(&mut small_buffer).is_err());
+	}
+}
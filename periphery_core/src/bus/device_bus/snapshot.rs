@@ -0,0 +1,278 @@
+//! Snapshots of all the registers on a chip. Used to capture the complete configuration
+//! of a device and to compare it with a known-good unit.
+
+use prelude::v1::*;
+use base::*;
+use bus::*;
+
+use terminal_cli::*;
+
+/// A single register's raw contents at the time of the snapshot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisterSnapshotEntry {
+	pub name: Cow<'static, str>,
+	pub address: u8,
+	pub size_bytes: usize,
+	/// `None` if the register couldn't be read.
+	pub value: Option<Vec<u8>>
+}
+
+/// Raw contents of every register on a chip.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisterSnapshot {
+	pub chip: Cow<'static, str>,
+	pub registers: Vec<RegisterSnapshotEntry>
+}
+
+impl RegisterSnapshot {
+	pub fn new<C: Into<Cow<'static, str>>>(chip: C) -> Self {
+		RegisterSnapshot {
+			chip: chip.into(),
+			registers: vec![]
+		}
+	}
+
+	/// Read the register from the bus and append it to the snapshot.
+	pub fn read_register<B: DeviceRegisterBus>(&mut self, name: &'static str, address: u8, size_bytes: usize, register_bus: &B) {
+		let mut buffer = vec![0; size_bytes];
+		let value = match register_bus.read_from_register(address, &mut buffer) {
+			Ok(_) => Some(buffer),
+			Err(_) => None
+		};
+
+		self.registers.push(RegisterSnapshotEntry {
+			name: name.into(),
+			address: address,
+			size_bytes: size_bytes,
+			value: value
+		});
+	}
+
+	pub fn get(&self, name: &str) -> Option<&RegisterSnapshotEntry> {
+		self.registers.iter().find(|r| r.name == name)
+	}
+
+	/// Raw value of the named register, if it was read successfully.
+	pub fn get_value(&self, name: &str) -> Option<&[u8]> {
+		match self.get(name) {
+			Some(&RegisterSnapshotEntry { value: Some(ref v), .. }) => Some(v),
+			_ => None
+		}
+	}
+
+	/// Compare this snapshot against a reference one. Only registers that differ are returned.
+	pub fn diff<'a>(&'a self, reference: &'a RegisterSnapshot) -> Vec<RegisterSnapshotDiff<'a>> {
+		let mut ret = vec![];
+
+		for register in &self.registers {
+			let reference_register = reference.get(&register.name);
+			let reference_value = reference_register.and_then(|r| r.value.as_ref().map(|v| v.as_slice()));
+			let value = register.value.as_ref().map(|v| v.as_slice());
+
+			if reference_register.is_none() || reference_value != value {
+				ret.push(RegisterSnapshotDiff {
+					name: &register.name,
+					address: register.address,
+					value: value,
+					reference: reference_value
+				});
+			}
+		}
+
+		for reference_register in &reference.registers {
+			if self.get(&reference_register.name).is_none() {
+				ret.push(RegisterSnapshotDiff {
+					name: &reference_register.name,
+					address: reference_register.address,
+					value: None,
+					reference: reference_register.value.as_ref().map(|v| v.as_slice())
+				});
+			}
+		}
+
+		ret
+	}
+
+	/// Parse the text representation, as produced by the `Display` implementation.
+	pub fn parse(input: &str) -> Result<RegisterSnapshot, PeripheryError> {
+		let mut snapshot = RegisterSnapshot::new("");
+
+		for line in input.lines() {
+			let line = line.trim();
+			if line.len() == 0 { continue; }
+
+			if line.starts_with("#") {
+				let header = line[1..].trim();
+				if header.starts_with("chip ") {
+					snapshot.chip = header[5..].trim().to_string().into();
+				}
+				continue;
+			}
+
+			let mut parts = line.split_whitespace();
+			let address = parts.next().ok_or(PeripheryError::ParseError)?;
+			let size_bytes = parts.next().ok_or(PeripheryError::ParseError)?;
+			let name = parts.next().ok_or(PeripheryError::ParseError)?;
+
+			let address = parse_hex_u8(address)?;
+			let size_bytes = usize::from_str(size_bytes).map_err(|_| PeripheryError::ParseError)?;
+
+			let value = {
+				let mut values = parts.peekable();
+				if values.peek() == Some(&"-") {
+					None
+				} else {
+					let mut v = vec![];
+					for b in values {
+						v.push(parse_hex_u8(b)?);
+					}
+					if v.len() != size_bytes {
+						return Err(PeripheryError::RegisterSizeMismatch);
+					}
+					Some(v)
+				}
+			};
+
+			snapshot.registers.push(RegisterSnapshotEntry {
+				name: name.to_string().into(),
+				address: address,
+				size_bytes: size_bytes,
+				value: value
+			});
+		}
+
+		Ok(snapshot)
+	}
+
+	#[cfg(feature = "std")]
+	pub fn save_to_file(&self, path: &str) -> Result<(), PeripheryError> {
+		use std::fs::File;
+
+		let mut file = File::create(path)?;
+		file.write_all(format!("{}", self).as_bytes())?;
+		Ok(())
+	}
+
+	#[cfg(feature = "std")]
+	pub fn load_from_file(path: &str) -> Result<RegisterSnapshot, PeripheryError> {
+		use std::fs::File;
+		use std::io::Read;
+
+		let mut file = File::open(path)?;
+		let mut contents = String::new();
+		file.read_to_string(&mut contents)?;
+		Self::parse(&contents)
+	}
+}
+
+fn parse_hex_u8(s: &str) -> Result<u8, PeripheryError> {
+	let s = if s.starts_with("0x") || s.starts_with("0X") { &s[2..] } else { s };
+	u8::from_str_radix(s, 16).map_err(|_| PeripheryError::ParseError)
+}
+
+impl Display for RegisterSnapshot {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		try!(write!(f, "# chip {}\r\n", self.chip));
+		for register in &self.registers {
+			try!(write!(f, "0x{:02X} {} {}", register.address, register.size_bytes, register.name));
+			match register.value {
+				Some(ref v) => {
+					for b in v {
+						try!(write!(f, " {:02X}", b));
+					}
+				},
+				None => {
+					try!(write!(f, " -"));
+				}
+			}
+			try!(write!(f, "\r\n"));
+		}
+		Ok(())
+	}
+}
+
+/// A register whose value differs between two snapshots.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisterSnapshotDiff<'a> {
+	pub name: &'a str,
+	pub address: u8,
+	pub value: Option<&'a [u8]>,
+	pub reference: Option<&'a [u8]>
+}
+
+impl<'a> Display for RegisterSnapshotDiff<'a> {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		write!(f, "Register [0x{:X}] {}: current {:?}, reference {:?}", self.address, self.name, self.value, self.reference)
+	}
+}
+
+/// Implemented by every chip generated with `#[register_map]`.
+pub trait RegisterSnapshotChip {
+	/// Read all the readable registers. Volatile registers are skipped, reading them
+	/// would pop FIFOs or clear pending interrupts.
+	fn snapshot(&self) -> RegisterSnapshot;
+	/// Write the registers found in the snapshot back to the chip. Returns the
+	/// number of written registers.
+	fn restore(&self, snapshot: &RegisterSnapshot) -> Result<usize, PeripheryError>;
+}
+
+pub fn register_snapshot_cli<C: RegisterSnapshotChip>(chip: &C, exec: &mut PrefixedExecutor) {
+	if let Some(mut ctx) = exec.command(&"registers/dump") {
+		let snapshot = chip.snapshot();
+		write!(ctx.get_terminal(), "{}", snapshot);
+	}
+
+	register_snapshot_file_cli(chip, exec);
+}
+
+#[cfg(feature = "std")]
+fn register_snapshot_file_cli<C: RegisterSnapshotChip>(chip: &C, exec: &mut PrefixedExecutor) {
+	if let Some(mut ctx) = exec.command(&"registers/save ") {
+		let path = ctx.get_args().trim().to_string();
+		let snapshot = chip.snapshot();
+		match snapshot.save_to_file(&path) {
+			Ok(_) => { write!(ctx.get_terminal(), "Saved {} registers to {}.\r\n", snapshot.registers.len(), path); },
+			Err(e) => { write!(ctx.get_terminal(), "Error saving the snapshot: {:?}\r\n", e); }
+		}
+	}
+
+	if let Some(mut ctx) = exec.command(&"registers/diff ") {
+		let path = ctx.get_args().trim().to_string();
+		match RegisterSnapshot::load_from_file(&path) {
+			Ok(reference) => {
+				let snapshot = chip.snapshot();
+				let diff = snapshot.diff(&reference);
+				for d in &diff {
+					write!(ctx.get_terminal(), "{}\r\n", d);
+				}
+				write!(ctx.get_terminal(), "{} registers differ.\r\n", diff.len());
+			},
+			Err(e) => { write!(ctx.get_terminal(), "Error loading the snapshot: {:?}\r\n", e); }
+		}
+	}
+}
+
+#[cfg(not(feature = "std"))]
+fn register_snapshot_file_cli<C: RegisterSnapshotChip>(chip: &C, exec: &mut PrefixedExecutor) {
+
+}
+
+#[cfg(test)]
+#[test]
+fn test_snapshot_roundtrip() {
+	let mut snapshot = RegisterSnapshot::new("TestRegisters");
+	snapshot.registers.push(RegisterSnapshotEntry { name: "id".into(), address: 0xD0, size_bytes: 1, value: Some(vec![0x58]) });
+	snapshot.registers.push(RegisterSnapshotEntry { name: "data".into(), address: 0xF7, size_bytes: 3, value: Some(vec![0x01, 0xAB, 0xFF]) });
+	snapshot.registers.push(RegisterSnapshotEntry { name: "broken".into(), address: 0x10, size_bytes: 2, value: None });
+
+	let text = format!("{}", snapshot);
+	let parsed = RegisterSnapshot::parse(&text).unwrap();
+	assert_eq!(snapshot, parsed);
+	assert_eq!(0, snapshot.diff(&parsed).len());
+
+	let mut changed = parsed.clone();
+	changed.registers[1].value = Some(vec![0x01, 0xAB, 0xFE]);
+	let diff = changed.diff(&snapshot);
+	assert_eq!(1, diff.len());
+	assert_eq!("data", diff[0].name);
+}
@@ -0,0 +1,187 @@
+//! Continuous data acquisition from devices. A data stream describes its channels with
+//! their types and units, the polled samples are timestamped by the system clock, so
+//! the recordings are self-describing and streams of different devices can be
+//! resampled to common timestamps.
+
+use prelude::v1::*;
+use base::*;
+use device::*;
+use system::*;
+
+pub trait DataStreams {
+	fn get_stream_infos(&self) -> Vec<DataStream>;
+	fn get_poller(&self, stream: DataStreamId) -> Result<Box<DataStreamPoller + Send + Sync>, PeripheryError>;
+}
+
+pub trait DataStreamPoller {
+	fn get_info(&self) -> DataStream;
+	/// The device the poller was created by is polled through the `device` argument. The
+	/// samples are timestamped with the `clock`.
+	fn poll(&mut self, device: &Device, clock: &SystemApiClock) -> Result<Vec<DataStreamPolled>, PeripheryError>;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DataStreamId(pub usize);
+
+#[derive(Debug, Clone)]
+pub struct DataStream {
+	pub id: DataStreamId,
+	pub cli_id: Cow<'static, str>,
+	pub description: Cow<'static, str>,
+	pub poll_every_ms: usize,
+	pub channels: Vec<DataStreamChannel>
+}
+
+impl DataStream {
+	pub fn get_labels(&self) -> Vec<Cow<'static, str>> {
+		self.channels.iter().map(|c| c.label.clone()).collect()
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DataStreamChannelType {
+	F32,
+	I64,
+	Bool,
+	String
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataStreamChannel {
+	pub label: Cow<'static, str>,
+	pub channel_type: DataStreamChannelType,
+	/// The symbol of the unit, like `Pa` or `°/s`
+	pub unit: Option<Cow<'static, str>>
+}
+
+impl DataStreamChannel {
+	pub fn new(label: &'static str, channel_type: DataStreamChannelType) -> Self {
+		DataStreamChannel {
+			label: label.into(),
+			channel_type: channel_type,
+			unit: None
+		}
+	}
+
+	pub fn with_unit(mut self, unit: &'static str) -> Self {
+		self.unit = Some(unit.into());
+		self
+	}
+}
+
+/// The label with the unit, `x [g]`.
+impl Display for DataStreamChannel {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self.unit {
+			Some(ref unit) => write!(f, "{} [{}]", self.label, unit),
+			None => write!(f, "{}", self.label)
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataStreamValue {
+	F32(f32),
+	I64(i64),
+	Bool(bool),
+	String(Cow<'static, str>)
+}
+
+impl DataStreamValue {
+	pub fn get_type(&self) -> DataStreamChannelType {
+		match *self {
+			DataStreamValue::F32(_) => DataStreamChannelType::F32,
+			DataStreamValue::I64(_) => DataStreamChannelType::I64,
+			DataStreamValue::Bool(_) => DataStreamChannelType::Bool,
+			DataStreamValue::String(_) => DataStreamChannelType::String
+		}
+	}
+
+	/// Numeric value of the sample, booleans are 0 or 1.
+	pub fn as_f32(&self) -> Option<f32> {
+		match *self {
+			DataStreamValue::F32(v) => Some(v),
+			DataStreamValue::I64(v) => Some(v as f32),
+			DataStreamValue::Bool(v) => Some(if v { 1.0 } else { 0.0 }),
+			DataStreamValue::String(_) => None
+		}
+	}
+}
+
+impl Display for DataStreamValue {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match *self {
+			DataStreamValue::F32(v) => write!(f, "{}", v),
+			DataStreamValue::I64(v) => write!(f, "{}", v),
+			DataStreamValue::Bool(v) => write!(f, "{}", v),
+			DataStreamValue::String(ref v) => write!(f, "{}", v)
+		}
+	}
+}
+
+/// A single sample of all the channels of a stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataStreamPolled {
+	/// Microseconds, from the system clock
+	pub timestamp_us: u64,
+	pub values: Vec<DataStreamValue>
+}
+
+impl DataStreamPolled {
+	pub fn new(timestamp_us: u64, values: Vec<DataStreamValue>) -> Self {
+		DataStreamPolled {
+			timestamp_us: timestamp_us,
+			values: values
+		}
+	}
+
+	pub fn from_f32(timestamp_us: u64, values: &[f32]) -> Self {
+		Self::new(timestamp_us, values.iter().map(|v| DataStreamValue::F32(*v)).collect())
+	}
+
+	/// The sample at `timestamp_us`, between this sample and the `next` one. Numeric
+	/// channels are interpolated linearly, booleans and strings keep the value of this
+	/// sample. Used to resample the streams of different devices to common timestamps.
+	pub fn interpolate(&self, next: &DataStreamPolled, timestamp_us: u64) -> Option<DataStreamPolled> {
+		if timestamp_us < self.timestamp_us || timestamp_us > next.timestamp_us || self.values.len() != next.values.len() {
+			return None;
+		}
+
+		if next.timestamp_us == self.timestamp_us {
+			return Some(DataStreamPolled::new(timestamp_us, self.values.clone()));
+		}
+
+		let t = (timestamp_us - self.timestamp_us) as f64 / (next.timestamp_us - self.timestamp_us) as f64;
+
+		let values = self.values.iter().zip(next.values.iter()).map(|(a, b)| {
+			match (a, b) {
+				(&DataStreamValue::F32(a), &DataStreamValue::F32(b)) => {
+					DataStreamValue::F32(a + ((b - a) as f64 * t) as f32)
+				},
+				(&DataStreamValue::I64(a), &DataStreamValue::I64(b)) => {
+					DataStreamValue::I64(a + ((b - a) as f64 * t).round() as i64)
+				},
+				(a, _) => a.clone()
+			}
+		}).collect();
+
+		Some(DataStreamPolled::new(timestamp_us, values))
+	}
+}
+
+
+#[test]
+fn test_data_stream_interpolate() {
+	let a = DataStreamPolled::new(1000, vec![DataStreamValue::F32(1.0), DataStreamValue::I64(10), DataStreamValue::Bool(false)]);
+	let b = DataStreamPolled::new(2000, vec![DataStreamValue::F32(3.0), DataStreamValue::I64(20), DataStreamValue::Bool(true)]);
+
+	let c = a.interpolate(&b, 1250).unwrap();
+	assert_eq!(1250, c.timestamp_us);
+	assert_eq!(vec![DataStreamValue::F32(1.5), DataStreamValue::I64(13), DataStreamValue::Bool(false)], c.values);
+
+	assert_eq!(None, a.interpolate(&b, 2500));
+	assert_eq!(Some(1.0), DataStreamValue::Bool(true).as_f32());
+
+	let channel = DataStreamChannel::new("x", DataStreamChannelType::F32).with_unit("g");
+	assert_eq!("x [g]", format!("{}", channel));
+}
@@ -4,9 +4,19 @@
 use prelude::v1::*;
 use base::*;
 use bus::*;
+use bus::device_bus::register_map::*;
 use units::*;
 use system::*;
 use device_storage::*;
+use device_id::*;
+use device_info::*;
+
+#[cfg(feature="std")]
+use std::sync::atomic::{AtomicU8, Ordering};
+#[cfg(not(feature="std"))]
+use core::sync::atomic::{AtomicU8, Ordering};
+use self_test::*;
+use data_stream::*;
 
 use terminal_cli::*;
 
@@ -33,16 +43,30 @@ impl<'a> DeviceBusCli<'a> {
 		self.commands = Some(Box::new(commands));
 		self
 	}
+
+	/// Registers and commands of the device, combined.
+	pub fn register_map(&self) -> RegisterMap {
+		let mut map = RegisterMap::new("");
+		if let Some(ref registers) = self.registers {
+			map.merge(registers.register_map());
+		}
+		if let Some(ref commands) = self.commands {
+			map.merge(commands.register_map());
+		}
+		map
+	}
 }
 
 
 
 pub trait RegisterBusCli {
 	fn registers_cli<'b>(&self, exec: &mut ::terminal_cli::PrefixedExecutor);	
+	fn register_map(&self) -> RegisterMap;
 }
 
 pub trait BusCommandsCli {
 	fn commands_cli<'b>(&self, exec: &mut ::terminal_cli::PrefixedExecutor);	
+	fn register_map(&self) -> RegisterMap;
 }
 
 
@@ -68,9 +92,24 @@ pub trait Device: Send + Sync {
 	fn get_angular_speed_3_sensor(&self) -> Option<&AngularSpeed3Sensor> {
 		None
 	}
+	fn get_color_sensor(&self) -> Option<&ColorSensor> {
+		None
+	}
+	fn get_proximity_sensor(&self) -> Option<&ProximitySensor> {
+		None
+	}
+	fn get_gesture_sensor(&self) -> Option<&GestureSensor> {
+		None
+	}
 	fn get_storage_device(&self) -> Option<&StorageDevice> {
 		None
 	}
+	fn get_power_management(&self) -> Option<&PowerManagement> {
+		None
+	}
+	fn get_self_test(&self) -> Option<&SelfTest> {
+		None
+	}
 
 	fn get_cli(&self) -> Option<&DeviceCli> {
 		None
@@ -80,12 +119,33 @@ pub trait Device: Send + Sync {
 		None
 	}
 
+	/// Native data streams of the driver. Every sensor also has a generic stream, see
+	/// `DeviceDataStreams`.
 	fn get_data_streams(&self) -> Option<&DataStreams> {
 		None
 	}
 
+	/// Machine-readable description of the device's registers and commands.
+	fn register_map(&self) -> Option<RegisterMap> {
+		self.get_registers_cli().map(|cli| cli.register_map())
+	}
+
+	/// The unique id of a detected device, with the bus and the address.
+	fn get_device_id(&self) -> Option<&DeviceId> {
+		None
+	}
+
+	/// Manufacturer, part number and the detected variant of the device.
+	fn info(&self) -> DeviceInfo {
+		DeviceInfo::default()
+	}
+
 	fn execute_cli<'a>(&self, exec: &mut CliExecutor) {
 		if let Some(ref mut exec) = exec.with_prefix(&format!("{}/", self.id())) {
+			if let Some(mut ctx) = exec.command(&"info") {
+				write!(ctx.get_terminal(), "{}\r\n{}", self.description(), self.info());
+			}
+
 			if let Some(als) = self.get_ambient_light_sensor() {
 				if let Some(mut ctx) = exec.command(&"ambient_light/get") {
 					match als.get_ambient_light() {
@@ -149,7 +209,89 @@ pub trait Device: Send + Sync {
 				}
 			}
 
+			if let Some(color) = self.get_color_sensor() {
+				if let Some(mut ctx) = exec.command(&"color/get") {
+					match color.get_color() {
+						Ok(c) => ctx.get_terminal().print_line(&format!("{}", c)),
+						Err(e) => ctx.get_terminal().print_line(&format!("Error reading color data: {:?}", e))
+					}
+				}
+
+				if let Some(mut ctx) = exec.command(&"color_temperature/get") {
+					match color.get_color_temperature() {
+						Ok(t) => ctx.get_terminal().print_line(&format!("{}", t)),
+						Err(e) => ctx.get_terminal().print_line(&format!("Error reading color temperature: {:?}", e))
+					}
+				}
+			}
+
+			if let Some(proximity) = self.get_proximity_sensor() {
+				if let Some(mut ctx) = exec.command(&"proximity/get") {
+					match proximity.get_proximity() {
+						Ok(p) => ctx.get_terminal().print_line(&format!("{}", p)),
+						Err(e) => ctx.get_terminal().print_line(&format!("Error reading proximity data: {:?}", e))
+					}
+				}
+			}
+
+			if let Some(gesture) = self.get_gesture_sensor() {
+				if let Some(mut ctx) = exec.command(&"gesture/get") {
+					match gesture.get_detected_gestures() {
+						Ok(ref gestures) if gestures.len() == 0 => ctx.get_terminal().print_line("No gesture detected."),
+						Ok(gestures) => {
+							for g in gestures {
+								ctx.get_terminal().print_line(&format!("{:?}", g));
+							}
+						},
+						Err(e) => ctx.get_terminal().print_line(&format!("Error reading gestures: {:?}", e))
+					}
+				}
+			}
+
+			if let Some(power) = self.get_power_management() {
+				if let Some(mut ctx) = exec.command(&"power/state") {
+					match power.power_state() {
+						Ok(s) => ctx.get_terminal().print_line(&format!("{:?}", s)),
+						Err(e) => ctx.get_terminal().print_line(&format!("Error reading the power state: {:?}", e))
+					}
+				}
+
+				if let Some(mut ctx) = exec.command(&"power/sleep") {
+					match power.sleep() {
+						Ok(_) => ctx.get_terminal().print_line("Device is sleeping."),
+						Err(e) => ctx.get_terminal().print_line(&format!("Error: {:?}", e))
+					}
+				}
+
+				if let Some(mut ctx) = exec.command(&"power/wake") {
+					match power.wake() {
+						Ok(_) => ctx.get_terminal().print_line("Device is awake."),
+						Err(e) => ctx.get_terminal().print_line(&format!("Error: {:?}", e))
+					}
+				}
+
+				if let Some(mut ctx) = exec.command(&"power/soft_reset") {
+					match power.soft_reset() {
+						Ok(_) => ctx.get_terminal().print_line("Device was reset."),
+						Err(e) => ctx.get_terminal().print_line(&format!("Error: {:?}", e))
+					}
+				}
+			}
+
+			if let Some(self_test) = self.get_self_test() {
+				if let Some(mut ctx) = exec.command(&"self_test") {
+					match self_test.self_test() {
+						Ok(report) => { write!(ctx.get_terminal(), "{}", report); },
+						Err(e) => ctx.get_terminal().print_line(&format!("Error running the self-test: {:?}", e))
+					}
+				}
+			}
+
 			if let Some(cli) = self.get_registers_cli() {
+				if let Some(mut ctx) = exec.command(&"register_map") {
+					write!(ctx.get_terminal(), "{}\r\n", cli.register_map().to_json());
+				}
+
 				if let Some(registers_cli) = cli.registers {				
 					registers_cli.registers_cli(exec);
 				}
@@ -230,34 +372,150 @@ pub trait AngularSpeed3Sensor {
 	device_sensor_fn!();
 }
 
-pub trait DeviceCli {
-	fn execute_cli(&self, exec: &mut PrefixedExecutor);
+pub trait ColorSensor {
+	fn get_color(&self) -> Result<Color, PeripheryError>;
+	fn get_color_temperature(&self) -> Result<ColorTemperature, PeripheryError>;
+
+	device_sensor_fn!();
+}
+
+pub trait ProximitySensor {
+	fn get_proximity(&self) -> Result<Proximity, PeripheryError>;
+
+	device_sensor_fn!();
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DetectedGesture {
+	SwipeUp,
+	SwipeDown,
+	SwipeLeft,
+	SwipeRight,
+	/// Something came close to the sensor and stayed there
+	Near
 }
 
-pub trait DataStreams {
-	fn get_stream_infos(&self) -> Vec<DataStream>;
-	fn get_poller(&self, stream: DataStreamId) -> Result<Box<DataStreamPoller + Send + Sync>, PeripheryError>;
+pub trait GestureSensor {
+	/// The gestures completed since the last call, in the order they were made. Has
+	/// to be called often enough to see the whole motion.
+	fn get_detected_gestures(&self) -> Result<Vec<DetectedGesture>, PeripheryError>;
+
+	device_sensor_fn!();
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PowerState {
+	Active,
+	/// Low power mode, the configuration is retained
+	Sleep
+}
+
+pub trait PowerManagement {
+	/// Put the device into its lowest power mode that keeps the configuration.
+	fn sleep(&self) -> Result<(), PeripheryError>;
+	/// Resume the operation after `sleep`.
+	fn wake(&self) -> Result<(), PeripheryError>;
+	/// Reset the device to its power-on state. The device has to be initialized again.
+	fn soft_reset(&self) -> Result<(), PeripheryError> {
+		Err(PeripheryError::NotImplemented)
+	}
+	fn power_state(&self) -> Result<PowerState, PeripheryError>;
 }
 
-pub trait DataStreamPoller {
-	fn get_info(&self) -> DataStream;
-	fn poll(&mut self) -> Result<Vec<DataStreamPolled>, PeripheryError>;
+/// A power mode that fits into a byte, so it can be kept in a `PowerStateCell`.
+pub trait PowerStateValue: Copy {
+	fn to_state_byte(&self) -> u8;
+	fn from_state_byte(b: u8) -> Option<Self>;
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-pub struct DataStreamId(pub usize);
+impl PowerStateValue for PowerState {
+	fn to_state_byte(&self) -> u8 {
+		match *self {
+			PowerState::Active => 0,
+			PowerState::Sleep => 1
+		}
+	}
 
-#[derive(Debug, Clone)]
-pub struct DataStream {
-	pub id: DataStreamId,
-	pub cli_id: Cow<'static, str>,
-	pub description: Cow<'static, str>,
-	pub poll_every_ms: usize,
-	pub labels: Vec<Cow<'static, str>>
+	fn from_state_byte(b: u8) -> Option<Self> {
+		match b {
+			0 => Some(PowerState::Active),
+			1 => Some(PowerState::Sleep),
+			_ => None
+		}
+	}
 }
 
-#[derive(Debug)]
-pub enum DataStreamPolled {
-	F32 { data: Vec<f32> },
-	Strings { data: Vec<Cow<'static, str>> }
+/// The power mode last commanded by the driver, for chips that can't report it or
+/// can't tell it apart from their other modes. Empty until the first `set`. Clones
+/// share the state, which keeps the driver `Send + Sync` without `std`.
+#[derive(Clone, Debug)]
+pub struct PowerStateCell<T = PowerState> {
+	state: Arc<AtomicU8>,
+	_value: PhantomData<T>
+}
+
+impl<T> PowerStateCell<T> where T: PowerStateValue {
+	pub fn new() -> Self {
+		PowerStateCell {
+			state: Arc::new(AtomicU8::new(0)),
+			_value: PhantomData
+		}
+	}
+
+	pub fn get(&self) -> Option<T> {
+		Self::decode(self.state.load(Ordering::SeqCst))
+	}
+
+	pub fn set(&self, value: T) {
+		self.state.store(value.to_state_byte().wrapping_add(1), Ordering::SeqCst);
+	}
+
+	/// Returns the last value and empties the cell.
+	pub fn take(&self) -> Option<T> {
+		Self::decode(self.state.swap(0, Ordering::SeqCst))
+	}
+
+	pub fn clear(&self) {
+		self.state.store(0, Ordering::SeqCst);
+	}
+
+	fn decode(b: u8) -> Option<T> {
+		match b {
+			0 => None,
+			b => T::from_state_byte(b - 1)
+		}
+	}
+}
+
+impl<T> Default for PowerStateCell<T> where T: PowerStateValue {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+pub trait DeviceCli {
+	fn execute_cli(&self, exec: &mut PrefixedExecutor);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_power_state_cell() {
+		let cell: PowerStateCell = PowerStateCell::new();
+		assert_eq!(None, cell.get());
+
+		let shared = cell.clone();
+		cell.set(PowerState::Sleep);
+		assert_eq!(Some(PowerState::Sleep), shared.get());
+
+		assert_eq!(Some(PowerState::Sleep), shared.take());
+		assert_eq!(None, cell.get());
+
+		cell.set(PowerState::Active);
+		assert_eq!(Some(PowerState::Active), cell.get());
+		cell.clear();
+		assert_eq!(None, shared.get());
+	}
 }
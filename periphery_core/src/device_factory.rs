@@ -5,6 +5,8 @@ use prelude::v1::*;
 use base::*;
 use bus::*;
 use system::*;
+use device::*;
+use device_id::*;
 
 pub trait DeviceArguments<B: Bus, A, O> {
     fn new(bus: B, additional: A) -> Result<O, PeripheryError>;
@@ -72,6 +74,17 @@ pub trait DeviceI2CDetection<D: 'static, B, A> : Default
     }
 
     fn find_all_devices(&self, bus: B) -> Result<Vec<D>, PeripheryError> {
+        let devices = self.find_all_devices_on_addresses(bus)?;
+        Ok(devices.into_iter().map(|(_, device)| device).collect())
+    }
+
+    /// Detected devices with their unique ids, `bus/address/device`.
+    fn find_all_devices_identified(&self, bus: B) -> Result<Vec<(DeviceId, D)>, PeripheryError> {
+        let devices = self.find_all_devices_on_addresses(bus.clone())?;
+        Ok(devices.into_iter().map(|(address, device)| (DeviceId::on_i2c(&bus, address, &device), device)).collect())
+    }
+
+    fn find_all_devices_on_addresses(&self, bus: B) -> Result<Vec<(I2CAddress, D)>, PeripheryError> {
         let mut ret = vec![];
 		
 		for i2c_address in self.get_addresses() {
@@ -90,7 +103,7 @@ pub trait DeviceI2CDetection<D: 'static, B, A> : Default
 
             if let Ok(args) = A::new(bus.clone(), i2c_args) {
                 if let Ok(device) = Self::new(args) {
-                    ret.push(device);
+                    ret.push((*i2c_address, device));
 				}
             }
 		}
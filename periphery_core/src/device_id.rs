@@ -0,0 +1,223 @@
+//! Unique identification of detected devices. A device's own `id()` only names its
+//! type, the full id also includes the bus and the address, like `i2c-1/0x76/ms5611`.
+
+use prelude::v1::*;
+use base::*;
+use bus::*;
+use bus::i2c::*;
+use bus::device_bus::register_map::*;
+use device::*;
+use device_info::*;
+use self_test::*;
+use device_storage::*;
+use units::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceId {
+	/// The CLI prefix of the bus
+	pub bus: Cow<'static, str>,
+	pub bus_kind: Option<DeviceBusKind>,
+	/// The address of the device on the bus, if it has one
+	pub address: Option<Cow<'static, str>>,
+	/// The type id of the device
+	pub device: Cow<'static, str>,
+	/// User-assigned name that replaces the path
+	pub alias: Option<Cow<'static, str>>
+}
+
+impl DeviceId {
+	pub fn new(bus: &str, address: Option<&str>, device: &str) -> Self {
+		DeviceId {
+			bus: bus.to_string().into(),
+			bus_kind: None,
+			address: address.map(|a| a.to_string().into()),
+			device: device.to_string().into(),
+			alias: None
+		}
+	}
+
+	pub fn on_i2c<B: Bus>(bus: &B, address: I2CAddress, device: &Device) -> Self {
+		let bus = bus.get_cli_prefix().unwrap_or("bus".into());
+		let mut id = DeviceId::new(&bus, Some(&format!("{}", address)), &device.id());
+		id.bus_kind = Some(DeviceBusKind::I2C);
+		id
+	}
+
+	/// The alias can't contain whitespace or slashes, as it is used as a CLI prefix.
+	pub fn set_alias(&mut self, alias: &str) -> Result<(), PeripheryError> {
+		if alias.len() == 0 || alias.contains(|c: char| c.is_whitespace() || c == '/') {
+			return Err(PeripheryError::ParseError);
+		}
+
+		self.alias = Some(alias.to_string().into());
+		Ok(())
+	}
+
+	pub fn clear_alias(&mut self) {
+		self.alias = None;
+	}
+
+	/// The full hierarchical path, `bus/address/device`.
+	pub fn get_path(&self) -> String {
+		match self.address {
+			Some(ref address) => format!("{}/{}/{}", self.bus, address, self.device),
+			None => format!("{}/{}", self.bus, self.device)
+		}
+	}
+
+	/// The alias if it was assigned, the full path otherwise.
+	pub fn get_id(&self) -> Cow<str> {
+		match self.alias {
+			Some(ref alias) => Cow::Borrowed(alias),
+			None => self.get_path().into()
+		}
+	}
+
+	/// Fill in the bus and the address the device doesn't know about itself.
+	pub fn complete_info(&self, mut info: DeviceInfo) -> DeviceInfo {
+		if info.bus_kind.is_none() {
+			info.bus_kind = self.bus_kind;
+		}
+		if info.bus.is_none() {
+			info.bus = Some(self.bus.clone());
+		}
+		if info.address.is_none() {
+			info.address = self.address.clone();
+		}
+		info
+	}
+
+	/// Matches either the full path or the alias.
+	pub fn matches(&self, id: &str) -> bool {
+		self.get_path() == id || self.alias.as_ref().map(|a| a == id).unwrap_or(false)
+	}
+}
+
+impl Display for DeviceId {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self.alias {
+			Some(ref alias) => write!(f, "{} ({})", alias, self.get_path()),
+			None => write!(f, "{}", self.get_path())
+		}
+	}
+}
+
+/// A detected device together with its unique id. Its `id()` is the full id, so the
+/// CLI commands and data streams of devices of the same type don't collide.
+pub struct IdentifiedDevice {
+	id: DeviceId,
+	device: Box<Device + Send + Sync>
+}
+
+impl IdentifiedDevice {
+	pub fn new(id: DeviceId, device: Box<Device + Send + Sync>) -> Self {
+		IdentifiedDevice {
+			id: id,
+			device: device
+		}
+	}
+
+	pub fn set_alias(&mut self, alias: &str) -> Result<(), PeripheryError> {
+		self.id.set_alias(alias)
+	}
+
+	pub fn get_inner(&self) -> &Device {
+		&*self.device
+	}
+
+	pub fn into_inner(self) -> Box<Device + Send + Sync> {
+		self.device
+	}
+}
+
+impl Device for IdentifiedDevice {
+	fn get_ambient_light_sensor(&self) -> Option<&AmbientLightSensor> {
+		self.device.get_ambient_light_sensor()
+	}
+	fn get_ambient_temperature_sensor(&self) -> Option<&AmbientTemperatureSensor> {
+		self.device.get_ambient_temperature_sensor()
+	}
+	fn get_atmospheric_pressure_sensor(&self) -> Option<&AtmosphericPressureSensor> {
+		self.device.get_atmospheric_pressure_sensor()
+	}
+	fn get_atmospheric_humidity_sensor(&self) -> Option<&AtmosphericHumiditySensor> {
+		self.device.get_atmospheric_humidity_sensor()
+	}
+	fn get_acceleration_3_sensor(&self) -> Option<&Acceleration3Sensor> {
+		self.device.get_acceleration_3_sensor()
+	}
+	fn get_magnetic_field_3_sensor(&self) -> Option<&MagneticField3Sensor> {
+		self.device.get_magnetic_field_3_sensor()
+	}
+	fn get_angular_speed_3_sensor(&self) -> Option<&AngularSpeed3Sensor> {
+		self.device.get_angular_speed_3_sensor()
+	}
+	fn get_color_sensor(&self) -> Option<&ColorSensor> {
+		self.device.get_color_sensor()
+	}
+	fn get_proximity_sensor(&self) -> Option<&ProximitySensor> {
+		self.device.get_proximity_sensor()
+	}
+	fn get_gesture_sensor(&self) -> Option<&GestureSensor> {
+		self.device.get_gesture_sensor()
+	}
+	fn get_storage_device(&self) -> Option<&StorageDevice> {
+		self.device.get_storage_device()
+	}
+	fn get_power_management(&self) -> Option<&PowerManagement> {
+		self.device.get_power_management()
+	}
+	fn get_self_test(&self) -> Option<&SelfTest> {
+		self.device.get_self_test()
+	}
+	fn get_cli(&self) -> Option<&DeviceCli> {
+		self.device.get_cli()
+	}
+	fn get_registers_cli(&self) -> Option<DeviceBusCli> {
+		self.device.get_registers_cli()
+	}
+	fn get_data_streams(&self) -> Option<&DataStreams> {
+		self.device.get_data_streams()
+	}
+	fn register_map(&self) -> Option<RegisterMap> {
+		self.device.register_map()
+	}
+	fn get_device_id(&self) -> Option<&DeviceId> {
+		Some(&self.id)
+	}
+
+	fn info(&self) -> DeviceInfo {
+		self.id.complete_info(self.device.info())
+	}
+
+	fn init_after_detection(&self) -> Result<bool, PeripheryError> {
+		self.device.init_after_detection()
+	}
+
+	fn description(&self) -> Cow<str> {
+		self.device.description()
+	}
+
+	fn id(&self) -> Cow<str> {
+		self.id.get_id()
+	}
+}
+
+
+#[test]
+fn test_device_id() {
+	let mut id = DeviceId::new("i2c-1", Some("0x76"), "ms5611");
+	assert_eq!("i2c-1/0x76/ms5611", id.get_id());
+	assert!(id.matches("i2c-1/0x76/ms5611"));
+
+	assert!(id.set_alias("outside baro").is_err());
+	assert!(id.set_alias("baro/1").is_err());
+	id.set_alias("outside").unwrap();
+	assert_eq!("outside", id.get_id());
+	assert!(id.matches("outside"));
+	assert!(id.matches("i2c-1/0x76/ms5611"));
+	assert_eq!("outside (i2c-1/0x76/ms5611)", format!("{}", id));
+
+	let id = DeviceId::new("spi-0", None, "spi_flash");
+	assert_eq!("spi-0/spi_flash", id.get_id());
+}
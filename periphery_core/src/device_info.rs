@@ -0,0 +1,72 @@
+//! Structured metadata about a device, for inventory reports and device filtering.
+
+use prelude::v1::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DeviceBusKind {
+	I2C,
+	Spi
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeviceInfo {
+	pub manufacturer: Option<Cow<'static, str>>,
+	pub part_number: Option<Cow<'static, str>>,
+	/// The detected variant or revision of the chip
+	pub variant: Option<Cow<'static, str>>,
+	pub bus_kind: Option<DeviceBusKind>,
+	/// The CLI prefix of the bus
+	pub bus: Option<Cow<'static, str>>,
+	pub address: Option<Cow<'static, str>>,
+	pub serial_number: Option<Cow<'static, str>>,
+	pub driver_version: Option<Cow<'static, str>>
+}
+
+impl DeviceInfo {
+	pub fn new(manufacturer: &'static str, part_number: &'static str) -> Self {
+		DeviceInfo {
+			manufacturer: Some(manufacturer.into()),
+			part_number: Some(part_number.into()),
+			.. Default::default()
+		}
+	}
+
+	pub fn with_variant<V: Into<Cow<'static, str>>>(mut self, variant: V) -> Self {
+		self.variant = Some(variant.into());
+		self
+	}
+
+	pub fn with_serial_number<S: Into<Cow<'static, str>>>(mut self, serial_number: S) -> Self {
+		self.serial_number = Some(serial_number.into());
+		self
+	}
+
+	/// Usually `env!("CARGO_PKG_VERSION")` of the driver's crate.
+	pub fn with_driver_version(mut self, driver_version: &'static str) -> Self {
+		self.driver_version = Some(driver_version.into());
+		self
+	}
+}
+
+impl Display for DeviceInfo {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		fn line(f: &mut Formatter, label: &str, value: &Option<Cow<'static, str>>) -> fmt::Result {
+			if let Some(ref value) = *value {
+				try!(write!(f, "{}: {}\r\n", label, value));
+			}
+			Ok(())
+		}
+
+		try!(line(f, "Manufacturer", &self.manufacturer));
+		try!(line(f, "Part number", &self.part_number));
+		try!(line(f, "Variant", &self.variant));
+		if let Some(bus_kind) = self.bus_kind {
+			try!(write!(f, "Bus kind: {:?}\r\n", bus_kind));
+		}
+		try!(line(f, "Bus", &self.bus));
+		try!(line(f, "Address", &self.address));
+		try!(line(f, "Serial number", &self.serial_number));
+		try!(line(f, "Driver version", &self.driver_version));
+		Ok(())
+	}
+}
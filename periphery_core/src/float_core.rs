@@ -20,6 +20,8 @@ pub trait LocalFloat: Sized {
     fn atan(self) -> f32;    
     fn asin(self) -> f32;
     fn acos(self) -> f32;
+    fn sin(self) -> f32;
+    fn cos(self) -> f32;
 }
 
 impl LocalFloat for f32 {
@@ -53,4 +55,14 @@ impl LocalFloat for f32 {
     fn acos(self) -> f32 {
         unsafe { acosf(self) }
     }
+
+    #[inline]
+    fn sin(self) -> f32 {
+        unsafe { intrinsics::sinf32(self) }
+    }
+
+    #[inline]
+    fn cos(self) -> f32 {
+        unsafe { intrinsics::cosf32(self) }
+    }
 }
@@ -0,0 +1,225 @@
+//! Rotations for the 3-axis sensor readings, see the vector operations in `units`.
+
+use prelude::v1::*;
+
+/// A 3x3 matrix, stored as rows.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Matrix3(pub [[f32; 3]; 3]);
+
+impl Matrix3 {
+	pub fn from_rows(rows: [[f32; 3]; 3]) -> Matrix3 {
+		Matrix3(rows)
+	}
+
+	pub fn identity() -> Matrix3 {
+		Matrix3([
+			[1.0, 0.0, 0.0],
+			[0.0, 1.0, 0.0],
+			[0.0, 0.0, 1.0]
+		])
+	}
+
+	pub fn transpose(&self) -> Matrix3 {
+		let m = &self.0;
+		Matrix3([
+			[m[0][0], m[1][0], m[2][0]],
+			[m[0][1], m[1][1], m[2][1]],
+			[m[0][2], m[1][2], m[2][2]]
+		])
+	}
+
+	pub fn mul_vector(&self, v: [f32; 3]) -> [f32; 3] {
+		let m = &self.0;
+		[
+			m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+			m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+			m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2]
+		]
+	}
+}
+
+impl Mul for Matrix3 {
+	type Output = Matrix3;
+
+	fn mul(self, other: Matrix3) -> Matrix3 {
+		let mut r = [[0.0; 3]; 3];
+		for i in 0..3 {
+			for j in 0..3 {
+				r[i][j] = self.0[i][0] * other.0[0][j] + self.0[i][1] * other.0[1][j] + self.0[i][2] * other.0[2][j];
+			}
+		}
+		Matrix3(r)
+	}
+}
+
+impl Default for Matrix3 {
+	fn default() -> Self {
+		Self::identity()
+	}
+}
+
+/// A rotation quaternion. The operations expect it to be normalized.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quaternion {
+	pub w: f32,
+	pub x: f32,
+	pub y: f32,
+	pub z: f32
+}
+
+impl Quaternion {
+	pub fn new(w: f32, x: f32, y: f32, z: f32) -> Quaternion {
+		Quaternion { w: w, x: x, y: y, z: z }
+	}
+
+	pub fn identity() -> Quaternion {
+		Quaternion::new(1.0, 0.0, 0.0, 0.0)
+	}
+
+	/// Rotation around the axis, counter-clockwise when looking against it. The axis
+	/// doesn't have to be normalized.
+	pub fn from_axis_angle(axis: [f32; 3], angle_radians: f32) -> Quaternion {
+		let n = (axis[0] * axis[0] + axis[1] * axis[1] + axis[2] * axis[2]).sqrt();
+		if n == 0.0 {
+			return Quaternion::identity();
+		}
+
+		let half = angle_radians / 2.0;
+		let s = half.sin() / n;
+		Quaternion::new(half.cos(), axis[0] * s, axis[1] * s, axis[2] * s)
+	}
+
+	pub fn conjugate(&self) -> Quaternion {
+		Quaternion::new(self.w, -self.x, -self.y, -self.z)
+	}
+
+	pub fn norm(&self) -> f32 {
+		(self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+	}
+
+	pub fn normalize(&self) -> Quaternion {
+		let n = self.norm();
+		if n == 0.0 {
+			return Quaternion::identity();
+		}
+		Quaternion::new(self.w / n, self.x / n, self.y / n, self.z / n)
+	}
+
+	/// Rotates the vector by this rotation, q * v * q'.
+	pub fn rotate_vector(&self, v: [f32; 3]) -> [f32; 3] {
+		self.to_rotation_matrix().mul_vector(v)
+	}
+
+	pub fn to_rotation_matrix(&self) -> Matrix3 {
+		let (w, x, y, z) = (self.w, self.x, self.y, self.z);
+		Matrix3([
+			[1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y)],
+			[2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x)],
+			[2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y)]
+		])
+	}
+}
+
+/// The Hamilton product, `a * b` rotates by `b` first and then by `a`.
+impl Mul for Quaternion {
+	type Output = Quaternion;
+
+	fn mul(self, b: Quaternion) -> Quaternion {
+		let a = self;
+		Quaternion::new(
+			a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+			a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+			a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+			a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w
+		)
+	}
+}
+
+impl Default for Quaternion {
+	fn default() -> Self {
+		Self::identity()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::f32::consts::FRAC_PI_2;
+
+	fn assert_vector_eq(expected: [f32; 3], v: [f32; 3]) {
+		for i in 0..3 {
+			assert!((expected[i] - v[i]).abs() < 1e-5, "expected {:?}, got {:?}", expected, v);
+		}
+	}
+
+	fn assert_matrix_eq(expected: Matrix3, m: Matrix3) {
+		for i in 0..3 {
+			assert_vector_eq(expected.0[i], m.0[i]);
+		}
+	}
+
+	fn assert_quaternion_eq(expected: Quaternion, q: Quaternion) {
+		assert_vector_eq([expected.x, expected.y, expected.z], [q.x, q.y, q.z]);
+		assert!((expected.w - q.w).abs() < 1e-5, "expected {:?}, got {:?}", expected, q);
+	}
+
+	#[test]
+	fn test_matrix_mul_and_transpose() {
+		let a = Matrix3::from_rows([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]]);
+		// cycles the columns
+		let b = Matrix3::from_rows([[0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]]);
+
+		assert_eq!(Matrix3::from_rows([[3.0, 1.0, 2.0], [6.0, 4.0, 5.0], [9.0, 7.0, 8.0]]), a * b);
+		assert_eq!(a, a * Matrix3::identity());
+		assert_eq!(Matrix3::from_rows([[1.0, 4.0, 7.0], [2.0, 5.0, 8.0], [3.0, 6.0, 9.0]]), a.transpose());
+
+		// the inverse of a rotation is its transpose
+		let r = Quaternion::from_axis_angle([1.0, 2.0, 3.0], 0.7).to_rotation_matrix();
+		assert_matrix_eq(Matrix3::identity(), r * r.transpose());
+	}
+
+	#[test]
+	fn test_known_rotations() {
+		let z90 = Quaternion::from_axis_angle([0.0, 0.0, 1.0], FRAC_PI_2);
+		let x90 = Quaternion::from_axis_angle([2.0, 0.0, 0.0], FRAC_PI_2);
+
+		assert_vector_eq([0.0, 1.0, 0.0], z90.rotate_vector([1.0, 0.0, 0.0]));
+		assert_vector_eq([-1.0, 0.0, 0.0], z90.rotate_vector([0.0, 1.0, 0.0]));
+		assert_vector_eq([0.0, 0.0, 1.0], x90.rotate_vector([0.0, 1.0, 0.0]));
+	}
+
+	#[test]
+	fn test_hamilton_product_order() {
+		let z90 = Quaternion::from_axis_angle([0.0, 0.0, 1.0], FRAC_PI_2);
+		let x90 = Quaternion::from_axis_angle([1.0, 0.0, 0.0], FRAC_PI_2);
+
+		// i * j = k
+		assert_quaternion_eq(Quaternion::new(0.0, 0.0, 0.0, 1.0), Quaternion::new(0.0, 1.0, 0.0, 0.0) * Quaternion::new(0.0, 0.0, 1.0, 0.0));
+
+		// rotates by x90 first, x -> x -> y
+		assert_vector_eq([0.0, 1.0, 0.0], (z90 * x90).rotate_vector([1.0, 0.0, 0.0]));
+		// rotates by z90 first, x -> y -> z
+		assert_vector_eq([0.0, 0.0, 1.0], (x90 * z90).rotate_vector([1.0, 0.0, 0.0]));
+
+		assert_matrix_eq(z90.to_rotation_matrix() * x90.to_rotation_matrix(), (z90 * x90).to_rotation_matrix());
+	}
+
+	#[test]
+	fn test_conjugate() {
+		let q = Quaternion::from_axis_angle([1.0, -2.0, 0.5], 1.2);
+		assert_eq!(Quaternion::new(q.w, -q.x, -q.y, -q.z), q.conjugate());
+		assert_quaternion_eq(Quaternion::identity(), q * q.conjugate());
+
+		// the inverse rotation
+		let z90 = Quaternion::from_axis_angle([0.0, 0.0, 1.0], FRAC_PI_2);
+		assert_vector_eq([0.0, -1.0, 0.0], z90.conjugate().rotate_vector([1.0, 0.0, 0.0]));
+	}
+
+	#[test]
+	fn test_normalize() {
+		assert_quaternion_eq(Quaternion::identity(), Quaternion::new(2.0, 0.0, 0.0, 0.0).normalize());
+		assert_quaternion_eq(Quaternion::new(0.5, 0.5, 0.5, 0.5), Quaternion::new(3.0, 3.0, 3.0, 3.0).normalize());
+		assert!((1.0 - Quaternion::new(0.1, -2.0, 4.0, 0.3).normalize().norm()).abs() < 1e-5);
+		assert_eq!(Quaternion::identity(), Quaternion::new(0.0, 0.0, 0.0, 0.0).normalize());
+	}
+}
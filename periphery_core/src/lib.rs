@@ -31,6 +31,12 @@ pub extern crate terminal_cli;
 
 extern crate packed_struct;
 
+#[cfg(feature="serde")]
+extern crate serde;
+
+#[cfg(all(test, feature="serde"))]
+extern crate serde_json;
+
 //#[macro_use]
 //extern crate packed_struct_codegen;
 
@@ -44,19 +50,30 @@ pub mod system;
 
 #[macro_use]
 mod register;
+#[macro_use]
+pub mod register_fields;
 
 //#[macro_use]
 //mod bus_registers;
 
 pub mod device;
+pub mod device_id;
+pub mod device_info;
+pub mod self_test;
+pub mod data_stream;
+pub mod sensor_streams;
 pub mod device_factory;
 pub mod device_storage;
 pub mod units;
+#[cfg(feature="serde")]
+mod units_serde;
+pub mod geometry;
 
 // pub mod buspirate;
 
 pub mod utils;
 
 pub use register::*;
+pub use register_fields::*;
 pub use base::*;
 
@@ -11,7 +11,8 @@ pub use core::num::Wrapping;
 pub use core::cmp::*;
 pub use core::mem;
 pub use core::intrinsics::write_bytes;
-pub use core::ops::{Index, IndexMut, Deref};
+pub use core::ops::{Index, IndexMut, Deref, DerefMut};
+pub use core::ops::{Add, Sub, Mul, Div, Neg};
 
 pub use alloc::rc::Rc;
 pub use alloc::arc::Arc;
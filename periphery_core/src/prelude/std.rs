@@ -20,7 +20,8 @@ pub use std::io;
 pub use std::io::Write;
 pub use std::sync::Arc;
 pub use std::str::from_utf8;
-pub use std::ops::{Index, IndexMut, Deref};
+pub use std::ops::{Index, IndexMut, Deref, DerefMut};
+pub use std::ops::{Add, Sub, Mul, Div, Neg};
 pub use std::any::Any;
 pub use std::cmp;
 pub use std::borrow::*;
@@ -33,12 +34,22 @@ pub use ::bus::device_bus::device::*;
 pub use ::bus::device_bus::i2c::*;
 pub use ::bus::device_bus::commands::*;
 pub use ::bus::device_bus::registers::*;
+pub use ::bus::device_bus::snapshot::*;
+pub use ::bus::device_bus::register_map::*;
+pub use ::bus::device_bus::cached::*;
+pub use ::bus::device_bus::expectations::*;
 pub use ::bus::i2c::*;
 pub use ::bus::spi::*;
 
 
 pub use ::base::*;
 pub use ::device::*;
+pub use ::device_id::*;
+pub use ::device_info::*;
+pub use ::self_test::*;
+pub use ::data_stream::*;
+pub use ::sensor_streams::*;
 pub use ::device_factory::*;
 pub use ::system::*;
-pub use ::units::*;
\ No newline at end of file
+pub use ::units::*;
+pub use ::geometry::*;
\ No newline at end of file
@@ -0,0 +1,394 @@
+//! Named access to the individual fields of a packed register, used by the
+//! `register/<name>/get` and `register/<name>/set` commands.
+
+use prelude::v1::*;
+use base::*;
+
+use packed_struct::prelude::*;
+
+/// A register type whose fields can be read and changed by their names.
+/// Implemented with the `register_fields!` macro.
+pub trait RegisterFields: Sized {
+	fn field_names() -> &'static [&'static str];
+	fn get_field(&self, field: &str) -> Result<String, PeripheryError>;
+	fn set_field(&mut self, field: &str, value: &str) -> Result<(), PeripheryError>;
+	fn get_field_kind(&self, field: &str) -> Result<RegisterFieldKind, PeripheryError>;
+}
+
+/// The type of a register field, as seen from the outside.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RegisterFieldKind {
+	Bool,
+	Unsigned { bits: u8 },
+	Signed { bits: u8 },
+	/// Variant names and their raw values.
+	Enum { variants: Vec<(String, u8)> }
+}
+
+impl RegisterFieldKind {
+	pub fn name(&self) -> &'static str {
+		match *self {
+			RegisterFieldKind::Bool => "bool",
+			RegisterFieldKind::Unsigned { .. } => "unsigned",
+			RegisterFieldKind::Signed { .. } => "signed",
+			RegisterFieldKind::Enum { .. } => "enum"
+		}
+	}
+
+	/// Values that, set one after another, touch every bit of the field.
+	pub fn probe_values(&self) -> Vec<String> {
+		match *self {
+			RegisterFieldKind::Bool => vec!["true".into()],
+			RegisterFieldKind::Unsigned { bits } => {
+				let max = if bits >= 64 { u64::max_value() } else { (1u64 << bits) - 1 };
+				vec![format!("{}", max)]
+			},
+			RegisterFieldKind::Signed { .. } => vec!["-1".into()],
+			RegisterFieldKind::Enum { ref variants } => variants.iter().map(|&(_, v)| format!("{}", v)).collect()
+		}
+	}
+}
+
+/// A value of a single register field, convertible from and to its text representation.
+pub trait RegisterFieldValue: Sized {
+	fn parse_field(value: &str) -> Result<Self, PeripheryError>;
+	fn format_field(&self) -> String;
+	fn field_kind() -> RegisterFieldKind;
+}
+
+impl RegisterFieldValue for bool {
+	fn parse_field(value: &str) -> Result<Self, PeripheryError> {
+		match value {
+			"true" | "1" => Ok(true),
+			"false" | "0" => Ok(false),
+			_ => Err(PeripheryError::UnsupportedFieldValue)
+		}
+	}
+
+	fn format_field(&self) -> String {
+		format!("{}", self)
+	}
+
+	fn field_kind() -> RegisterFieldKind {
+		RegisterFieldKind::Bool
+	}
+}
+
+fn parse_field_u64(value: &str) -> Result<u64, PeripheryError> {
+	let r = if value.starts_with("0x") {
+		u64::from_str_radix(&value[2..], 16)
+	} else if value.starts_with("0b") {
+		u64::from_str_radix(&value[2..], 2)
+	} else {
+		u64::from_str(value)
+	};
+
+	r.map_err(|_| PeripheryError::UnsupportedFieldValue)
+}
+
+fn parse_field_i64(value: &str) -> Result<i64, PeripheryError> {
+	if value.starts_with("-") {
+		let v = parse_field_u64(&value[1..])?;
+		if v > (i64::max_value() as u64) {
+			return Err(PeripheryError::UnsupportedFieldValue);
+		}
+		Ok(-(v as i64))
+	} else {
+		let v = parse_field_u64(value)?;
+		if v > (i64::max_value() as u64) {
+			return Err(PeripheryError::UnsupportedFieldValue);
+		}
+		Ok(v as i64)
+	}
+}
+
+macro_rules! register_field_unsigned {
+	($($T: ty),*) => (
+		$(
+			impl RegisterFieldValue for $T {
+				fn parse_field(value: &str) -> Result<Self, PeripheryError> {
+					let v = parse_field_u64(value)?;
+					if v > (<$T>::max_value() as u64) {
+						return Err(PeripheryError::UnsupportedFieldValue);
+					}
+					Ok(v as $T)
+				}
+
+				fn format_field(&self) -> String {
+					format!("{}", self)
+				}
+
+				fn field_kind() -> RegisterFieldKind {
+					RegisterFieldKind::Unsigned { bits: (mem::size_of::<$T>() * 8) as u8 }
+				}
+			}
+
+			impl<B> RegisterFieldValue for Integer<$T, B> where B: NumberOfBits {
+				fn parse_field(value: &str) -> Result<Self, PeripheryError> {
+					let v = parse_field_u64(value)?;
+					if (v >> B::number_of_bits()) != 0 {
+						return Err(PeripheryError::UnsupportedFieldValue);
+					}
+					Ok((v as $T).into())
+				}
+
+				fn format_field(&self) -> String {
+					format!("{}", **self)
+				}
+
+				fn field_kind() -> RegisterFieldKind {
+					RegisterFieldKind::Unsigned { bits: B::number_of_bits() }
+				}
+			}
+		)*
+	)
+}
+
+register_field_unsigned!(u8, u16, u32);
+
+macro_rules! register_field_signed {
+	($($T: ty),*) => (
+		$(
+			impl RegisterFieldValue for $T {
+				fn parse_field(value: &str) -> Result<Self, PeripheryError> {
+					let v = parse_field_i64(value)?;
+					if v > (<$T>::max_value() as i64) || v < (<$T>::min_value() as i64) {
+						return Err(PeripheryError::UnsupportedFieldValue);
+					}
+					Ok(v as $T)
+				}
+
+				fn format_field(&self) -> String {
+					format!("{}", self)
+				}
+
+				fn field_kind() -> RegisterFieldKind {
+					RegisterFieldKind::Signed { bits: (mem::size_of::<$T>() * 8) as u8 }
+				}
+			}
+		)*
+	)
+}
+
+register_field_signed!(i8, i16, i32);
+
+/// Parse an enum field by its variant name, or by its numerical value.
+pub fn parse_enum_field<E>(value: &str) -> Result<E, PeripheryError> where E: PrimitiveEnum<Primitive = u8> + Debug {
+	for i in 0..256 {
+		if let Some(e) = E::from_primitive(i as u8) {
+			if format!("{:?}", e).eq_ignore_ascii_case(value) {
+				return Ok(e);
+			}
+		}
+	}
+
+	let v = parse_field_u64(value)?;
+	if v > (u8::max_value() as u64) {
+		return Err(PeripheryError::UnsupportedFieldValue);
+	}
+	E::from_primitive(v as u8).ok_or(PeripheryError::UnsupportedFieldValue)
+}
+
+/// All the variant names of an enum field, with their raw values.
+pub fn enum_field_variants<E>() -> Vec<(String, u8)> where E: PrimitiveEnum<Primitive = u8> + Debug {
+	let mut ret = vec![];
+	for i in 0..256 {
+		if let Some(e) = E::from_primitive(i as u8) {
+			ret.push((format!("{:?}", e), i as u8));
+		}
+	}
+	ret
+}
+
+/// Implement `RegisterFieldValue` for enums that derive `PrimitiveEnum_u8`. The
+/// fields are set using their variant names.
+#[macro_export]
+macro_rules! register_field_enums {
+	($($T: ty),+) => (
+		$(
+			impl $crate::register_fields::RegisterFieldValue for $T {
+				fn parse_field(value: &str) -> Result<Self, $crate::PeripheryError> {
+					$crate::register_fields::parse_enum_field(value)
+				}
+
+				fn format_field(&self) -> String {
+					format!("{:?}", self)
+				}
+
+				fn field_kind() -> $crate::register_fields::RegisterFieldKind {
+					$crate::register_fields::RegisterFieldKind::Enum { variants: $crate::register_fields::enum_field_variants::<Self>() }
+				}
+			}
+		)+
+	)
+}
+
+/// Wraps the definition of a packed register struct and implements `RegisterFields`
+/// for it, so the field table is always the struct's own list of fields. Every field's
+/// type has to implement `RegisterFieldValue`.
+///
+/// ```ignore
+/// register_fields! {
+///     #[derive(PackedStruct, Debug, Copy, Clone)]
+///     #[packed_struct(size_bytes="1", bit_numbering="lsb0")]
+///     pub struct StatusRegister {
+///         #[packed_field(bits="3")]
+///         pub measuring: bool,
+///         #[packed_field(bits="0")]
+///         pub im_update: bool
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! register_fields {
+	(
+		$(#[$attr: meta])*
+		pub struct $T: ident { $($body: tt)* }
+	) => (
+		$(#[$attr])*
+		pub struct $T { $($body)* }
+
+		register_fields!(@field $T [] $($body)*);
+	);
+
+	// Collects the field names. The body is passed to the derives untouched, so the
+	// types are skipped token by token instead of being matched as `ty`.
+	(@field $T: ident [$($field: ident)*] $(#[$field_attr: meta])* pub $name: ident : $($rest: tt)*) => (
+		register_fields!(@type $T [$($field)* $name] $($rest)*);
+	);
+	(@field $T: ident [$($field: ident)*]) => (
+		register_fields!(@impl $T [$($field)*]);
+	);
+	// a comma between generic arguments of a type
+	(@field $T: ident [$($field: ident)*] $skip: tt $($rest: tt)*) => (
+		register_fields!(@type $T [$($field)*] $($rest)*);
+	);
+	(@type $T: ident [$($field: ident)*] , $($rest: tt)*) => (
+		register_fields!(@field $T [$($field)*] $($rest)*);
+	);
+	(@type $T: ident [$($field: ident)*] $skip: tt $($rest: tt)*) => (
+		register_fields!(@type $T [$($field)*] $($rest)*);
+	);
+	(@type $T: ident [$($field: ident)*]) => (
+		register_fields!(@impl $T [$($field)*]);
+	);
+
+	(@impl $T: ident [$($field: ident)+]) => (
+		impl $crate::register_fields::RegisterFields for $T {
+			fn field_names() -> &'static [&'static str] {
+				&[$(stringify!($field)),+]
+			}
+
+			fn get_field(&self, field: &str) -> Result<String, $crate::PeripheryError> {
+				use $crate::register_fields::RegisterFieldValue;
+
+				$(
+					if field == stringify!($field) {
+						return Ok(self.$field.format_field());
+					}
+				)+
+
+				Err($crate::PeripheryError::UnknownRegisterField)
+			}
+
+			fn set_field(&mut self, field: &str, value: &str) -> Result<(), $crate::PeripheryError> {
+				use $crate::register_fields::RegisterFieldValue;
+
+				$(
+					if field == stringify!($field) {
+						self.$field = RegisterFieldValue::parse_field(value)?;
+						return Ok(());
+					}
+				)+
+
+				Err($crate::PeripheryError::UnknownRegisterField)
+			}
+
+			fn get_field_kind(&self, field: &str) -> Result<$crate::register_fields::RegisterFieldKind, $crate::PeripheryError> {
+				fn kind<V: $crate::register_fields::RegisterFieldValue>(_: &V) -> $crate::register_fields::RegisterFieldKind {
+					V::field_kind()
+				}
+
+				$(
+					if field == stringify!($field) {
+						return Ok(kind(&self.$field));
+					}
+				)+
+
+				Err($crate::PeripheryError::UnknownRegisterField)
+			}
+		}
+	)
+}
+
+/// Apply a list of `field=value` assignments, separated by whitespace.
+pub fn set_register_fields<T: RegisterFields>(register: &mut T, assignments: &str) -> Result<(), PeripheryError> {
+	let mut any = false;
+	for assignment in assignments.split_whitespace() {
+		let mut parts = assignment.splitn(2, '=');
+		let field = parts.next().ok_or(PeripheryError::ParseError)?;
+		let value = parts.next().ok_or(PeripheryError::ParseError)?;
+		register.set_field(field, value)?;
+		any = true;
+	}
+
+	if !any {
+		return Err(PeripheryError::ParseError);
+	}
+
+	Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	register_fields! {
+		#[derive(Debug, Copy, Clone, PartialEq)]
+		pub struct TestRegister {
+			pub enabled: bool,
+			/// Documented fields are fine
+			pub rate: u8,
+			pub offset: i16,
+		}
+	}
+
+	/// The expansion names everything through `$crate`, nothing has to be imported.
+	mod without_imports {
+		register_fields! {
+			#[derive(Debug, Copy, Clone, PartialEq)]
+			pub struct BareRegister {
+				pub enabled: bool
+			}
+		}
+	}
+
+	#[test]
+	fn test_register_fields() {
+		let mut r = TestRegister { enabled: false, rate: 10, offset: 0 };
+
+		assert_eq!(&["enabled", "rate", "offset"], TestRegister::field_names());
+		assert_eq!("10", r.get_field("rate").unwrap());
+
+		set_register_fields(&mut r, "enabled=true rate=0x20 offset=-5").unwrap();
+		assert_eq!(TestRegister { enabled: true, rate: 0x20, offset: -5 }, r);
+
+		assert!(r.set_field("rate", "256").is_err());
+		assert!(r.set_field("enabled", "maybe").is_err());
+		assert!(r.set_field("missing", "1").is_err());
+		assert!(r.get_field("missing").is_err());
+		assert!(set_register_fields(&mut r, "rate").is_err());
+
+		assert_eq!(RegisterFieldKind::Signed { bits: 16 }, r.get_field_kind("offset").unwrap());
+		assert_eq!(vec!["255".to_string()], r.get_field_kind("rate").unwrap().probe_values());
+	}
+
+	#[test]
+	fn test_register_fields_without_imports() {
+		let mut r = without_imports::BareRegister { enabled: false };
+		r.set_field("enabled", "true").unwrap();
+		assert_eq!("true", r.get_field("enabled").unwrap());
+		assert!(r.get_field("missing").is_err());
+	}
+}
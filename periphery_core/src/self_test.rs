@@ -0,0 +1,113 @@
+//! Structured results of the on-chip self-tests, for production test benches.
+
+use prelude::v1::*;
+use base::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelfTestCheck {
+	pub name: Cow<'static, str>,
+	pub passed: bool,
+	/// The measured value, if the check has one
+	pub value: Option<f32>,
+	pub min: Option<f32>,
+	pub max: Option<f32>,
+	pub message: Option<Cow<'static, str>>
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SelfTestReport {
+	pub checks: Vec<SelfTestCheck>
+}
+
+impl SelfTestReport {
+	pub fn new() -> Self {
+		Default::default()
+	}
+
+	/// All the checks have passed. An empty report doesn't pass.
+	pub fn passed(&self) -> bool {
+		self.checks.len() > 0 && self.checks.iter().all(|c| c.passed)
+	}
+
+	pub fn add_check<N: Into<Cow<'static, str>>>(&mut self, name: N, passed: bool) -> &mut Self {
+		self.checks.push(SelfTestCheck {
+			name: name.into(),
+			passed: passed,
+			value: None,
+			min: None,
+			max: None,
+			message: None
+		});
+		self
+	}
+
+	/// Passes if the value is within the inclusive limits.
+	pub fn add_range<N: Into<Cow<'static, str>>>(&mut self, name: N, value: f32, min: f32, max: f32) -> &mut Self {
+		self.checks.push(SelfTestCheck {
+			name: name.into(),
+			passed: value >= min && value <= max,
+			value: Some(value),
+			min: Some(min),
+			max: Some(max),
+			message: None
+		});
+		self
+	}
+
+	pub fn add_error<N: Into<Cow<'static, str>>>(&mut self, name: N, error: &PeripheryError) -> &mut Self {
+		self.checks.push(SelfTestCheck {
+			name: name.into(),
+			passed: false,
+			value: None,
+			min: None,
+			max: None,
+			message: Some(format!("{:?}", error).into())
+		});
+		self
+	}
+}
+
+impl Display for SelfTestReport {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		for check in &self.checks {
+			try!(write!(f, "[{}] {}", if check.passed { "PASS" } else { "FAIL" }, check.name));
+			if let Some(value) = check.value {
+				try!(write!(f, ": {}", value));
+			}
+			if let (Some(min), Some(max)) = (check.min, check.max) {
+				try!(write!(f, " (limits {} .. {})", min, max));
+			}
+			if let Some(ref message) = check.message {
+				try!(write!(f, ", {}", message));
+			}
+			try!(write!(f, "\r\n"));
+		}
+
+		write!(f, "Self-test {}\r\n", if self.passed() { "PASSED" } else { "FAILED" })
+	}
+}
+
+pub trait SelfTest {
+	/// Run the on-chip self-test. Failed checks are a part of the report, errors are
+	/// returned only if the test couldn't be performed at all. The device has to be
+	/// initialized again afterwards, unless the implementation restores it.
+	fn self_test(&self) -> Result<SelfTestReport, PeripheryError>;
+}
+
+
+#[test]
+fn test_self_test_report() {
+	let mut report = SelfTestReport::new();
+	assert!(!report.passed());
+
+	report.add_check("crc", true).add_range("x", 450.0, 243.0, 575.0);
+	assert!(report.passed());
+
+	report.add_range("y", 600.0, 243.0, 575.0);
+	assert!(!report.passed());
+
+	let s = format!("{}", report);
+	assert!(s.contains("[PASS] crc\r\n"));
+	assert!(s.contains("[FAIL] y: 600 (limits 243 .. 575)\r\n"));
+	assert!(s.ends_with("Self-test FAILED\r\n"));
+}
@@ -0,0 +1,189 @@
+//! Data streams derived from the sensor capabilities of any device, so every sensor
+//! can be polled and recorded without driver support. Drivers can still provide their
+//! own streams with `Device::get_data_streams`, for example high-rate burst reads.
+//! Those replace the generic streams with the same CLI id.
+
+use prelude::v1::*;
+use base::*;
+use device::*;
+use data_stream::*;
+use system::*;
+
+/// The generic streams use ids above this one, to stay out of the way of the drivers.
+pub const SENSOR_STREAM_ID_BASE: usize = 1000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SensorStreamKind {
+	AmbientLight = 0,
+	AmbientTemperature = 1,
+	AtmosphericPressure = 2,
+	AtmosphericHumidity = 3,
+	Acceleration3 = 4,
+	MagneticField3 = 5,
+	AngularSpeed3 = 6
+}
+
+const ALL_SENSOR_STREAMS: [SensorStreamKind; 7] = [
+	SensorStreamKind::AmbientLight,
+	SensorStreamKind::AmbientTemperature,
+	SensorStreamKind::AtmosphericPressure,
+	SensorStreamKind::AtmosphericHumidity,
+	SensorStreamKind::Acceleration3,
+	SensorStreamKind::MagneticField3,
+	SensorStreamKind::AngularSpeed3
+];
+
+impl SensorStreamKind {
+	pub fn all() -> &'static [SensorStreamKind] {
+		&ALL_SENSOR_STREAMS
+	}
+
+	pub fn get_id(&self) -> DataStreamId {
+		DataStreamId(SENSOR_STREAM_ID_BASE + *self as usize)
+	}
+
+	pub fn from_id(id: DataStreamId) -> Option<SensorStreamKind> {
+		Self::all().iter().find(|k| k.get_id() == id).cloned()
+	}
+
+	/// Does the device have the sensor this stream reads from.
+	pub fn is_available(&self, device: &Device) -> bool {
+		match *self {
+			SensorStreamKind::AmbientLight => device.get_ambient_light_sensor().is_some(),
+			SensorStreamKind::AmbientTemperature => device.get_ambient_temperature_sensor().is_some(),
+			SensorStreamKind::AtmosphericPressure => device.get_atmospheric_pressure_sensor().is_some(),
+			SensorStreamKind::AtmosphericHumidity => device.get_atmospheric_humidity_sensor().is_some(),
+			SensorStreamKind::Acceleration3 => device.get_acceleration_3_sensor().is_some(),
+			SensorStreamKind::MagneticField3 => device.get_magnetic_field_3_sensor().is_some(),
+			SensorStreamKind::AngularSpeed3 => device.get_angular_speed_3_sensor().is_some()
+		}
+	}
+
+	pub fn get_info(&self) -> DataStream {
+		let (cli_id, description, poll_every_ms, labels, unit): (&'static str, &'static str, usize, &[&'static str], &'static str) = match *self {
+			SensorStreamKind::AmbientLight => ("ambient_light", "Ambient light", 100, &["illuminance"], "lx"),
+			SensorStreamKind::AmbientTemperature => ("ambient_temperature", "Ambient temperature", 1000, &["temperature"], "°C"),
+			SensorStreamKind::AtmosphericPressure => ("atmospheric_pressure", "Atmospheric pressure", 100, &["pressure"], "Pa"),
+			SensorStreamKind::AtmosphericHumidity => ("atmospheric_humidity", "Relative humidity", 1000, &["humidity"], "%"),
+			SensorStreamKind::Acceleration3 => ("acceleration_3", "Acceleration", 10, &["x", "y", "z"], "g"),
+			SensorStreamKind::MagneticField3 => ("magnetic_field_3", "Magnetic field", 50, &["x", "y", "z"], "G"),
+			SensorStreamKind::AngularSpeed3 => ("angular_speed_3", "Angular speed", 10, &["x", "y", "z"], "°/s")
+		};
+
+		DataStream {
+			id: self.get_id(),
+			cli_id: cli_id.into(),
+			description: description.into(),
+			poll_every_ms: poll_every_ms,
+			channels: labels.iter().map(|l| DataStreamChannel::new(*l, DataStreamChannelType::F32).with_unit(unit)).collect()
+		}
+	}
+
+	/// A single reading, in the units of the stream's channels.
+	pub fn read(&self, device: &Device) -> Result<Vec<f32>, PeripheryError> {
+		match *self {
+			SensorStreamKind::AmbientLight => {
+				let s = device.get_ambient_light_sensor().ok_or(PeripheryError::NotImplemented)?;
+				Ok(vec![s.get_ambient_light()?.get_lux()])
+			},
+			SensorStreamKind::AmbientTemperature => {
+				let s = device.get_ambient_temperature_sensor().ok_or(PeripheryError::NotImplemented)?;
+				Ok(vec![s.get_ambient_temperature()?.get_temperature().get_degrees_celsius()])
+			},
+			SensorStreamKind::AtmosphericPressure => {
+				let s = device.get_atmospheric_pressure_sensor().ok_or(PeripheryError::NotImplemented)?;
+				Ok(vec![s.get_atmospheric_pressure()?.get_pressure().get_pascal()])
+			},
+			SensorStreamKind::AtmosphericHumidity => {
+				let s = device.get_atmospheric_humidity_sensor().ok_or(PeripheryError::NotImplemented)?;
+				Ok(vec![s.get_relative_atmospheric_humidity()?.get_percentage().get_percentage()])
+			},
+			SensorStreamKind::Acceleration3 => {
+				let s = device.get_acceleration_3_sensor().ok_or(PeripheryError::NotImplemented)?;
+				let a = s.get_acceleration_3()?;
+				Ok(vec![a.get_x().get_g_force(), a.get_y().get_g_force(), a.get_z().get_g_force()])
+			},
+			SensorStreamKind::MagneticField3 => {
+				let s = device.get_magnetic_field_3_sensor().ok_or(PeripheryError::NotImplemented)?;
+				let m = s.get_magnetic_field_3()?;
+				Ok(vec![m.get_x().get_gauss(), m.get_y().get_gauss(), m.get_z().get_gauss()])
+			},
+			SensorStreamKind::AngularSpeed3 => {
+				let s = device.get_angular_speed_3_sensor().ok_or(PeripheryError::NotImplemented)?;
+				let w = s.get_angular_speed_3()?;
+				Ok(vec![w.get_x().get_degrees_per_second(), w.get_y().get_degrees_per_second(), w.get_z().get_degrees_per_second()])
+			}
+		}
+	}
+}
+
+/// Reads a single sensor of the polled device.
+pub struct SensorStreamPoller {
+	kind: SensorStreamKind
+}
+
+impl SensorStreamPoller {
+	pub fn new(kind: SensorStreamKind) -> Self {
+		SensorStreamPoller {
+			kind: kind
+		}
+	}
+}
+
+impl DataStreamPoller for SensorStreamPoller {
+	fn get_info(&self) -> DataStream {
+		self.kind.get_info()
+	}
+
+	fn poll(&mut self, device: &Device, clock: &SystemApiClock) -> Result<Vec<DataStreamPolled>, PeripheryError> {
+		let values = self.kind.read(device)?;
+		Ok(vec![DataStreamPolled::from_f32(clock.get_timestamp_us(), &values)])
+	}
+}
+
+/// The native streams of the device, combined with the generic streams of its sensors.
+pub struct DeviceDataStreams<'a> {
+	device: &'a Device
+}
+
+impl<'a> DeviceDataStreams<'a> {
+	pub fn new(device: &'a Device) -> Self {
+		DeviceDataStreams {
+			device: device
+		}
+	}
+}
+
+impl<'a> DataStreams for DeviceDataStreams<'a> {
+	fn get_stream_infos(&self) -> Vec<DataStream> {
+		let mut streams = self.device.get_data_streams().map(|d| d.get_stream_infos()).unwrap_or(vec![]);
+
+		for kind in SensorStreamKind::all() {
+			if !kind.is_available(self.device) {
+				continue;
+			}
+
+			let info = kind.get_info();
+			if streams.iter().any(|s| s.cli_id == info.cli_id) {
+				continue;
+			}
+
+			streams.push(info);
+		}
+
+		streams
+	}
+
+	fn get_poller(&self, stream: DataStreamId) -> Result<Box<DataStreamPoller + Send + Sync>, PeripheryError> {
+		if let Some(native) = self.device.get_data_streams() {
+			if native.get_stream_infos().iter().any(|s| s.id == stream) {
+				return native.get_poller(stream);
+			}
+		}
+
+		match SensorStreamKind::from_id(stream) {
+			Some(kind) if kind.is_available(self.device) => Ok(Box::new(SensorStreamPoller::new(kind))),
+			_ => Err(PeripheryError::NotImplemented)
+		}
+	}
+}
@@ -13,6 +13,10 @@ pub trait SystemApi : Send + Sync + Clone {
 		Err(PeripheryError::NotImplemented)
 	}
 
+	fn get_clock(&self) -> Result<&SystemApiClock, PeripheryError> {
+		Err(PeripheryError::NotImplemented)
+	}
+
 
 	fn trace(&self, line: &str) {
 		if let Ok(debug) = self.get_debug() {
@@ -37,6 +41,12 @@ pub trait SystemApiSleep {
 	fn sleep_ms(&self, ms: u32);
 }
 
+pub trait SystemApiClock {
+	/// Microseconds since the UNIX epoch, or since the start of the system if the wall
+	/// clock isn't available.
+	fn get_timestamp_us(&self) -> u64;
+}
+
 pub trait SystemApiDebug {
 	fn debug(&self, line: &str);
 }
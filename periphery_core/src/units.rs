@@ -1,6 +1,7 @@
 //! Physical SI units for sensors to report their readings.
 
 use prelude::v1::*;
+use geometry::*;
 
 #[derive(Copy, Clone)]
 pub struct Temperature {
@@ -8,7 +9,7 @@ pub struct Temperature {
 	unit: TemperatureUnit
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum TemperatureUnit {
 	DegreesCelsius,
 	Kelvin
@@ -38,6 +39,12 @@ impl Temperature {
 			TemperatureUnit::Kelvin => self.val
 		}
 	}
+
+	/// The unit the temperature was measured in.
+	#[inline]
+	pub fn get_unit(&self) -> TemperatureUnit {
+		self.unit
+	}
 	#[inline]
 	pub fn to_degrees_celsius(&self) -> Temperature {
 		Temperature::from_degrees_celsius(self.get_degrees_celsius())
@@ -133,6 +140,83 @@ impl Display for ColorTemperature {
     }
 }
 
+/// The measured color, as the share of each channel in their sum.
+#[derive(Copy, Clone, Default)]
+pub struct Color {
+	red: f32,
+	green: f32,
+	blue: f32
+}
+
+impl Color {
+	/// The channels can be in any scale, for example the raw ADC counts.
+	pub fn from_rgb(red: f32, green: f32, blue: f32) -> Color {
+		let sum = red + green + blue;
+		if sum <= 0.0 {
+			return Color::default();
+		}
+
+		Color { red: red / sum, green: green / sum, blue: blue / sum }
+	}
+	#[inline]
+	pub fn get_red(&self) -> f32 {
+		self.red
+	}
+	#[inline]
+	pub fn get_green(&self) -> f32 {
+		self.green
+	}
+	#[inline]
+	pub fn get_blue(&self) -> f32 {
+		self.blue
+	}
+
+	fn self_fmt(&self, f: &mut Formatter) -> fmt::Result {
+		write!(f, "Color: R {:.3}, G {:.3}, B {:.3}", self.red, self.green, self.blue)
+	}
+}
+
+impl Debug for Color {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	self.self_fmt(f)
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	self.self_fmt(f)
+    }
+}
+
+
+/// Relative distance to the nearest object, 0% when nothing is in range and 100% at
+/// the closest distance the sensor can resolve.
+#[derive(Copy, Clone)]
+pub struct Proximity { percentage: Percentage }
+
+impl Proximity {
+	#[inline]
+	pub fn from_percentage(p: Percentage) -> Proximity {
+		Proximity { percentage: p }
+	}
+	#[inline]
+	pub fn get_percentage(&self) -> Percentage {
+		self.percentage
+	}
+}
+
+impl Debug for Proximity {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Proximity: {:?}", self.percentage)
+    }
+}
+
+impl Display for Proximity {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	write!(f, "Proximity: {}", self.percentage)
+    }
+}
+
 
 
 
@@ -548,6 +632,442 @@ impl Display for AngularSpeed3 {
 }
 
 
+/// Vector math for the 3-axis readings. Lengths are in the unit of the axes, rotations
+/// take a `Matrix3` or a `Quaternion`.
+macro_rules! units_vector3 {
+	($vector: ident, $axis: ident, $from: ident, $get: ident) => {
+		impl $vector {
+			#[inline]
+			pub fn from_array(v: [f32; 3]) -> Self {
+				$vector {
+					x: $axis::$from(v[0]),
+					y: $axis::$from(v[1]),
+					z: $axis::$from(v[2])
+				}
+			}
+
+			#[inline]
+			pub fn to_array(&self) -> [f32; 3] {
+				[self.x.$get(), self.y.$get(), self.z.$get()]
+			}
+
+			pub fn norm(&self) -> $axis {
+				$axis::$from(self.dot(self).sqrt())
+			}
+
+			/// The same direction with a length of 1. A zero vector stays zero.
+			pub fn normalize(&self) -> Self {
+				let n = self.norm().$get();
+				if n == 0.0 {
+					return *self;
+				}
+				*self / n
+			}
+
+			pub fn dot(&self, other: &Self) -> f32 {
+				let (a, b) = (self.to_array(), other.to_array());
+				a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+			}
+
+			pub fn cross(&self, other: &Self) -> Self {
+				let (a, b) = (self.to_array(), other.to_array());
+				Self::from_array([
+					a[1] * b[2] - a[2] * b[1],
+					a[2] * b[0] - a[0] * b[2],
+					a[0] * b[1] - a[1] * b[0]
+				])
+			}
+
+			pub fn rotate(&self, m: &Matrix3) -> Self {
+				Self::from_array(m.mul_vector(self.to_array()))
+			}
+
+			pub fn rotate_by_quaternion(&self, q: &Quaternion) -> Self {
+				Self::from_array(q.rotate_vector(self.to_array()))
+			}
+
+			/// Component-wise minimum.
+			pub fn min(&self, other: &Self) -> Self {
+				let (a, b) = (self.to_array(), other.to_array());
+				Self::from_array([partial_min(a[0], b[0]), partial_min(a[1], b[1]), partial_min(a[2], b[2])])
+			}
+
+			/// Component-wise maximum.
+			pub fn max(&self, other: &Self) -> Self {
+				let (a, b) = (self.to_array(), other.to_array());
+				Self::from_array([partial_max(a[0], b[0]), partial_max(a[1], b[1]), partial_max(a[2], b[2])])
+			}
+		}
+
+		impl Add for $vector {
+			type Output = $vector;
+
+			fn add(self, other: $vector) -> $vector {
+				let (a, b) = (self.to_array(), other.to_array());
+				$vector::from_array([a[0] + b[0], a[1] + b[1], a[2] + b[2]])
+			}
+		}
+
+		impl Sub for $vector {
+			type Output = $vector;
+
+			fn sub(self, other: $vector) -> $vector {
+				let (a, b) = (self.to_array(), other.to_array());
+				$vector::from_array([a[0] - b[0], a[1] - b[1], a[2] - b[2]])
+			}
+		}
+
+		impl Neg for $vector {
+			type Output = $vector;
+
+			fn neg(self) -> $vector {
+				self * -1.0
+			}
+		}
+
+		impl Mul<f32> for $vector {
+			type Output = $vector;
+
+			fn mul(self, k: f32) -> $vector {
+				let a = self.to_array();
+				$vector::from_array([a[0] * k, a[1] * k, a[2] * k])
+			}
+		}
+
+		impl Div<f32> for $vector {
+			type Output = $vector;
+
+			fn div(self, k: f32) -> $vector {
+				let a = self.to_array();
+				$vector::from_array([a[0] / k, a[1] / k, a[2] / k])
+			}
+		}
+	};
+}
+
+units_vector3!(Acceleration3, GForce, from_g_force, get_g_force);
+units_vector3!(MagneticField3, MagneticFieldStrength, from_gauss, get_gauss);
+units_vector3!(AngularSpeed3, AngularSpeed, from_degrees_per_second, get_degrees_per_second);
+
+
+#[derive(Copy, Clone, Default)]
+pub struct Voltage { volts: f32 }
+
+impl Voltage {
+	#[inline]
+	pub fn from_volts(volts: f32) -> Voltage {
+		Voltage { volts: volts }
+	}
+	#[inline]
+	pub fn from_milli_volts(milli_volts: f32) -> Voltage {
+		Voltage { volts: milli_volts / 1000.0 }
+	}
+	#[inline]
+	pub fn get_volts(&self) -> f32 {
+		self.volts
+	}
+	#[inline]
+	pub fn get_milli_volts(&self) -> f32 {
+		self.volts * 1000.0
+	}
+}
+
+impl Debug for Voltage {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	write!(f, "{} V", self.volts)
+    }
+}
+
+impl Display for Voltage {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	write!(f, "{} V", self.volts)
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct Current { amperes: f32 }
+
+impl Current {
+	#[inline]
+	pub fn from_amperes(amperes: f32) -> Current {
+		Current { amperes: amperes }
+	}
+	#[inline]
+	pub fn from_milli_amperes(milli_amperes: f32) -> Current {
+		Current { amperes: milli_amperes / 1000.0 }
+	}
+	#[inline]
+	pub fn from_micro_amperes(micro_amperes: f32) -> Current {
+		Current { amperes: micro_amperes / 1000000.0 }
+	}
+	#[inline]
+	pub fn get_amperes(&self) -> f32 {
+		self.amperes
+	}
+	#[inline]
+	pub fn get_milli_amperes(&self) -> f32 {
+		self.amperes * 1000.0
+	}
+	#[inline]
+	pub fn get_micro_amperes(&self) -> f32 {
+		self.amperes * 1000000.0
+	}
+}
+
+impl Debug for Current {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	write!(f, "{} A", self.amperes)
+    }
+}
+
+impl Display for Current {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	write!(f, "{} A", self.amperes)
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct ElectricalPower { watts: f32 }
+
+impl ElectricalPower {
+	#[inline]
+	pub fn from_watts(watts: f32) -> ElectricalPower {
+		ElectricalPower { watts: watts }
+	}
+	#[inline]
+	pub fn from_milli_watts(milli_watts: f32) -> ElectricalPower {
+		ElectricalPower { watts: milli_watts / 1000.0 }
+	}
+	#[inline]
+	pub fn get_watts(&self) -> f32 {
+		self.watts
+	}
+	#[inline]
+	pub fn get_milli_watts(&self) -> f32 {
+		self.watts * 1000.0
+	}
+}
+
+impl Debug for ElectricalPower {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	write!(f, "{} W", self.watts)
+    }
+}
+
+impl Display for ElectricalPower {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	write!(f, "{} W", self.watts)
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct Energy { joules: f32 }
+
+impl Energy {
+	#[inline]
+	pub fn from_joules(joules: f32) -> Energy {
+		Energy { joules: joules }
+	}
+	#[inline]
+	pub fn from_watt_hours(watt_hours: f32) -> Energy {
+		Energy { joules: watt_hours * 3600.0 }
+	}
+	#[inline]
+	pub fn from_milli_watt_hours(milli_watt_hours: f32) -> Energy {
+		Energy { joules: milli_watt_hours * 3.6 }
+	}
+	#[inline]
+	pub fn get_joules(&self) -> f32 {
+		self.joules
+	}
+	#[inline]
+	pub fn get_watt_hours(&self) -> f32 {
+		self.joules / 3600.0
+	}
+	#[inline]
+	pub fn get_milli_watt_hours(&self) -> f32 {
+		self.joules / 3.6
+	}
+}
+
+impl Debug for Energy {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	write!(f, "{} J", self.joules)
+    }
+}
+
+impl Display for Energy {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	write!(f, "{} J", self.joules)
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct Resistance { ohms: f32 }
+
+impl Resistance {
+	#[inline]
+	pub fn from_ohms(ohms: f32) -> Resistance {
+		Resistance { ohms: ohms }
+	}
+	#[inline]
+	pub fn from_kilo_ohms(kilo_ohms: f32) -> Resistance {
+		Resistance { ohms: kilo_ohms * 1000.0 }
+	}
+	#[inline]
+	pub fn get_ohms(&self) -> f32 {
+		self.ohms
+	}
+	#[inline]
+	pub fn get_kilo_ohms(&self) -> f32 {
+		self.ohms / 1000.0
+	}
+}
+
+impl Debug for Resistance {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	write!(f, "{} Ohm", self.ohms)
+    }
+}
+
+impl Display for Resistance {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	write!(f, "{} Ohm", self.ohms)
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct Frequency { hertz: f32 }
+
+impl Frequency {
+	#[inline]
+	pub fn from_hertz(hertz: f32) -> Frequency {
+		Frequency { hertz: hertz }
+	}
+	#[inline]
+	pub fn from_kilo_hertz(kilo_hertz: f32) -> Frequency {
+		Frequency { hertz: kilo_hertz * 1000.0 }
+	}
+	#[inline]
+	pub fn from_mega_hertz(mega_hertz: f32) -> Frequency {
+		Frequency { hertz: mega_hertz * 1000000.0 }
+	}
+	#[inline]
+	pub fn get_hertz(&self) -> f32 {
+		self.hertz
+	}
+	#[inline]
+	pub fn get_kilo_hertz(&self) -> f32 {
+		self.hertz / 1000.0
+	}
+	#[inline]
+	pub fn get_mega_hertz(&self) -> f32 {
+		self.hertz / 1000000.0
+	}
+
+	/// The duration of a single cycle.
+	pub fn get_period(&self) -> TimeSpan {
+		TimeSpan::from_seconds(1.0 / self.hertz)
+	}
+}
+
+impl Debug for Frequency {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	write!(f, "{} Hz", self.hertz)
+    }
+}
+
+impl Display for Frequency {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	write!(f, "{} Hz", self.hertz)
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct Distance { meters: f32 }
+
+impl Distance {
+	#[inline]
+	pub fn from_meters(meters: f32) -> Distance {
+		Distance { meters: meters }
+	}
+	#[inline]
+	pub fn from_centi_meters(centi_meters: f32) -> Distance {
+		Distance { meters: centi_meters / 100.0 }
+	}
+	#[inline]
+	pub fn from_milli_meters(milli_meters: f32) -> Distance {
+		Distance { meters: milli_meters / 1000.0 }
+	}
+	#[inline]
+	pub fn get_meters(&self) -> f32 {
+		self.meters
+	}
+	#[inline]
+	pub fn get_centi_meters(&self) -> f32 {
+		self.meters * 100.0
+	}
+	#[inline]
+	pub fn get_milli_meters(&self) -> f32 {
+		self.meters * 1000.0
+	}
+}
+
+impl Debug for Distance {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	write!(f, "{} m", self.meters)
+    }
+}
+
+impl Display for Distance {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	write!(f, "{} m", self.meters)
+    }
+}
+
+/// A span of time, as a unit like the others.
+#[derive(Copy, Clone, Default)]
+pub struct TimeSpan { seconds: f32 }
+
+impl TimeSpan {
+	#[inline]
+	pub fn from_seconds(seconds: f32) -> TimeSpan {
+		TimeSpan { seconds: seconds }
+	}
+	#[inline]
+	pub fn from_milli_seconds(milli_seconds: f32) -> TimeSpan {
+		TimeSpan { seconds: milli_seconds / 1000.0 }
+	}
+	#[inline]
+	pub fn from_micro_seconds(micro_seconds: f32) -> TimeSpan {
+		TimeSpan { seconds: micro_seconds / 1000000.0 }
+	}
+	#[inline]
+	pub fn get_seconds(&self) -> f32 {
+		self.seconds
+	}
+	#[inline]
+	pub fn get_milli_seconds(&self) -> f32 {
+		self.seconds * 1000.0
+	}
+	#[inline]
+	pub fn get_micro_seconds(&self) -> f32 {
+		self.seconds * 1000000.0
+	}
+}
+
+impl Debug for TimeSpan {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	write!(f, "{} s", self.seconds)
+    }
+}
+
+impl Display for TimeSpan {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    	write!(f, "{} s", self.seconds)
+    }
+}
+
 
 #[test]
 fn test_conversions() {
@@ -555,6 +1075,45 @@ fn test_conversions() {
 	assert_eq!(0.0, std_pressure.to_altitude_m());
 }
 
+#[test]
+fn test_electrical_units() {
+	assert_eq!(3.3, Voltage::from_milli_volts(3300.0).get_volts());
+	assert_eq!(500.0, Current::from_amperes(0.5).get_milli_amperes());
+	assert_eq!(7200.0, Energy::from_watt_hours(2.0).get_joules());
+	assert_eq!(0.001, Frequency::from_kilo_hertz(1.0).get_period().get_seconds());
+	assert_eq!(12.5, Distance::from_centi_meters(1.25).get_milli_meters());
+	assert_eq!("5 V", format!("{}", Voltage::from_volts(5.0)));
+}
+
+#[test]
+fn test_vector3() {
+	fn assert_close(expected: [f32; 3], v: [f32; 3]) {
+		for i in 0..3 {
+			assert!((expected[i] - v[i]).abs() < 0.0001, "expected {:?}, got {:?}", expected, v);
+		}
+	}
+
+	let a = Acceleration3::from_array([1.0, 2.0, 2.0]);
+	let b = Acceleration3::from_array([0.0, 1.0, -1.0]);
+
+	assert_close([1.0, 3.0, 1.0], (a + b).to_array());
+	assert_close([1.0, 1.0, 3.0], (a - b).to_array());
+	assert_close([2.0, 4.0, 4.0], (a * 2.0).to_array());
+	assert_eq!(3.0, a.norm().get_g_force());
+	assert_close([1.0 / 3.0, 2.0 / 3.0, 2.0 / 3.0], a.normalize().to_array());
+	assert_eq!(0.0, a.dot(&b));
+	assert_close([-4.0, 1.0, 1.0], a.cross(&b).to_array());
+	assert_close([0.0, 1.0, -1.0], a.min(&b).to_array());
+	assert_close([1.0, 2.0, 2.0], a.max(&b).to_array());
+
+	// 90 degrees around Z, X turns into Y
+	let x = AngularSpeed3::from_array([1.0, 0.0, 0.0]);
+	let q = Quaternion::from_axis_angle([0.0, 0.0, 1.0], ::std::f32::consts::PI / 2.0);
+	assert_close([0.0, 1.0, 0.0], x.rotate_by_quaternion(&q).to_array());
+	assert_close([0.0, 1.0, 0.0], x.rotate(&q.to_rotation_matrix()).to_array());
+	assert_close([1.0, 0.0, 0.0], x.rotate(&Matrix3::identity()).to_array());
+}
+
 #[test]
 fn test_struct_size() {
 	use std::mem;
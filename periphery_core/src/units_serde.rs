@@ -0,0 +1,245 @@
+//! Serde support for the units, with the `serde` feature. Every value is written as an
+//! object with the name of its unit, for example `{"pascal": 101325.0}`, so the
+//! recordings and configuration files stay readable and unambiguous.
+
+use prelude::v1::*;
+use units::*;
+
+use serde::ser::{Serialize, Serializer, SerializeStruct};
+use serde::de::{self, Deserialize, Deserializer, Visitor, MapAccess};
+
+/// A unit that is stored as a single, named value.
+trait SerdeUnit: Sized {
+	fn name() -> &'static str;
+	/// The unit names accepted when reading
+	fn fields() -> &'static [&'static str];
+	fn to_field(&self) -> (&'static str, f32);
+	fn from_field(field: &str, value: f32) -> Option<Self>;
+}
+
+fn serialize_unit<U: SerdeUnit, S: Serializer>(unit: &U, serializer: S) -> Result<S::Ok, S::Error> {
+	let (field, value) = unit.to_field();
+	let mut s = serializer.serialize_struct(U::name(), 1)?;
+	s.serialize_field(field, &value)?;
+	s.end()
+}
+
+fn deserialize_unit<'de, U: SerdeUnit, D: Deserializer<'de>>(deserializer: D) -> Result<U, D::Error> {
+	struct UnitVisitor<U>(PhantomData<U>);
+
+	impl<'de, U: SerdeUnit> Visitor<'de> for UnitVisitor<U> {
+		type Value = U;
+
+		fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+			write!(f, "an object with one of the fields {:?}", U::fields())
+		}
+
+		fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<U, A::Error> {
+			let mut unit = None;
+			while let Some(key) = map.next_key::<String>()? {
+				let value: f32 = map.next_value()?;
+				if unit.is_some() {
+					return Err(de::Error::custom("more than one value for the unit"));
+				}
+				unit = Some(U::from_field(&key, value).ok_or_else(|| de::Error::unknown_field(&key, U::fields()))?);
+			}
+
+			unit.ok_or_else(|| de::Error::missing_field(U::fields()[0]))
+		}
+	}
+
+	deserializer.deserialize_struct(U::name(), U::fields(), UnitVisitor(PhantomData))
+}
+
+macro_rules! serde_unit_impls {
+	($unit: ident) => {
+		impl Serialize for $unit {
+			fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+				serialize_unit(self, serializer)
+			}
+		}
+
+		impl<'de> Deserialize<'de> for $unit {
+			fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+				deserialize_unit(deserializer)
+			}
+		}
+	};
+}
+
+/// Units with a single representation, `serde_unit!(Pressure, "pascal", from_pascal, get_pascal)`.
+macro_rules! serde_unit {
+	($unit: ident, $field: expr, $from: ident, $get: ident) => {
+		impl SerdeUnit for $unit {
+			fn name() -> &'static str {
+				stringify!($unit)
+			}
+
+			fn fields() -> &'static [&'static str] {
+				const FIELDS: &'static [&'static str] = &[$field];
+				FIELDS
+			}
+
+			fn to_field(&self) -> (&'static str, f32) {
+				($field, self.$get())
+			}
+
+			fn from_field(field: &str, value: f32) -> Option<Self> {
+				if field == $field { Some($unit::$from(value)) } else { None }
+			}
+		}
+
+		serde_unit_impls!($unit);
+	};
+}
+
+serde_unit!(Percentage, "percentage", from_percentage, get_percentage);
+serde_unit!(GForce, "g_force", from_g_force, get_g_force);
+serde_unit!(Illuminance, "lux", from_lux, get_lux);
+serde_unit!(Pressure, "pascal", from_pascal, get_pascal);
+serde_unit!(MagneticFieldStrength, "gauss", from_gauss, get_gauss);
+serde_unit!(AngularSpeed, "degrees_per_second", from_degrees_per_second, get_degrees_per_second);
+serde_unit!(Voltage, "volts", from_volts, get_volts);
+serde_unit!(Current, "amperes", from_amperes, get_amperes);
+serde_unit!(ElectricalPower, "watts", from_watts, get_watts);
+serde_unit!(Energy, "joules", from_joules, get_joules);
+serde_unit!(Resistance, "ohms", from_ohms, get_ohms);
+serde_unit!(Frequency, "hertz", from_hertz, get_hertz);
+serde_unit!(Distance, "meters", from_meters, get_meters);
+serde_unit!(TimeSpan, "seconds", from_seconds, get_seconds);
+
+/// Keeps the unit the temperature was measured in.
+impl SerdeUnit for Temperature {
+	fn name() -> &'static str {
+		"Temperature"
+	}
+
+	fn fields() -> &'static [&'static str] {
+		const FIELDS: &'static [&'static str] = &["degrees_celsius", "kelvin"];
+		FIELDS
+	}
+
+	fn to_field(&self) -> (&'static str, f32) {
+		match self.get_unit() {
+			TemperatureUnit::DegreesCelsius => ("degrees_celsius", self.get_degrees_celsius()),
+			TemperatureUnit::Kelvin => ("kelvin", self.get_kelvin())
+		}
+	}
+
+	fn from_field(field: &str, value: f32) -> Option<Self> {
+		match field {
+			"degrees_celsius" => Some(Temperature::from_degrees_celsius(value)),
+			"kelvin" => Some(Temperature::from_kelvin(value)),
+			_ => None
+		}
+	}
+}
+
+serde_unit_impls!(Temperature);
+
+/// The sensor readings that wrap a unit are written as the unit itself.
+macro_rules! serde_wrapper {
+	($wrapper: ident, $inner: ident, $from: ident, $get: ident) => {
+		impl Serialize for $wrapper {
+			fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+				self.$get().serialize(serializer)
+			}
+		}
+
+		impl<'de> Deserialize<'de> for $wrapper {
+			fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+				$inner::deserialize(deserializer).map($wrapper::$from)
+			}
+		}
+	};
+}
+
+serde_wrapper!(AmbientTemperature, Temperature, from_temperature, get_temperature);
+serde_wrapper!(ColorTemperature, Temperature, from_temperature, get_temperature);
+serde_wrapper!(AtmosphericPressure, Pressure, from_pressure, get_pressure);
+serde_wrapper!(RelativeHumidity, Percentage, from_percentage, get_percentage);
+serde_wrapper!(Proximity, Percentage, from_percentage, get_percentage);
+
+/// Objects with named fields, `{"x": {"g_force": 0.0}, "y": ..., "z": ...}`.
+macro_rules! serde_fields {
+	($ty: ident, $value: ty, [$($field: ident => $get: ident),+], $construct: expr) => {
+		impl Serialize for $ty {
+			fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+				let mut s = serializer.serialize_struct(stringify!($ty), [$(stringify!($field)),+].len())?;
+				$( s.serialize_field(stringify!($field), &self.$get())?; )+
+				s.end()
+			}
+		}
+
+		impl<'de> Deserialize<'de> for $ty {
+			fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+				const FIELDS: &'static [&'static str] = &[$(stringify!($field)),+];
+
+				struct FieldsVisitor;
+
+				impl<'de> Visitor<'de> for FieldsVisitor {
+					type Value = $ty;
+
+					fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+						write!(f, "an object with the fields {:?}", FIELDS)
+					}
+
+					fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<$ty, A::Error> {
+						$( let mut $field: Option<$value> = None; )+
+						while let Some(key) = map.next_key::<String>()? {
+							match key.as_str() {
+								$( stringify!($field) => { $field = Some(map.next_value()?); }, )+
+								_ => { return Err(de::Error::unknown_field(&key, FIELDS)); }
+							}
+						}
+						$( let $field = $field.ok_or_else(|| de::Error::missing_field(stringify!($field)))?; )+
+
+						Ok(($construct)($($field),+))
+					}
+				}
+
+				deserializer.deserialize_struct(stringify!($ty), FIELDS, FieldsVisitor)
+			}
+		}
+	};
+}
+
+macro_rules! serde_vector3 {
+	($vector: ident, $axis: ident) => {
+		serde_fields!($vector, $axis, [x => get_x, y => get_y, z => get_z], |x, y, z| $vector { x: x, y: y, z: z });
+	};
+}
+
+serde_vector3!(Acceleration3, GForce);
+serde_vector3!(MagneticField3, MagneticFieldStrength);
+serde_vector3!(AngularSpeed3, AngularSpeed);
+
+serde_fields!(Color, f32, [red => get_red, green => get_green, blue => get_blue], Color::from_rgb);
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json;
+
+	#[test]
+	fn test_units_serde() {
+		let p = AtmosphericPressure::from_pressure(Pressure::from_pascal(101325.0));
+		assert_eq!(r#"{"pascal":101325.0}"#, serde_json::to_string(&p).unwrap());
+
+		let t: Temperature = serde_json::from_str(r#"{"kelvin": 300.0}"#).unwrap();
+		assert_eq!(300.0, t.get_kelvin());
+		assert_eq!(r#"{"kelvin":300.0}"#, serde_json::to_string(&t).unwrap());
+		assert!(serde_json::from_str::<Temperature>(r#"{"fahrenheit": 80.0}"#).is_err());
+		assert!(serde_json::from_str::<Pressure>(r#"{}"#).is_err());
+
+		let a = Acceleration3::from_array([0.0, 0.5, 1.0]);
+		let json = serde_json::to_string(&a).unwrap();
+		assert_eq!(r#"{"x":{"g_force":0.0},"y":{"g_force":0.5},"z":{"g_force":1.0}}"#, json);
+		let a: Acceleration3 = serde_json::from_str(&json).unwrap();
+		assert_eq!([0.0, 0.5, 1.0], a.to_array());
+
+		let c: Color = serde_json::from_str(r#"{"red": 0.5, "green": 0.25, "blue": 0.25}"#).unwrap();
+		assert_eq!(0.5, c.get_red());
+	}
+}
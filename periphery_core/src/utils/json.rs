@@ -0,0 +1,36 @@
+//! Minimal JSON output helpers, for exports that don't warrant a serializer.
+
+use prelude::v1::*;
+
+/// Formats the string as a quoted and escaped JSON string literal.
+pub struct JsonStr<'a>(pub &'a str);
+
+impl<'a> Display for JsonStr<'a> {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		try!(write!(f, "\""));
+		for c in self.0.chars() {
+			match c {
+				'"' => try!(write!(f, "\\\"")),
+				'\\' => try!(write!(f, "\\\\")),
+				'\n' => try!(write!(f, "\\n")),
+				'\r' => try!(write!(f, "\\r")),
+				'\t' => try!(write!(f, "\\t")),
+				c if (c as u32) < 0x20 => try!(write!(f, "\\u{:04x}", c as u32)),
+				c => try!(write!(f, "{}", c))
+			}
+		}
+		write!(f, "\"")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_json_str() {
+		assert_eq!("\"plain\"", format!("{}", JsonStr("plain")));
+		assert_eq!("\"a \\\"b\\\" \\\\ c\\n\\t\\u0001\"", format!("{}", JsonStr("a \"b\" \\ c\n\t\u{1}")));
+		assert_eq!("\"°C\"", format!("{}", JsonStr("°C")));
+	}
+}
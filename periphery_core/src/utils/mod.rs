@@ -1,3 +1,4 @@
 pub mod flash;
 pub mod storage;
-pub mod sectors;
\ No newline at end of file
+pub mod sectors;
+pub mod json;
\ No newline at end of file
@@ -8,67 +8,107 @@ use packed_struct::*;
 
 use ::gesture_detection::*;
 
+use std::sync::Mutex;
+
 pub type Apds9960OnI2CBus<B> = Apds9960<<B as Bus>::SystemApi, <<<B as Bus>::I2C as I2CBus>::DeviceFactory as I2CBusDeviceFactory>::Registers>;
 
-registers!(
-  chip Apds9960Registers {
-    register [0x80; 1] => enable: EnableRegister,
-    register [0x81; 1] => adc_integration_time: AdcIntegrationTime,
-    register [0x83; 1] => wait_time: u8,
-    register [0x84; 4] => als_interrupt_threshold: AlsInterruptThreshold,
-    register [0x89; 1] => proximity_interrupt_low_threshold: u8,
+#[register_map]
+pub struct Apds9960Registers {
+    #[register(address = 0x80)]
+    enable: EnableRegister,
+    #[register(address = 0x81)]
+    adc_integration_time: AdcIntegrationTime,
+    #[register(address = 0x83)]
+    wait_time: u8,
+    #[register(address = 0x84, size = 4)]
+    als_interrupt_threshold: AlsInterruptThreshold,
+    #[register(address = 0x89)]
+    proximity_interrupt_low_threshold: u8,
     // is this a typo in the docs?
-    register [0x8B; 1] => proximity_interrupt_high_threshold: u8,
-    register [0x8C; 1] => persistence: Persistence,
-    register [0x8D; 1] => config1: Config1,
-    register [0x8E; 1] => proximity_pulse_count: ProximityPulseCount,
-    register [0x8F; 1] => control1: ControlRegister1,
-    register [0x90; 1] => config2: Config2,
-
-
-
-    register [0x92; 1] => id: ChipId,
-    register [0x93; 1] => status: Status,
-
-    register [0x94; 2] => channel_clear: LsbU16,
-    register [0x96; 2] => channel_red: LsbU16,
-    register [0x98; 2] => channel_green: LsbU16,
-    register [0x9A; 2] => channel_blue: LsbU16,
-
-    register [0x94; 8] => channels: RGBCData,
-
-
-
-    register [0x9C; 1] => proximity_data: u8,
-    register [0x9D; 1] => proximity_offset_up_right: u8,
-    register [0x9E; 1] => proximity_offset_down_left: u8,
-
-    register [0x9F; 1] => config3: Config3,
-
-    register [0xA0; 1] => gesture_proximity_enter_threshold: u8,
-    register [0xA1; 1] => gesture_proximity_exit_threshold: u8,
-    register [0xA2; 1] => gesture_config1: GestureConfig1,
-    register [0xA3; 1] => gesture_config2: GestureConfig2,
-    register [0xA4; 1] => gesture_up_offset: i8,
-    register [0xA5; 1] => gesture_down_offset: i8,
-    register [0xA7; 1] => gesture_left_offset: i8,
-    register [0xA9; 1] => gesture_right_offset: i8,
-    register [0xA6; 1] => gesture_pulse_count: GesturePulseCount,
-
-    register [0xAA; 1] => gesture_config3: GestureConfig3,
-    register [0xAB; 1] => gesture_config4: GestureConfig4,
-    register [0xAE; 1] => gesture_fifo_level: u8,
-    register [0xAF; 1] => gesture_status: GestureStatus,
-
-    register [0xE4; 1] => force_interrupt: u8,
-    register [0xE5; 1] => proximity_interrupt_clear: u8,
-    register [0xE6; 1] => als_interrupt_clear: u8,
-    register [0xE7; 1] => clear_all_non_gesture_interrupts: u8,
-
-    register [0xFC; 4] => gesture_fifo: GestureFifo
-
-  }
-);
+    #[register(address = 0x8B)]
+    proximity_interrupt_high_threshold: u8,
+    #[register(address = 0x8C)]
+    persistence: Persistence,
+    #[register(address = 0x8D)]
+    config1: Config1,
+    #[register(address = 0x8E)]
+    proximity_pulse_count: ProximityPulseCount,
+    #[register(address = 0x8F)]
+    control1: ControlRegister1,
+    #[register(address = 0x90)]
+    config2: Config2,
+
+
+
+    #[register(address = 0x92, access = "ro")]
+    id: ChipId,
+    #[register(address = 0x93, access = "ro")]
+    status: Status,
+
+    #[register(address = 0x94, size = 2, access = "ro")]
+    channel_clear: LsbU16,
+    #[register(address = 0x96, size = 2, access = "ro")]
+    channel_red: LsbU16,
+    #[register(address = 0x98, size = 2, access = "ro")]
+    channel_green: LsbU16,
+    #[register(address = 0x9A, size = 2, access = "ro")]
+    channel_blue: LsbU16,
+
+    #[register(address = 0x94, size = 8, access = "ro")]
+    channels: RGBCData,
+
+
+
+    #[register(address = 0x9C, access = "ro")]
+    proximity_data: u8,
+    #[register(address = 0x9D)]
+    proximity_offset_up_right: u8,
+    #[register(address = 0x9E)]
+    proximity_offset_down_left: u8,
+
+    #[register(address = 0x9F)]
+    config3: Config3,
+
+    #[register(address = 0xA0)]
+    gesture_proximity_enter_threshold: u8,
+    #[register(address = 0xA1)]
+    gesture_proximity_exit_threshold: u8,
+    #[register(address = 0xA2)]
+    gesture_config1: GestureConfig1,
+    #[register(address = 0xA3)]
+    gesture_config2: GestureConfig2,
+    #[register(address = 0xA4)]
+    gesture_up_offset: i8,
+    #[register(address = 0xA5)]
+    gesture_down_offset: i8,
+    #[register(address = 0xA7)]
+    gesture_left_offset: i8,
+    #[register(address = 0xA9)]
+    gesture_right_offset: i8,
+    #[register(address = 0xA6)]
+    gesture_pulse_count: GesturePulseCount,
+
+    #[register(address = 0xAA)]
+    gesture_config3: GestureConfig3,
+    #[register(address = 0xAB)]
+    gesture_config4: GestureConfig4,
+    #[register(address = 0xAE, access = "ro")]
+    gesture_fifo_level: u8,
+    #[register(address = 0xAF, access = "ro")]
+    gesture_status: GestureStatus,
+
+    #[register(address = 0xE4, access = "wo")]
+    force_interrupt: u8,
+    #[register(address = 0xE5, access = "wo")]
+    proximity_interrupt_clear: u8,
+    #[register(address = 0xE6, access = "wo")]
+    als_interrupt_clear: u8,
+    #[register(address = 0xE7, access = "wo")]
+    clear_all_non_gesture_interrupts: u8,
+
+    #[register(address = 0xFC, size = 4, access = "ro", volatile)]
+    gesture_fifo: GestureFifo
+}
 
 
 #[derive(Clone, Copy)]
@@ -94,7 +134,8 @@ impl<B> DeviceI2CDetection<Apds9960OnI2CBus<B>, B, I2CDeviceRegisters<B>> for Ap
 	fn new(args: I2CDeviceRegisters<B>) -> Result<Apds9960OnI2CBus<B>, PeripheryError> {
         let sensor = Apds9960 {
             system: args.system_api,
-            bus: args.device_bus
+            bus: args.device_bus,
+            gesture_buffer: Default::default()
         };
 
         let id = sensor.registers().id().read()?;
@@ -106,7 +147,9 @@ impl<B> DeviceI2CDetection<Apds9960OnI2CBus<B>, B, I2CDeviceRegisters<B>> for Ap
 
 #[derive(Debug, Clone)]
 pub enum Apds9960Error {
-    AmbientLightSensorValueNotValid
+    AmbientLightSensorValueNotValid,
+    ProximityValueNotValid,
+    NotEnoughLightForColor
 }
 
 impl From<Apds9960Error> for PeripheryError {
@@ -123,6 +166,57 @@ impl From<Apds9960Error> for PeripheryError {
 pub struct Apds9960<S, B> where S: SystemApi, B: DeviceRegisterBus {
     system: S,
     bus: B,
+    gesture_buffer: Arc<Mutex<GestureBuffer>>
+}
+
+/// Gesture FIFO samples collected for the detection, until a gesture is detected. The
+/// detector works on the sample indices, so the points are timed by the sample count.
+#[derive(Default)]
+struct GestureBuffer {
+    points: Vec<GestureSensorPoint>,
+    samples: usize
+}
+
+const MAX_GESTURE_POINTS: usize = 500;
+
+impl GestureBuffer {
+    /// Adds the FIFO samples, returns the gestures detected in the buffer, in order.
+    fn detect(&mut self, fifo: &[GestureFifo]) -> Vec<Gesture> {
+        for g in fifo {
+            self.points.push(GestureSensorPoint {
+                milliseconds: self.samples as f32,
+                up: g.up as f32,
+                down: g.down as f32,
+                left: g.left as f32,
+                right: g.right as f32
+            });
+            self.samples += 1;
+        }
+
+        let n = self.points.len();
+        if n > MAX_GESTURE_POINTS {
+            self.points.drain(..(n - MAX_GESTURE_POINTS));
+        }
+
+        let mut detector = GestureDetector::new();
+        let mut gestures = detector.detect(&self.points);
+        if gestures.len() > 0 {
+            self.points.clear();
+        }
+
+        gestures.sort_by_key(|g| g.pos);
+        gestures
+    }
+}
+
+fn detected_gesture(kind: GestureKind) -> DetectedGesture {
+    match kind {
+        GestureKind::SwipeToUp => DetectedGesture::SwipeUp,
+        GestureKind::SwipeToDown => DetectedGesture::SwipeDown,
+        GestureKind::SwipeToLeft => DetectedGesture::SwipeLeft,
+        GestureKind::SwipeToRight => DetectedGesture::SwipeRight,
+        GestureKind::HandNear => DetectedGesture::Near
+    }
 }
 
 impl<S, B> Apds9960<S, B> where S: SystemApi, B: DeviceRegisterBus {
@@ -131,6 +225,9 @@ impl<S, B> Apds9960<S, B> where S: SystemApi, B: DeviceRegisterBus {
         Apds9960Registers::new(&self.bus)
     }
 
+    /// Powers the chip on with the ambient light and the proximity detection enabled.
+    /// The first proximity value is valid after one full cycle, about 30 ms with the
+    /// default wait time.
     pub fn init(&self) -> Result<(), PeripheryError> {
         let r = self.registers();
 
@@ -221,6 +318,7 @@ impl<S, B> Apds9960<S, B> where S: SystemApi, B: DeviceRegisterBus {
         
 
         enable_register.als_enable = true;
+        enable_register.proximity_detect_enable = true;
         enable_register.power_on = true;
 
         r.enable().write(&enable_register)?;
@@ -360,6 +458,67 @@ impl<S, B> AmbientLightSensor for Apds9960<S, B> where S: SystemApi, B: DeviceRe
     }
 }
 
+impl<S, B> ColorSensor for Apds9960<S, B> where S: SystemApi, B: DeviceRegisterBus {
+    fn get_color(&self) -> Result<Color, PeripheryError> {
+        let raw = self.get_ambient_light_raw()?;
+        Ok(Color::from_rgb(raw.red as f32, raw.green as f32, raw.blue as f32))
+    }
+
+    fn get_color_temperature(&self) -> Result<ColorTemperature, PeripheryError> {
+        let raw = self.get_ambient_light_raw()?;
+        let (r, g, b) = (raw.red as f32, raw.green as f32, raw.blue as f32);
+
+        // RGB to CIE XYZ, then McCamy's approximation from the chromaticity
+        let x = -0.14282 * r + 1.54924 * g - 0.95641 * b;
+        let y = -0.32466 * r + 1.57837 * g - 0.73191 * b;
+        let z = -0.68202 * r + 0.77073 * g + 0.56332 * b;
+        let sum = x + y + z;
+        if sum <= 0.0 || y <= 0.0 {
+            return Err(Apds9960Error::NotEnoughLightForColor.into());
+        }
+
+        let n = (x / sum - 0.3320) / (0.1858 - y / sum);
+        let cct = 449.0 * n.powi(3) + 3525.0 * n.powi(2) + 6823.3 * n + 5520.33;
+
+        Ok(ColorTemperature::from_temperature(Temperature::from_kelvin(cct)))
+    }
+}
+
+/// The proximity detection is enabled by `init`, the reading fails with
+/// `MeasurementNotReady` while it's turned off.
+impl<S, B> ProximitySensor for Apds9960<S, B> where S: SystemApi, B: DeviceRegisterBus {
+    fn get_proximity(&self) -> Result<Proximity, PeripheryError> {
+        let r = self.registers();
+
+        let enable = r.enable().read()?;
+        if !enable.power_on || !enable.proximity_detect_enable {
+            return Err(PeripheryError::MeasurementNotReady);
+        }
+
+        if !r.status().read()?.proximity_valid {
+            return Err(Apds9960Error::ProximityValueNotValid.into());
+        }
+
+        let proximity = r.proximity_data().read()?;
+        Ok(Proximity::from_percentage(Percentage::from_percentage(proximity as f32 / 255.0 * 100.0)))
+    }
+}
+
+impl<S, B> GestureSensor for Apds9960<S, B> where S: SystemApi, B: DeviceRegisterBus {
+    fn get_detected_gestures(&self) -> Result<Vec<DetectedGesture>, PeripheryError> {
+        if !self.registers().gesture_config4().read()?.gesture_mode {
+            self.enable_gestures()?;
+        }
+
+        let fifo = self.get_gestures()?;
+
+        let mut buffer = self.gesture_buffer.lock().map_err(|_| PeripheryError::LockingError)?;
+        let gestures = buffer.detect(&fifo);
+
+        Ok(gestures.iter().map(|g| detected_gesture(g.kind)).collect())
+    }
+}
+
 impl<S: 'static, B: 'static> Device for Apds9960<S, B> where S: SystemApi, B: DeviceRegisterBus {
     fn description(&self) -> Cow<str> {
         "APDS-9960 Digital Proximity, Ambient Light, RGB and Gesture Sensor".into()
@@ -375,10 +534,26 @@ impl<S: 'static, B: 'static> Device for Apds9960<S, B> where S: SystemApi, B: De
         "apds9960".into()
     }
 
+    fn info(&self) -> DeviceInfo {
+        DeviceInfo::new("Broadcom", "APDS-9960").with_driver_version(env!("CARGO_PKG_VERSION"))
+    }
+
     fn get_ambient_light_sensor(&self) -> Option<&AmbientLightSensor> {
 		Some(self)
 	}
 
+    fn get_color_sensor(&self) -> Option<&ColorSensor> {
+        Some(self)
+    }
+
+    fn get_proximity_sensor(&self) -> Option<&ProximitySensor> {
+        Some(self)
+    }
+
+    fn get_gesture_sensor(&self) -> Option<&GestureSensor> {
+        Some(self)
+    }
+
     fn init_after_detection(&self) -> Result<bool, PeripheryError> {
         self.init()?;
 		Ok(true)
@@ -427,11 +602,11 @@ fn datastream_2_info() -> DataStream {
         cli_id: "gestures_raw_fixed_gain".into(),
         description: "Dumps raw gesture sensor data, fixed gain".into(),
         poll_every_ms: 10,
-        labels: vec![
-            "up".into(),
-            "down".into(),
-            "left".into(),
-            "right".into()
+        channels: vec![
+            DataStreamChannel::new("up", DataStreamChannelType::I64),
+            DataStreamChannel::new("down", DataStreamChannelType::I64),
+            DataStreamChannel::new("left", DataStreamChannelType::I64),
+            DataStreamChannel::new("right", DataStreamChannelType::I64)
         ]
     }
 }
@@ -442,12 +617,29 @@ fn datastream_10_info() -> DataStream {
         cli_id: "gestures".into(),
         description: "Detects gestures".into(),
         poll_every_ms: 10,
-        labels: vec![
-            "GestureStep".into()
+        channels: vec![
+            DataStreamChannel::new("GestureStep", DataStreamChannelType::String)
         ]
     }
 }
 
+/// Values for the channels of `datastream_2_info`
+fn raw_gesture_values(gesture: &GestureFifo) -> Vec<DataStreamValue> {
+    vec![
+        DataStreamValue::I64(gesture.up as i64),
+        DataStreamValue::I64(gesture.down as i64),
+        DataStreamValue::I64(gesture.left as i64),
+        DataStreamValue::I64(gesture.right as i64)
+    ]
+}
+
+/// Values for the channels of `datastream_10_info`
+fn gesture_step_values(gesture: &Gesture) -> Vec<DataStreamValue> {
+    vec![
+        DataStreamValue::String(format!("{:?}", gesture).into())
+    ]
+}
+
 impl<S: 'static, B: 'static> DataStreams for Apds9960<S, B> where S: SystemApi, B: DeviceRegisterBus {
     fn get_stream_infos(&self) -> Vec<DataStream> {
         vec![datastream_2_info(), datastream_10_info()]
@@ -472,9 +664,7 @@ impl<S: 'static, B: 'static> DataStreams for Apds9960<S, B> where S: SystemApi,
 
                 let p = GestureDetectorPoller {
                     device: device,
-                    max_measurements: 500,
-                    ms: 0,
-                    buffer: vec![]
+                    buffer: Default::default()
                 };
                 Ok(Box::new(p))
             },
@@ -497,24 +687,16 @@ impl<S: 'static, B: 'static> DataStreamPoller for RawGesturePoller<S, B> where S
         self.info.clone()
     }
 
-	fn poll(&mut self) -> Result<Vec<DataStreamPolled>, PeripheryError> {
+	fn poll(&mut self, _device: &Device, clock: &SystemApiClock) -> Result<Vec<DataStreamPolled>, PeripheryError> {
         let mut ret = vec![];
         let mut raw = vec![];
 
-        let mult = 1.0;
-
         let gestures = self.device.get_gestures()?;
+        let timestamp_us = clock.get_timestamp_us();
         for gesture in gestures {
             raw.push(gesture);
             
-            let polled = DataStreamPolled::F32 {
-                data: vec![
-                    (gesture.up as f32) * mult,
-                    (gesture.down as f32) * mult,
-                    (gesture.left as f32) * mult,
-                    (gesture.right as f32) * mult
-                ]
-            };            
+            let polled = DataStreamPolled::new(timestamp_us, raw_gesture_values(&gesture));
 
             ret.push(polled);
         }
@@ -525,53 +707,147 @@ impl<S: 'static, B: 'static> DataStreamPoller for RawGesturePoller<S, B> where S
 
 pub struct GestureDetectorPoller<S, B> where S: SystemApi, B: DeviceRegisterBus {
     device: Apds9960<S, B>,
-    max_measurements: usize,
-    buffer: Vec<GestureSensorPoint>,
-    ms: u64
+    buffer: GestureBuffer
 }
 
 impl<S: 'static, B: 'static> DataStreamPoller for GestureDetectorPoller<S, B> where S: SystemApi, B: DeviceRegisterBus {
     fn get_info(&self) -> DataStream {
-        datastream_2_info()
+        datastream_10_info()
     }
 
-	fn poll(&mut self) -> Result<Vec<DataStreamPolled>, PeripheryError> {
-        self.ms += 1;
-        
-        while self.device.registers().gesture_fifo_level().read()? > 0 {
-            let gesture = self.device.registers().gesture_fifo().read()?;
-
-            let data = GestureSensorPoint {
-                milliseconds: self.ms as f32,
-                up: (gesture.up as f32),
-                down: (gesture.down as f32),
-                left: (gesture.left as f32),
-                right: (gesture.right as f32)
-            };
-            
-            self.buffer.push(data);
-        }
+	fn poll(&mut self, _device: &Device, clock: &SystemApiClock) -> Result<Vec<DataStreamPolled>, PeripheryError> {
+        let fifo = self.device.get_gestures()?;
+        let gestures = self.buffer.detect(&fifo);
 
         let mut ret = vec![];
-
-        let mut detector = GestureDetector::new();
-        let gestures = detector.detect(&self.buffer);
-
+        let timestamp_us = clock.get_timestamp_us();
         for gesture in &gestures {
-            let ev = DataStreamPolled::Strings {
-                data: vec![format!("{:?}", gesture).into()]
-            };
+            let ev = DataStreamPolled::new(timestamp_us, gesture_step_values(gesture));
             ret.push(ev);
         }
 
-        if gestures.len() > 0 {
-            self.buffer.clear();
-        }
+        return Ok(ret);
+    }
+}
 
-        while self.buffer.len() > self.max_measurements {
-            self.buffer.remove(0);
-        }
+#[cfg(test)]
+fn assert_values_match_info(info: &DataStream, values: &[DataStreamValue]) {
+    let types: Vec<_> = values.iter().map(|v| v.get_type()).collect();
+    let channels: Vec<_> = info.channels.iter().map(|c| c.channel_type).collect();
+    assert_eq!(channels, types, "values of the stream {}", info.cli_id);
+}
 
-        return Ok(ret);
+#[test]
+#[cfg(test)]
+fn test_raw_gesture_poller() {
+    let bus = ExpectationBus::new();
+    let sensor = Apds9960 { system: TestSystemApi, bus: bus.clone(), gesture_buffer: Default::default() };
+    let mut poller = RawGesturePoller { device: sensor.clone(), info: datastream_2_info() };
+
+    bus.expect_read(0xAE, &[1])
+       .expect_read(0xFC, &[10, 20, 30, 40])
+       .expect_read(0xAE, &[0]);
+
+    let polled = poller.poll(&sensor, &TestSystemApi).unwrap();
+    assert_eq!(1, polled.len());
+    assert_eq!(1000, polled[0].timestamp_us);
+    assert_eq!(vec![DataStreamValue::I64(10), DataStreamValue::I64(20), DataStreamValue::I64(30), DataStreamValue::I64(40)], polled[0].values);
+    assert_values_match_info(&poller.get_info(), &polled[0].values);
+    bus.done();
+}
+
+#[test]
+#[cfg(test)]
+fn test_gesture_detector_poller_values_match_info() {
+    let bus = ExpectationBus::new();
+    let sensor = Apds9960 { system: TestSystemApi, bus: bus.clone(), gesture_buffer: Default::default() };
+    let mut poller = GestureDetectorPoller { device: sensor.clone(), buffer: Default::default() };
+
+    // a single sample below the noise threshold isn't a gesture
+    bus.expect_read(0xAE, &[1])
+       .expect_read(0xFC, &[1, 2, 3, 4])
+       .expect_read(0xAE, &[0]);
+    assert_eq!(0, poller.poll(&sensor, &TestSystemApi).unwrap().len());
+    bus.done();
+
+    let gesture = Gesture { kind: GestureKind::SwipeToLeft, pos: 10, len: 20 };
+    assert_values_match_info(&poller.get_info(), &gesture_step_values(&gesture));
+}
+
+#[test]
+#[cfg(test)]
+fn test_ambient_light_and_color() {
+    let bus = ExpectationBus::new();
+    let sensor = Apds9960 { system: TestSystemApi, bus: bus.clone(), gesture_buffer: Default::default() };
+
+    // clear 1000, red 400, green 350, blue 300
+    let channels = [0xE8, 0x03, 0x90, 0x01, 0x5E, 0x01, 0x2C, 0x01];
+
+    // 37 integration cycles, 4x gain
+    bus.expect_read(0x93, &[0x01])
+       .expect_read(0x94, &channels)
+       .expect_read(0x81, &[0xDB])
+       .expect_read(0x8F, &[0x01]);
+    let lux = sensor.get_ambient_light().unwrap().get_lux();
+    assert!((lux - 332.09).abs() < 0.01);
+
+    bus.expect_read(0x93, &[0x01])
+       .expect_read(0x94, &channels);
+    let color = sensor.get_color().unwrap();
+    assert!((color.get_red() - 0.3810).abs() < 0.0001);
+    assert!((color.get_green() - 0.3333).abs() < 0.0001);
+    assert!((color.get_blue() - 0.2857).abs() < 0.0001);
+
+    bus.expect_read(0x93, &[0x01])
+       .expect_read(0x94, &channels);
+    let cct = sensor.get_color_temperature().unwrap().get_temperature().get_kelvin();
+    assert!((cct - 4864.2).abs() < 0.5);
+
+    // the ALS isn't valid
+    bus.expect_read(0x93, &[0x00]);
+    assert!(sensor.get_color().is_err());
+    bus.done();
+
+    assert_eq!(0.0, Color::from_rgb(0.0, 0.0, 0.0).get_red());
+}
+
+#[test]
+#[cfg(test)]
+fn test_detected_gestures() {
+    assert_eq!(DetectedGesture::SwipeUp, detected_gesture(GestureKind::SwipeToUp));
+    assert_eq!(DetectedGesture::SwipeDown, detected_gesture(GestureKind::SwipeToDown));
+    assert_eq!(DetectedGesture::SwipeLeft, detected_gesture(GestureKind::SwipeToLeft));
+    assert_eq!(DetectedGesture::SwipeRight, detected_gesture(GestureKind::SwipeToRight));
+    assert_eq!(DetectedGesture::Near, detected_gesture(GestureKind::HandNear));
+
+    // the points are timed by the sample count, across the FIFO reads
+    let mut buffer = GestureBuffer::default();
+    let fifo = GestureFifo { up: 1, down: 2, left: 3, right: 4 };
+    assert_eq!(0, buffer.detect(&[fifo, fifo]).len());
+    assert_eq!(0, buffer.detect(&[fifo]).len());
+    let times: Vec<_> = buffer.points.iter().map(|p| p.milliseconds).collect();
+    assert_eq!(vec![0.0, 1.0, 2.0], times);
+}
+
+#[test]
+#[cfg(test)]
+fn test_proximity_requires_detection() {
+    let bus = ExpectationBus::new();
+    let sensor = Apds9960 { system: TestSystemApi, bus: bus.clone(), gesture_buffer: Default::default() };
+
+    // powered on, only the ambient light is enabled
+    bus.expect_read(0x80, &[0x03]);
+    match sensor.get_proximity() {
+        Err(PeripheryError::MeasurementNotReady) => (),
+        r => panic!("unexpected {:?}", r)
     }
+    bus.done();
+
+    // proximity enabled and valid, nothing is written
+    bus.expect_read(0x80, &[0x05])
+       .expect_read(0x93, &[0x02])
+       .expect_read(0x9C, &[255]);
+    let proximity = sensor.get_proximity().unwrap();
+    assert!((100.0 - proximity.get_percentage().get_percentage()).abs() < 0.01);
+    bus.done();
 }
@@ -9,29 +9,44 @@ use packed_struct::prelude::*;
 type MsbI16 = MsbInteger<i16, packed_bits::Bits16, Integer<i16, packed_bits::Bits16>>;
 type MsbU16 = MsbInteger<u16, packed_bits::Bits16, Integer<u16, packed_bits::Bits16>>;
 
-registers!(
-  chip Bmp180Registers {
-  	register [0xAA; 2] => ac1: MsbI16,
-  	register [0xAC; 2] => ac2: MsbI16,
-  	register [0xAE; 2] => ac3: MsbI16,
-  	register [0xB0; 2] => ac4: MsbU16,
-  	register [0xB2; 2] => ac5: MsbU16,
-  	register [0xB4; 2] => ac6: MsbU16,
-  	register [0xB6; 2] => b1 : MsbI16,
-  	register [0xB8; 2] => b2 : MsbI16,
-  	register [0xBA; 2] => mb : MsbI16,
-  	register [0xBC; 2] => mc : MsbI16,
-  	register [0xBE; 2] => md : MsbI16,
-
-    register [0xD0; 1] => id: u8,
-
-    register [0xE0; 1] => soft_reset: ResetRegister,
-
-    register [0xF4; 1] => measurement_control: MeasurementControlRegister,
-    register [0xF6; 2] => measurement_u16: MeasurementValueMsb,
-    register [0xF8; 1] => measurement_xlsb: u8    
-  }
-);
+#[register_map]
+pub struct Bmp180Registers {
+    #[register(address = 0xAA, size = 2, access = "ro")]
+    ac1: MsbI16,
+    #[register(address = 0xAC, size = 2, access = "ro")]
+    ac2: MsbI16,
+    #[register(address = 0xAE, size = 2, access = "ro")]
+    ac3: MsbI16,
+    #[register(address = 0xB0, size = 2, access = "ro")]
+    ac4: MsbU16,
+    #[register(address = 0xB2, size = 2, access = "ro")]
+    ac5: MsbU16,
+    #[register(address = 0xB4, size = 2, access = "ro")]
+    ac6: MsbU16,
+    #[register(address = 0xB6, size = 2, access = "ro")]
+    b1: MsbI16,
+    #[register(address = 0xB8, size = 2, access = "ro")]
+    b2: MsbI16,
+    #[register(address = 0xBA, size = 2, access = "ro")]
+    mb: MsbI16,
+    #[register(address = 0xBC, size = 2, access = "ro")]
+    mc: MsbI16,
+    #[register(address = 0xBE, size = 2, access = "ro")]
+    md: MsbI16,
+
+    #[register(address = 0xD0, access = "ro")]
+    id: u8,
+
+    #[register(address = 0xE0, access = "wo")]
+    soft_reset: ResetRegister,
+
+    #[register(address = 0xF4)]
+    measurement_control: MeasurementControlRegister,
+    #[register(address = 0xF6, size = 2, access = "ro")]
+    measurement_u16: MeasurementValueMsb,
+    #[register(address = 0xF8, access = "ro")]
+    measurement_xlsb: u8
+}
 
 pub type Bmp180OnI2CBus<B> = Bmp180<<B as Bus>::SystemApi, <<<B as Bus>::I2C as I2CBus>::DeviceFactory as I2CBusDeviceFactory>::Registers>;
 
@@ -195,6 +210,10 @@ impl<S, B> Device for Bmp180<S, B> where S: SystemApi, B: DeviceRegisterBus {
         "bmp180".into()
     }
 
+    fn info(&self) -> DeviceInfo {
+        DeviceInfo::new("Bosch Sensortec", "BMP180").with_driver_version(env!("CARGO_PKG_VERSION"))
+    }
+
 	fn get_cli(&self) -> Option<&DeviceCli> {
 		Some(self)
 	}
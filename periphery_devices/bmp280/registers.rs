@@ -36,6 +36,17 @@ pub enum PowerMode {
     NormalMode = 3
 }
 
+/// Kept by the driver while the chip sleeps, to restore the mode on wake.
+impl PowerStateValue for PowerMode {
+    fn to_state_byte(&self) -> u8 {
+        self.to_primitive()
+    }
+
+    fn from_state_byte(b: u8) -> Option<Self> {
+        PowerMode::from_primitive(b)
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PrimitiveEnum_u8)]
 pub enum StandbyTime {
     Standby0_5ms = 0,
@@ -48,35 +59,41 @@ pub enum StandbyTime {
     Standby4000ms = 7
 }
 
-#[derive(PackedStruct, Debug, Copy, Clone)]
-#[packed_struct(size_bytes="1", bit_numbering="lsb0")]
-pub struct StatusRegister {
-    #[packed_field(bits="3")]
-    pub measuring: bool,
-    #[packed_field(bits="0")]
-    pub im_update: bool
+register_fields! {
+    #[derive(PackedStruct, Debug, Copy, Clone)]
+    #[packed_struct(size_bytes="1", bit_numbering="lsb0")]
+    pub struct StatusRegister {
+        #[packed_field(bits="3")]
+        pub measuring: bool,
+        #[packed_field(bits="0")]
+        pub im_update: bool
+    }
 }
 
-#[derive(PackedStruct, Debug, Copy, Clone)]
-#[packed_struct(size_bytes="1", bit_numbering="lsb0")]
-pub struct ControlMeasurementRegister {
-    #[packed_field(bits="7..5", ty="enum")]
-    pub oversampling_temperature: Oversampling,
-    #[packed_field(bits="4..2", ty="enum")]
-    pub oversampling_pressure: Oversampling,
-    #[packed_field(bits="1..0", ty="enum")]
-    pub power_mode: PowerMode    
+register_fields! {
+    #[derive(PackedStruct, Debug, Copy, Clone)]
+    #[packed_struct(size_bytes="1", bit_numbering="lsb0")]
+    pub struct ControlMeasurementRegister {
+        #[packed_field(bits="7..5", ty="enum")]
+        pub oversampling_temperature: Oversampling,
+        #[packed_field(bits="4..2", ty="enum")]
+        pub oversampling_pressure: Oversampling,
+        #[packed_field(bits="1..0", ty="enum")]
+        pub power_mode: PowerMode
+    }
 }
 
-#[derive(PackedStruct, Debug, Copy, Clone)]
-#[packed_struct(size_bytes="1", bit_numbering="lsb0")]
-pub struct ConfigurationRegister {
-    #[packed_field(bits="7..5", ty="enum")]
-    pub standby_time: StandbyTime,
-    #[packed_field(bits="4..2", ty="enum")]
-    pub iir_filter: IirFilter,
-    #[packed_field(bits="0")]
-    pub enable_3wire_spi: bool
+register_fields! {
+    #[derive(PackedStruct, Debug, Copy, Clone)]
+    #[packed_struct(size_bytes="1", bit_numbering="lsb0")]
+    pub struct ConfigurationRegister {
+        #[packed_field(bits="7..5", ty="enum")]
+        pub standby_time: StandbyTime,
+        #[packed_field(bits="4..2", ty="enum")]
+        pub iir_filter: IirFilter,
+        #[packed_field(bits="0")]
+        pub enable_3wire_spi: bool
+    }
 }
 
 #[derive(PackedStruct, Debug, Copy, Clone)]
@@ -114,3 +131,5 @@ pub struct CalibrationCoefficients {
     pub p9: i16
 }
 
+
+register_field_enums!(Oversampling, IirFilter, PowerMode, StandbyTime);
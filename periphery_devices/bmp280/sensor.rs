@@ -6,22 +6,32 @@ use registers::*;
 
 use packed_struct::*;
 
-registers!(
-  chip Bmp280Registers {
-    register [0x88; 24] => calibration_coefficients: CalibrationCoefficients,
-    
-    register [0xD0; 1] => id: u8,
-
-    register [0xF3; 1] => status: StatusRegister,
-    register [0xF4; 1] => control_measurement: ControlMeasurementRegister,
-    register [0xF5; 1] => config: ConfigurationRegister,
-
-    register [0xE0; 1] => soft_reset: ResetRegister,
-
-    register [0xF7; 3] => pressure: MeasurementValue,
-    register [0xFA; 3] => temperature: MeasurementValue
-  }
-);
+#[register_map]
+pub struct Bmp280Registers {
+    /// Factory trimming parameters, little endian
+    #[register(address = 0x88, size = 24, access = "ro")]
+    calibration_coefficients: CalibrationCoefficients,
+
+    /// Chip identification, always 0x58
+    #[register(address = 0xD0, access = "ro", reset = 0x58)]
+    id: u8,
+
+    #[register(address = 0xF3, access = "ro", reset = 0x00, volatile, fields)]
+    status: StatusRegister,
+    #[register(address = 0xF4, reset = 0x00, fields)]
+    control_measurement: ControlMeasurementRegister,
+    #[register(address = 0xF5, reset = 0x00, fields)]
+    config: ConfigurationRegister,
+
+    /// Write 0xB6 to reset the chip
+    #[register(address = 0xE0, access = "wo")]
+    soft_reset: ResetRegister,
+
+    #[register(address = 0xF7, size = 3, access = "ro", reset = 0x800000, volatile)]
+    pressure: MeasurementValue,
+    #[register(address = 0xFA, size = 3, access = "ro", reset = 0x800000, volatile)]
+    temperature: MeasurementValue
+}
 
 pub type Bmp280OnI2CBus<B> = Bmp280<<<<B as Bus>::I2C as I2CBus>::DeviceFactory as I2CBusDeviceFactory>::Registers>;
 
@@ -47,7 +57,8 @@ impl<B> DeviceI2CDetection<Bmp280OnI2CBus<B>, B, I2CDeviceRegisters<B>> for Bmp2
 
 	fn new(args: I2CDeviceRegisters<B>) -> Result<Bmp280OnI2CBus<B>, PeripheryError> {
         let sensor = Bmp280 {
-            bus: args.device_bus
+            bus: args.device_bus,
+            mode_before_sleep: Default::default()
         };
 
         let id = sensor.registers().id().read()?;
@@ -63,6 +74,8 @@ impl<B> DeviceI2CDetection<Bmp280OnI2CBus<B>, B, I2CDeviceRegisters<B>> for Bmp2
 #[derive(Clone)]
 pub struct Bmp280<B> where B: DeviceRegisterBus {
     bus: B,
+    /// Restored by `wake`.
+    mode_before_sleep: PowerStateCell<PowerMode>
 }
 
 impl<B> Bmp280<B> where B: DeviceRegisterBus {
@@ -92,6 +105,10 @@ impl<B> Device for Bmp280<B> where B: DeviceRegisterBus {
         "bmp280".into()
     }
 
+    fn info(&self) -> DeviceInfo {
+        DeviceInfo::new("Bosch Sensortec", "BMP280").with_driver_version(env!("CARGO_PKG_VERSION"))
+    }
+
     fn get_ambient_temperature_sensor(&self) -> Option<&AmbientTemperatureSensor> {
         Some(self)
     }
@@ -99,6 +116,10 @@ impl<B> Device for Bmp280<B> where B: DeviceRegisterBus {
     fn get_atmospheric_pressure_sensor(&self) -> Option<&AtmosphericPressureSensor> {
         Some(self)
     }
+
+    fn get_power_management(&self) -> Option<&PowerManagement> {
+        Some(self)
+    }
     
     fn init_after_detection(&self) -> Result<bool, PeripheryError> {
         self.registers().control_measurement().write(&ControlMeasurementRegister {
@@ -117,6 +138,45 @@ impl<B> Device for Bmp280<B> where B: DeviceRegisterBus {
 	}
 }
 
+impl<B> PowerManagement for Bmp280<B> where B: DeviceRegisterBus {
+    fn sleep(&self) -> Result<(), PeripheryError> {
+        let mut c = self.registers().control_measurement().read()?;
+        if c.power_mode != PowerMode::SleepMode {
+            self.mode_before_sleep.set(c.power_mode);
+        }
+
+        c.power_mode = PowerMode::SleepMode;
+        self.registers().control_measurement().write(&c)
+    }
+
+    /// Restores the power mode from before `sleep`. Does nothing if the device wasn't
+    /// put to sleep by the driver.
+    fn wake(&self) -> Result<(), PeripheryError> {
+        if let Some(mode) = self.mode_before_sleep.take() {
+            self.registers().control_measurement().modify(|c| {
+                c.power_mode = mode;
+            })?;
+        }
+        Ok(())
+    }
+
+    /// The chip needs 2 ms to start up after the reset.
+    fn soft_reset(&self) -> Result<(), PeripheryError> {
+        self.mode_before_sleep.clear();
+
+        self.registers().soft_reset().write(&ResetRegister {
+            state: ResetState::TriggerReset
+        })
+    }
+
+    fn power_state(&self) -> Result<PowerState, PeripheryError> {
+        match self.registers().control_measurement().read()?.power_mode {
+            PowerMode::SleepMode => Ok(PowerState::Sleep),
+            _ => Ok(PowerState::Active)
+        }
+    }
+}
+
 impl<B> AmbientTemperatureSensor for Bmp280<B> where B: DeviceRegisterBus {
     fn get_ambient_temperature(&self) -> Result<AmbientTemperature, PeripheryError> {
         let c = self.read_calibration_coefficients()?;
@@ -222,4 +282,59 @@ fn test_bmp280_calculation() {
     assert_eq!(25767233, p.pressure); // floating point calculation from documentation: 25767236
 
     assert!((100653.0 - p.to_atmospheric_pressure().get_pressure().get_pascal()).abs() < 1.0)
-}
\ No newline at end of file
+}
+
+#[test]
+#[cfg(test)]
+fn test_bmp280_init() {
+    let bus = ExpectationBus::new();
+    bus.expect_write(0xF4, &[0x33])
+       .expect_write(0xF5, &[0x04]);
+
+    let sensor = Bmp280 { bus: bus.clone(), mode_before_sleep: Default::default() };
+    assert_eq!(true, sensor.init_after_detection().unwrap());
+
+    bus.done();
+}
+
+#[test]
+#[cfg(test)]
+fn test_bmp280_sleep() {
+    let bus = ExpectationBus::new();
+    bus.expect_read(0xF4, &[0x33])
+       .expect_write(0xF4, &[0x30])
+       .expect_read(0xF4, &[0x30]);
+
+    let sensor = Bmp280 { bus: bus.clone(), mode_before_sleep: Default::default() };
+    sensor.sleep().unwrap();
+    assert_eq!(PowerState::Sleep, sensor.power_state().unwrap());
+
+    bus.done();
+
+    // the forced mode from before the sleep is restored, not the normal mode
+    bus.expect_read(0xF4, &[0x31])
+       .expect_write(0xF4, &[0x30])
+       .expect_read(0xF4, &[0x30])
+       .expect_write(0xF4, &[0x31]);
+
+    sensor.sleep().unwrap();
+    sensor.wake().unwrap();
+    // only once
+    sensor.wake().unwrap();
+
+    bus.done();
+}
+
+#[test]
+#[cfg(test)]
+fn test_bmp280_register_fields() {
+    // every packed field has an entry in the field table
+    assert_eq!(Some(vec![]), unmapped_register_bits::<StatusRegister>(1));
+    assert_eq!(Some(vec![]), unmapped_register_bits::<ControlMeasurementRegister>(1));
+    assert_eq!(Some(vec![]), unmapped_register_bits::<ConfigurationRegister>(1));
+
+    let mut c = ControlMeasurementRegister::from_register_value(&[0x00]).unwrap();
+    set_register_fields(&mut c, "oversampling_pressure=Times8 power_mode=NormalMode").unwrap();
+    assert_eq!("Times8", c.get_field("oversampling_pressure").unwrap());
+    assert_eq!(&["oversampling_temperature", "oversampling_pressure", "power_mode"], ControlMeasurementRegister::field_names());
+}
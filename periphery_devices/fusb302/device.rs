@@ -3,38 +3,62 @@ use periphery_core::prelude::v1::*;
 use periphery_core::terminal_cli::*;
 
 use registers::*;
+// the chip's register, not the core's device id
+use registers::DeviceId;
 
 use packed_struct::*;
 
 pub const FIFO_ADDRESS: u8 = 0x43; 
 
-registers!(
-  chip Fusb302Registers {
-    register [0x01; 1] => device_id: DeviceId,
-    register [0x02; 1] => switches0: Switches0,
-    register [0x03; 1] => switches1: Switches1,
-    register [0x04; 1] => measure: Measure,
-    register [0x05; 1] => slice: Slice,
-    register [0x06; 1] => control0: Control0,
-    register [0x07; 1] => control1: Control1,
-    register [0x08; 1] => control2: Control2,
-    register [0x09; 1] => control3: Control3,
-    register [0x0A; 1] => mask: Mask,
-    register [0x0B; 1] => power: Power,
-    register [0x0C; 1] => reset: Reset,
-    register [0x0D; 1] => ocp_reg: OcpReg,
-    register [0x0E; 1] => mask_a: MaskA,
-    register [0x0F; 1] => mask_b: MaskB,
-    register [0x3C; 1] => status0a: Status0A,
-    register [0x3D; 1] => status1a: Status1A,
-    register [0x3E; 1] => interrupt_a: InterruptA,
-    register [0x3F; 1] => interrupt_b: InterruptB,    
-    register [0x40; 1] => status0: Status0,
-    register [0x41; 1] => status1: Status1,
-    register [0x42; 1] => interrupt: Interrupt,
-    register [FIFO_ADDRESS; 1] => fifo: u8
-  }
-);
+#[register_map]
+pub struct Fusb302Registers {
+    #[register(address = 0x01, access = "ro")]
+    device_id: DeviceId,
+    #[register(address = 0x02)]
+    switches0: Switches0,
+    #[register(address = 0x03)]
+    switches1: Switches1,
+    #[register(address = 0x04)]
+    measure: Measure,
+    #[register(address = 0x05)]
+    slice: Slice,
+    #[register(address = 0x06)]
+    control0: Control0,
+    #[register(address = 0x07)]
+    control1: Control1,
+    #[register(address = 0x08)]
+    control2: Control2,
+    #[register(address = 0x09)]
+    control3: Control3,
+    #[register(address = 0x0A)]
+    mask: Mask,
+    #[register(address = 0x0B)]
+    power: Power,
+    #[register(address = 0x0C, access = "wo")]
+    reset: Reset,
+    #[register(address = 0x0D)]
+    ocp_reg: OcpReg,
+    #[register(address = 0x0E)]
+    mask_a: MaskA,
+    #[register(address = 0x0F)]
+    mask_b: MaskB,
+    #[register(address = 0x3C, access = "ro")]
+    status0a: Status0A,
+    #[register(address = 0x3D, access = "ro")]
+    status1a: Status1A,
+    #[register(address = 0x3E, access = "ro", volatile)]
+    interrupt_a: InterruptA,
+    #[register(address = 0x3F, access = "ro", volatile)]
+    interrupt_b: InterruptB,
+    #[register(address = 0x40, access = "ro")]
+    status0: Status0,
+    #[register(address = 0x41, access = "ro")]
+    status1: Status1,
+    #[register(address = 0x42, access = "ro", volatile)]
+    interrupt: Interrupt,
+    #[register(address = 0x43, volatile)]
+    fifo: u8
+}
 
 pub type Fusb302OnI2CBus<B> = Fusb302<
         <B as Bus>::SystemApi,
@@ -68,22 +92,17 @@ impl<B> DeviceI2CDetection<Fusb302OnI2CBus<B>, B, I2CDeviceAll<B>> for Fusb302Fa
     }
 
 	fn new(args: I2CDeviceAll<B>) -> Result<Fusb302OnI2CBus<B>, PeripheryError> {
-        let device = Fusb302 {
+        let device_id = Fusb302Registers::new(&args.device_registers).device_id().read()?;
+        if *device_id.version != 9 || *device_id.revision < 1 {
+            return Err(PeripheryError::UnsupportedFieldValue);
+        }
+
+        Ok(Fusb302 {
             system: args.system_api,
             bus: args.device_registers,
-            data: args.device_data
-        };
-
-        match device.registers().device_id().read() {
-            Ok(device_id) => {
-                if *device_id.version == 9 && *device_id.revision >= 1 {
-                    Ok(device)
-                } else {
-                    Err(PeripheryError::UnsupportedFieldValue)
-                }
-            },
-            Err(e) => Err(e)
-        }
+            data: args.device_data,
+            device_id: device_id
+        })
     }
 }
 
@@ -91,7 +110,9 @@ impl<B> DeviceI2CDetection<Fusb302OnI2CBus<B>, B, I2CDeviceAll<B>> for Fusb302Fa
 pub struct Fusb302<S, B, D: > where S: SystemApi, B: DeviceRegisterBus, D: DeviceDataTransfer {
     system: S,
     bus: B,
-    data: D
+    data: D,
+    /// Read during the detection, the chip's version never changes.
+    device_id: DeviceId
 }
 
 impl<S, B, D> Fusb302<S, B, D> where S: SystemApi, B: DeviceRegisterBus, D: DeviceDataTransfer {
@@ -128,12 +149,53 @@ impl<S, B, D> Fusb302<S, B, D> where S: SystemApi, B: DeviceRegisterBus, D: Devi
         }
 
         self.registers().power().write(&Power {
-            pwr: 0xF.into()
+            pwr: POWER_ALL.into()
         })?;
 
         Ok(true)
     }
 
+    /// Keep shadow copies of the configuration registers, so that their `modify` calls
+    /// become single bus writes. A write to the reset register drops the shadows.
+    pub fn with_register_cache(self) -> Fusb302<S, CachedRegisterBus<B>, D> {
+        let (configuration, control0, control1, control3, reset) = {
+            let r = self.registers();
+            let configuration = [
+                r.switches0().address,
+                r.switches1().address,
+                r.measure().address,
+                r.slice().address,
+                r.control2().address,
+                r.mask().address,
+                r.power().address,
+                r.ocp_reg().address,
+                r.mask_a().address,
+                r.mask_b().address
+            ];
+            (configuration, r.control0().address, r.control1().address, r.control3().address, r.reset().address)
+        };
+
+        let mut bus = CachedRegisterBus::new(self.bus);
+        for address in &configuration {
+            bus.cache_register(*address);
+        }
+        // tx_flush, tx_start
+        bus.cache_register_self_clearing(control0, 0b0100_0001);
+        // rx_flush
+        bus.cache_register_self_clearing(control1, 0b0000_0100);
+        // send_hard_reset
+        bus.cache_register_self_clearing(control3, 0b0100_0000);
+        bus.invalidate_on_write(reset);
+        bus.volatile_registers(&Fusb302Registers::<B>::register_map());
+
+        Fusb302 {
+            system: self.system,
+            bus: bus,
+            data: self.data,
+            device_id: self.device_id
+        }
+    }
+
     pub fn pd_reset(&self) -> Result<(), PeripheryError> {
         self.registers().reset().write(&Reset {
             pd_reset: true,
@@ -264,6 +326,12 @@ impl<S, B, D> Fusb302<S, B, D> where S: SystemApi, B: DeviceRegisterBus, D: Devi
     }
 }
 
+impl<S, B, D> Fusb302<S, CachedRegisterBus<B>, D> where S: SystemApi, B: DeviceRegisterBus, D: DeviceDataTransfer {
+    pub fn get_register_cache(&self) -> &CachedRegisterBus<B> {
+        &self.bus
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct CcVoltageStatus {
     pub cc1: TypeC_CC_VoltageStatus,
@@ -317,6 +385,43 @@ fn convert_bc_lvl(status: Status0, pulling_up: bool) -> TypeC_CC_VoltageStatus {
     }
 }
 
+/// Power register bits: bandgap and wake circuit, receiver and current references,
+/// measure block, internal oscillator.
+const POWER_ALL: u8 = 0b1111;
+const POWER_WAKE_ONLY: u8 = 0b0001;
+
+impl<S, B, D> PowerManagement for Fusb302<S, B, D> where S: SystemApi, B: DeviceRegisterBus, D: DeviceDataTransfer {
+    /// Only the wake detection stays powered.
+    fn sleep(&self) -> Result<(), PeripheryError> {
+        self.registers().power().write(&Power {
+            pwr: POWER_WAKE_ONLY.into()
+        })
+    }
+
+    fn wake(&self) -> Result<(), PeripheryError> {
+        self.registers().power().write(&Power {
+            pwr: POWER_ALL.into()
+        })
+    }
+
+    /// Resets the registers to their power-on values, `init` has to be called again.
+    fn soft_reset(&self) -> Result<(), PeripheryError> {
+        self.registers().reset().write(&Reset {
+            pd_reset: false,
+            sw_reset: true
+        })
+    }
+
+    fn power_state(&self) -> Result<PowerState, PeripheryError> {
+        let power = self.registers().power().read()?;
+        if *power.pwr == POWER_ALL {
+            Ok(PowerState::Active)
+        } else {
+            Ok(PowerState::Sleep)
+        }
+    }
+}
+
 impl<S, B, D> Device for Fusb302<S, B, D> where S: SystemApi, B: DeviceRegisterBus, D: DeviceDataTransfer {
     fn description(&self) -> Cow<str> {
         "FUSB302 Programmable USB Type-C Controller with PD".into()
@@ -328,7 +433,98 @@ impl<S, B, D> Device for Fusb302<S, B, D> where S: SystemApi, B: DeviceRegisterB
         Some(c)
     }
 
+    fn get_power_management(&self) -> Option<&PowerManagement> {
+        Some(self)
+    }
+
     fn id(&self) -> Cow<str> {
         "fusb302".into()
     }
+
+    fn info(&self) -> DeviceInfo {
+        let id = &self.device_id;
+
+        DeviceInfo::new("ON Semiconductor", "FUSB302")
+            .with_driver_version(env!("CARGO_PKG_VERSION"))
+            .with_variant(format!("version {}, revision {}", *id.version, (b'A' + *id.revision) as char))
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn test_fusb302_snapshot_skips_volatile_registers() {
+    let bus = ExpectationBus::new();
+    let registers = Fusb302Registers::new(&bus);
+
+    // the clear-on-read interrupts and the FIFO are never read
+    let readable = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0D, 0x0E, 0x0F, 0x3C, 0x3D, 0x40, 0x41];
+    for address in readable.iter() {
+        bus.expect_read(*address, &[*address]);
+    }
+
+    let mut snapshot = registers.snapshot();
+    bus.done();
+    assert_eq!(readable.len(), snapshot.registers.len());
+    for name in &["interrupt_a", "interrupt_b", "interrupt", "fifo"] {
+        assert!(snapshot.get(name).is_none());
+    }
+
+    // and the FIFO isn't written back, even from a snapshot that has it
+    snapshot.registers.push(RegisterSnapshotEntry {
+        name: "fifo".into(),
+        address: FIFO_ADDRESS,
+        size_bytes: 1,
+        value: Some(vec![0xE0])
+    });
+
+    let writable = [0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0D, 0x0E, 0x0F];
+    for address in writable.iter() {
+        bus.expect_write(*address, &[*address]);
+    }
+
+    assert_eq!(writable.len(), registers.restore(&snapshot).unwrap());
+    bus.done();
+}
+
+#[test]
+#[cfg(test)]
+fn test_fusb302_info_without_bus_traffic() {
+    let bus = ExpectationBus::new();
+    let device = Fusb302 {
+        system: TestSystemApi,
+        bus: bus.clone(),
+        data: bus.clone(),
+        device_id: DeviceId { version: 9.into(), revision: 2.into() }
+    };
+
+    let info = device.info();
+    assert_eq!(Some("version 9, revision C".into()), info.variant);
+    bus.done();
+}
+
+#[test]
+#[cfg(test)]
+fn test_fusb302_power_management() {
+    let bus = ExpectationBus::new();
+    bus.expect_write(0x0B, &[0x01])
+       .expect_read(0x0B, &[0x01])
+       .expect_write(0x0B, &[0x0F])
+       .expect_read(0x0B, &[0x0F])
+       .expect_write(0x0C, &[0x01]);
+
+    let device = Fusb302 {
+        system: TestSystemApi,
+        bus: bus.clone(),
+        data: bus.clone(),
+        device_id: DeviceId { version: 9.into(), revision: 2.into() }
+    };
+
+    device.sleep().unwrap();
+    assert_eq!(PowerState::Sleep, device.power_state().unwrap());
+    device.wake().unwrap();
+    assert_eq!(PowerState::Active, device.power_state().unwrap());
+    // only the reset register is written, no initialization
+    device.soft_reset().unwrap();
+
+    bus.done();
 }
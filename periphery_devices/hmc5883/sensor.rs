@@ -5,20 +5,27 @@ use registers::*;
 
 use packed_struct::prelude::*;
 
-registers!(
-  chip Hmc5883Registers {
-    register [0x00; 1] => config_a: ConfigurationRegisterA,
-    register [0x01; 1] => config_b: ConfigurationRegisterB,
-    register [0x02; 1] => mode: ModeRegister,
-    
-    register [0x03; 6] => data: MagData,
-    
-    register [0x09; 1] => status: StatusRegister,
-  	register [0x0A; 1] => id_a: u8,
-    register [0x0B; 1] => id_b: u8,
-    register [0x0C; 1] => id_c: u8
-  }
-);
+#[register_map]
+pub struct Hmc5883Registers {
+    #[register(address = 0x00)]
+    config_a: ConfigurationRegisterA,
+    #[register(address = 0x01)]
+    config_b: ConfigurationRegisterB,
+    #[register(address = 0x02)]
+    mode: ModeRegister,
+
+    #[register(address = 0x03, size = 6, access = "ro")]
+    data: MagData,
+
+    #[register(address = 0x09, access = "ro")]
+    status: StatusRegister,
+    #[register(address = 0x0A, access = "ro")]
+    id_a: u8,
+    #[register(address = 0x0B, access = "ro")]
+    id_b: u8,
+    #[register(address = 0x0C, access = "ro")]
+    id_c: u8
+}
 
 pub const HMC58X3_X_SELF_TEST_GAUSS: f32 = 1.16;
 pub const HMC58X3_Y_SELF_TEST_GAUSS: f32 = 1.16;
@@ -52,7 +59,8 @@ impl<B> DeviceI2CDetection<Hmc5883OnI2CBus<B>, B, I2CDeviceRegisters<B>> for Hmc
 	fn new(args: I2CDeviceRegisters<B>) -> Result<Hmc5883OnI2CBus<B>, PeripheryError> {
         let sensor = Hmc5883 {
             system: args.system_api,
-            bus: args.device_bus
+            bus: args.device_bus,
+            power_state: PowerStateCell::new()
         };
 
         let id_a = sensor.registers().id_a().read()?;
@@ -70,7 +78,10 @@ impl<B> DeviceI2CDetection<Hmc5883OnI2CBus<B>, B, I2CDeviceRegisters<B>> for Hmc
 #[derive(Clone)]
 pub struct Hmc5883<S, B> where S: SystemApi, B: DeviceRegisterBus {
     system: S,
-    bus: B
+    bus: B,
+    /// The chip idles between the single measurements anyway, its mode can't tell
+    /// a sleeping device from an idle one.
+    power_state: PowerStateCell
 }
 
 impl<S, B> Hmc5883<S, B> where S: SystemApi, B: DeviceRegisterBus {
@@ -109,74 +120,70 @@ impl<S, B> Hmc5883<S, B> where S: SystemApi, B: DeviceRegisterBus {
         Hmc5883Registers::new(&self.bus)
     }
 
-    // this really doesn't do much ATM
-    /*
-    /// The device will be reset to default settings after the calibration.
-    pub fn self_calibrate(&self) -> Result<MagneticGains, PeripheryError> {
-        let samples_per_bias = 10;
-        let mut sum = (0 as i32, 0 as i32, 0 as i32);
+    fn check_awake(&self) -> Result<(), PeripheryError> {
+        if self.power_state.get() == Some(PowerState::Sleep) {
+            return Err(PeripheryError::DeviceAsleep);
+        }
+        Ok(())
+    }
 
-        let read_single = || {
-            let mode = ModeRegister {
-                high_speed_i2c_enabled: false,
-                operating_mode: OperatingMode::SingleMeasurement
-            };
-            try!(self.registers.mode.write(&self.bus, &mode));
-            self.system.sleep_ms(60);
+    /// Trigger a single measurement and read it.
+    fn read_single(&self) -> Result<MagneticField3Raw, PeripheryError> {
+        self.check_awake()?;
 
-            self.get_magnetic_field_3_raw()
+        let mode = ModeRegister {
+            high_speed_i2c_enabled: false,
+            operating_mode: OperatingMode::SingleMeasurement
         };
+        self.registers().mode().write(&mode)?;
+        self.system.get_sleep()?.sleep_ms(6);
 
-        let config_b = ConfigurationRegisterB {
-            gain: Gain::Gain_660
+        self.get_magnetic_field_3_raw()
+    }
+
+    /// Average of the measurements with the internal bias field applied. The first
+    /// measurement after the change of the gain is thrown away.
+    fn measure_with_bias(&self, mode: MeasurementMode, gain: Gain, samples: u8) -> Result<(f32, f32, f32), PeripheryError> {
+        let config_a = ConfigurationRegisterA {
+            samples: SamplesAveraged::Samples8,
+            data_output_rate: DataOutputRate::Output_15Hz,
+            measurement_mode: mode
         };
-        try!(self.registers.config_b.write(&self.bus, &config_b));
-
-        {
-            let mut sample = |m: i32| {
-                // throw away initial measurements
-                try!(read_single());
-                try!(read_single());
-
-                for i in 0..samples_per_bias {
-                    let data = try!(read_single());
-                    sum.0 += (data.x as i32) * m;
-                    sum.1 += (data.y as i32) * m;
-                    sum.2 += (data.z as i32) * m;
-                }
-                Ok(())
-            };       
-            
-
-            let mut config_a = ConfigurationRegisterA {
-                samples: SamplesAveraged::Samples8,
-                data_output_rate: DataOutputRate::Output_15Hz,
-                measurement_mode: MeasurementMode::PositiveBias
-            };
-            try!(self.registers.config_a.write(&self.bus, &config_a));            
-
-            // Sample with positive bias
-            try!(sample(1));
-
-            config_a.measurement_mode = MeasurementMode::NegativeBias;
-            try!(self.registers.config_a.write(&self.bus, &config_a));
-
-            // Sample with negative bias
-            try!(sample(-1));
+        self.registers().config_a().write(&config_a)?;
+        self.registers().config_b().write(&ConfigurationRegisterB { gain: gain })?;
+
+        self.read_single()?;
+
+        let mut sum = (0 as i32, 0 as i32, 0 as i32);
+        for _ in 0..samples {
+            let data = self.read_single()?;
+            sum.0 += data.x as i32;
+            sum.1 += data.y as i32;
+            sum.2 += data.z as i32;
         }
 
-        // Reset to defaults
-        try!(self.init_defaults());
+        let n = samples as f32;
+        Ok((sum.0 as f32 / n, sum.1 as f32 / n, sum.2 as f32 / n))
+    }
+
+    /// Gains that correct the sensitivity of the individual axes, measured with the
+    /// internal bias field. The device will be reset to default settings afterwards.
+    pub fn self_calibrate(&self) -> Result<MagneticGains, PeripheryError> {
+        let samples = 10;
+        let gain = Gain::Gain_660;
 
-        let g = (config_b.gain.get_lsb_per_gauss() as f32);
-        let samples = 2.0 * (samples_per_bias as f32);
+        let positive = self.measure_with_bias(MeasurementMode::PositiveBias, gain, samples);
+        let negative = self.measure_with_bias(MeasurementMode::NegativeBias, gain, samples);
+        self.init_defaults()?;
+        let (positive, negative) = (positive?, negative?);
+
+        let g = gain.get_lsb_per_gauss() as f32;
         Ok(MagneticGains {
-            x: (g * HMC58X3_X_SELF_TEST_GAUSS * samples) / sum.0 as f32,
-            y: (g * HMC58X3_Y_SELF_TEST_GAUSS * samples) / sum.1 as f32,
-            z: (g * HMC58X3_Z_SELF_TEST_GAUSS * samples) / sum.2 as f32
+            x: (g * HMC58X3_X_SELF_TEST_GAUSS) / ((positive.0 - negative.0) / 2.0),
+            y: (g * HMC58X3_Y_SELF_TEST_GAUSS) / ((positive.1 - negative.1) / 2.0),
+            z: (g * HMC58X3_Z_SELF_TEST_GAUSS) / ((positive.2 - negative.2) / 2.0)
         })
     }
-    */
 }
 
 
@@ -185,6 +192,14 @@ impl<S, B> Device for Hmc5883<S, B> where S: SystemApi, B: DeviceRegisterBus {
         Some(self)
     }
 
+    fn get_power_management(&self) -> Option<&PowerManagement> {
+        Some(self)
+    }
+
+    fn get_self_test(&self) -> Option<&SelfTest> {
+        Some(self)
+    }
+
     fn description(&self) -> Cow<str> {
         "HMC5883 3 axis compass".into()
     }
@@ -199,14 +214,76 @@ impl<S, B> Device for Hmc5883<S, B> where S: SystemApi, B: DeviceRegisterBus {
         "hmc5883".into()
     }
 
+    fn info(&self) -> DeviceInfo {
+        DeviceInfo::new("Honeywell", "HMC5883L").with_driver_version(env!("CARGO_PKG_VERSION"))
+    }
+
     fn init_after_detection(&self) -> Result<bool, PeripheryError> {
         self.init_defaults()?;
         Ok(true)
     }    
 }
 
+/// Measurements are triggered one at a time, the chip returns to the idle mode after
+/// each of them. Sleeping also stops the continuous measurement mode, and no new
+/// measurements are triggered until the device is woken up.
+impl<S, B> PowerManagement for Hmc5883<S, B> where S: SystemApi, B: DeviceRegisterBus {
+    fn sleep(&self) -> Result<(), PeripheryError> {
+        self.registers().mode().write(&ModeRegister {
+            high_speed_i2c_enabled: false,
+            operating_mode: OperatingMode::Idle1
+        })?;
+        self.power_state.set(PowerState::Sleep);
+        Ok(())
+    }
+
+    /// The chip leaves the idle mode with the next triggered measurement.
+    fn wake(&self) -> Result<(), PeripheryError> {
+        self.power_state.set(PowerState::Active);
+        Ok(())
+    }
+
+    fn power_state(&self) -> Result<PowerState, PeripheryError> {
+        Ok(self.power_state.get().unwrap_or(PowerState::Active))
+    }
+}
+
+/// Measures the internal bias field in both directions, with the gain and the limits
+/// from the datasheet. The default settings are restored afterwards.
+impl<S, B> SelfTest for Hmc5883<S, B> where S: SystemApi, B: DeviceRegisterBus {
+    fn self_test(&self) -> Result<SelfTestReport, PeripheryError> {
+        let (min, max) = (243.0, 575.0);
+
+        let positive = self.measure_with_bias(MeasurementMode::PositiveBias, Gain::Gain_390, 1);
+        let negative = self.measure_with_bias(MeasurementMode::NegativeBias, Gain::Gain_390, 1);
+        self.init_defaults()?;
+
+        let mut report = SelfTestReport::new();
+        match positive {
+            Ok((x, y, z)) => {
+                report.add_range("positive bias x", x, min, max)
+                      .add_range("positive bias y", y, min, max)
+                      .add_range("positive bias z", z, min, max);
+            },
+            Err(e) => { report.add_error("positive bias", &e); }
+        }
+        match negative {
+            Ok((x, y, z)) => {
+                report.add_range("negative bias x", x, -max, -min)
+                      .add_range("negative bias y", y, -max, -min)
+                      .add_range("negative bias z", z, -max, -min);
+            },
+            Err(e) => { report.add_error("negative bias", &e); }
+        }
+
+        Ok(report)
+    }
+}
+
 impl<S, B> MagneticField3Sensor for Hmc5883<S, B> where S: SystemApi, B: DeviceRegisterBus {
     fn get_magnetic_field_3(&self) -> Result<MagneticField3, PeripheryError> {
+        self.check_awake()?;
+
         let mode = ModeRegister {
             high_speed_i2c_enabled: false,
             operating_mode: OperatingMode::SingleMeasurement
@@ -262,4 +339,23 @@ impl Default for MagneticGains {
             z: 1.0
         }
     }
-}
\ No newline at end of file
+}
+#[test]
+#[cfg(test)]
+fn test_hmc5883_sleep() {
+    let bus = ExpectationBus::new();
+    bus.expect_write(0x02, &[0x02]);
+
+    let sensor = Hmc5883 { system: TestSystemApi, bus: bus.clone(), power_state: PowerStateCell::new() };
+    assert_eq!(PowerState::Active, sensor.power_state().unwrap());
+    sensor.sleep().unwrap();
+    assert_eq!(PowerState::Sleep, sensor.power_state().unwrap());
+
+    // no measurements are triggered while sleeping
+    assert!(sensor.get_magnetic_field_3().is_err());
+    bus.done();
+
+    sensor.wake().unwrap();
+    assert_eq!(PowerState::Active, sensor.power_state().unwrap());
+    bus.done();
+}
@@ -45,13 +45,15 @@ impl DigitalLowPassFilter {
     } 
 }
 
-#[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
-#[packed_struct(bit_numbering="msb0")]
-pub struct ConfigRegister {
-    #[packed_field(bits="2..4", ty="enum")]
-	pub ext_sync_set: ExtSync,
-    #[packed_field(bits="5..7", ty="enum")]
-	pub dlpf_cfg: DigitalLowPassFilter
+register_fields! {
+    #[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
+    #[packed_struct(bit_numbering="msb0")]
+    pub struct ConfigRegister {
+        #[packed_field(bits="2..4", ty="enum")]
+        pub ext_sync_set: ExtSync,
+        #[packed_field(bits="5..7", ty="enum")]
+        pub dlpf_cfg: DigitalLowPassFilter
+    }
 }
 
 
@@ -75,17 +77,19 @@ impl GyroFullScale {
     }
 }
 
-#[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
-#[packed_struct(bit_numbering="msb0")]
-pub struct GyroConfig {
-    #[packed_field(bits="0")]
-	pub x_axis_self_test_enabled: bool,
-    #[packed_field(bits="1")]
-	pub y_axis_self_test_enabled: bool,
-    #[packed_field(bits="2")]
-	pub z_axis_self_test_enabled: bool,
-    #[packed_field(bits="3..4", ty="enum")]
-	pub scale: GyroFullScale
+register_fields! {
+    #[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
+    #[packed_struct(bit_numbering="msb0")]
+    pub struct GyroConfig {
+        #[packed_field(bits="0")]
+        pub x_axis_self_test_enabled: bool,
+        #[packed_field(bits="1")]
+        pub y_axis_self_test_enabled: bool,
+        #[packed_field(bits="2")]
+        pub z_axis_self_test_enabled: bool,
+        #[packed_field(bits="3..4", ty="enum")]
+        pub scale: GyroFullScale
+    }
 }
 
 
@@ -109,17 +113,19 @@ impl AccelerometerFullScale {
     }
 }
 
-#[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
-#[packed_struct(bit_numbering="msb0")]
-pub struct AccelConfig {
-    #[packed_field(bits="0")]
-	pub x_axis_self_test_enabled: bool,
-    #[packed_field(bits="1")]
-	pub y_axis_self_test_enabled: bool,
-    #[packed_field(bits="2")]
-	pub z_axis_self_test_enabled: bool,
-    #[packed_field(bits="3..4", ty="enum")]
-	pub scale: AccelerometerFullScale
+register_fields! {
+    #[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
+    #[packed_struct(bit_numbering="msb0")]
+    pub struct AccelConfig {
+        #[packed_field(bits="0")]
+        pub x_axis_self_test_enabled: bool,
+        #[packed_field(bits="1")]
+        pub y_axis_self_test_enabled: bool,
+        #[packed_field(bits="2")]
+        pub z_axis_self_test_enabled: bool,
+        #[packed_field(bits="3..4", ty="enum")]
+        pub scale: AccelerometerFullScale
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PrimitiveEnum_u8)]
@@ -135,87 +141,99 @@ pub enum ClockSource {
 }
 
 
-#[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
-#[packed_struct(bit_numbering="msb0")]
-pub struct PowerManagement1 {
-    #[packed_field(bits="0")]
-	pub device_reset: bool,
-    #[packed_field(bits="1")]
-	pub sleep: bool,
-    #[packed_field(bits="2")]
-	pub cycle: bool,
-    #[packed_field(bits="4")]
-	pub temperature_disabled: bool,
-    #[packed_field(bits="5..7", ty="enum")]
-	pub clock_source: ClockSource
+register_fields! {
+    #[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
+    #[packed_struct(bit_numbering="msb0")]
+    pub struct PowerManagement1 {
+        #[packed_field(bits="0")]
+        pub device_reset: bool,
+        #[packed_field(bits="1")]
+        pub sleep: bool,
+        #[packed_field(bits="2")]
+        pub cycle: bool,
+        #[packed_field(bits="4")]
+        pub temperature_disabled: bool,
+        #[packed_field(bits="5..7", ty="enum")]
+        pub clock_source: ClockSource
+    }
 }
 
-#[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
-#[packed_struct(bit_numbering="msb0")]
-pub struct SignalPathReset {
-    #[packed_field(bits="5")]
-	pub gyro_reset: bool,
-    #[packed_field(bits="6")]
-	pub accel_reset: bool,
-    #[packed_field(bits="7")]
-	pub temperature_reset: bool
+register_fields! {
+    #[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
+    #[packed_struct(bit_numbering="msb0")]
+    pub struct SignalPathReset {
+        #[packed_field(bits="5")]
+        pub gyro_reset: bool,
+        #[packed_field(bits="6")]
+        pub accel_reset: bool,
+        #[packed_field(bits="7")]
+        pub temperature_reset: bool
+    }
 }
 
-#[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
-#[packed_struct(bit_numbering="msb0")]
-pub struct InterruptPinConfig {
-    #[packed_field(bits="0")]
-	pub int_level: bool,
-    #[packed_field(bits="1")]
-	pub int_open: bool,
-    #[packed_field(bits="2")]
-	pub latch_interrupt_enable: bool,
-    #[packed_field(bits="3")]
-	pub interrupt_ready_clear: bool,
-    #[packed_field(bits="4")]
-	pub fsync_int_level: bool,
-    // todo: check specs?
-    #[packed_field(bits="6")]
-	pub fsync_int_en: bool
+register_fields! {
+    #[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
+    #[packed_struct(bit_numbering="msb0")]
+    pub struct InterruptPinConfig {
+        #[packed_field(bits="0")]
+        pub int_level: bool,
+        #[packed_field(bits="1")]
+        pub int_open: bool,
+        #[packed_field(bits="2")]
+        pub latch_interrupt_enable: bool,
+        #[packed_field(bits="3")]
+        pub interrupt_ready_clear: bool,
+        #[packed_field(bits="4")]
+        pub fsync_int_level: bool,
+        // todo: check specs?
+        #[packed_field(bits="6")]
+        pub fsync_int_en: bool
+    }
 }
 
-#[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
-#[packed_struct(bit_numbering="msb0")]
-pub struct InterruptEnable {
-    #[packed_field(bits="3")]
-	pub fifo_oflow_en: bool,
-    #[packed_field(bits="4")]
-	pub i2c_mst_int_en: bool,
-    #[packed_field(bits="7")]
-	pub data_rdy_en: bool
+register_fields! {
+    #[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
+    #[packed_struct(bit_numbering="msb0")]
+    pub struct InterruptEnable {
+        #[packed_field(bits="3")]
+        pub fifo_oflow_en: bool,
+        #[packed_field(bits="4")]
+        pub i2c_mst_int_en: bool,
+        #[packed_field(bits="7")]
+        pub data_rdy_en: bool
+    }
 }
 
-#[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
-#[packed_struct(bit_numbering="msb0")]
-pub struct InterruptStatus {
-    #[packed_field(bits="3")]
-	pub fifo_oflow_int: bool,
-    #[packed_field(bits="4")]
-	pub i2c_mst_int: bool,
-    #[packed_field(bits="7")]
-	pub data_rdy_int: bool
+register_fields! {
+    #[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
+    #[packed_struct(bit_numbering="msb0")]
+    pub struct InterruptStatus {
+        #[packed_field(bits="3")]
+        pub fifo_oflow_int: bool,
+        #[packed_field(bits="4")]
+        pub i2c_mst_int: bool,
+        #[packed_field(bits="7")]
+        pub data_rdy_int: bool
+    }
 }
 
-#[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
-#[packed_struct(bit_numbering="msb0")]
-pub struct UserControl {
-    #[packed_field(bits="1")]
-	pub fifo_en: bool,
-    #[packed_field(bits="2")]
-	pub i2c_mst_en: bool,
-    #[packed_field(bits="3")]
-	pub i2c_if_dis: bool,
-    #[packed_field(bits="5")]
-	pub fifo_reset: bool,
-    #[packed_field(bits="6")]
-	pub i2c_mst_reset: bool,
-    #[packed_field(bits="7")]
-	pub sig_cond_reset: bool
+register_fields! {
+    #[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
+    #[packed_struct(bit_numbering="msb0")]
+    pub struct UserControl {
+        #[packed_field(bits="1")]
+        pub fifo_en: bool,
+        #[packed_field(bits="2")]
+        pub i2c_mst_en: bool,
+        #[packed_field(bits="3")]
+        pub i2c_if_dis: bool,
+        #[packed_field(bits="5")]
+        pub fifo_reset: bool,
+        #[packed_field(bits="6")]
+        pub i2c_mst_reset: bool,
+        #[packed_field(bits="7")]
+        pub sig_cond_reset: bool
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PrimitiveEnum_u8)]
@@ -226,23 +244,25 @@ pub enum WakeCtrl {
     Freq_40Hz = 3
 }
 
-#[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
-#[packed_struct(bit_numbering="msb0")]
-pub struct PowerManagement2 {
-    #[packed_field(bits="0..1", ty="enum")]
-	pub lp_wake_ctrl: WakeCtrl,
-    #[packed_field(bits="2")]
-	pub stby_xa: bool,
-    #[packed_field(bits="3")]
-	pub stby_ya: bool,
-    #[packed_field(bits="4")]
-	pub stby_za: bool,
-    #[packed_field(bits="5")]
-	pub stby_xg: bool,
-    #[packed_field(bits="6")]
-	pub stby_yg: bool,
-    #[packed_field(bits="7")]
-	pub stby_zg: bool
+register_fields! {
+    #[derive(PackedStruct, Copy, Clone, Debug, PartialEq)]
+    #[packed_struct(bit_numbering="msb0")]
+    pub struct PowerManagement2 {
+        #[packed_field(bits="0..1", ty="enum")]
+        pub lp_wake_ctrl: WakeCtrl,
+        #[packed_field(bits="2")]
+        pub stby_xa: bool,
+        #[packed_field(bits="3")]
+        pub stby_ya: bool,
+        #[packed_field(bits="4")]
+        pub stby_za: bool,
+        #[packed_field(bits="5")]
+        pub stby_xg: bool,
+        #[packed_field(bits="6")]
+        pub stby_yg: bool,
+        #[packed_field(bits="7")]
+        pub stby_zg: bool
+    }
 }
 
 
@@ -279,3 +299,21 @@ pub struct AccTempGyroData {
     #[packed_field(size_bytes="6")]
     pub gyroscope: GyroscopeData
 }
+
+register_field_enums!(ExtSync, DigitalLowPassFilter, GyroFullScale, AccelerometerFullScale, ClockSource, WakeCtrl);
+
+#[test]
+#[cfg(test)]
+fn test_mpu_register_fields() {
+    // every packed field has an entry in the field table
+    assert_eq!(Some(vec![]), unmapped_register_bits::<ConfigRegister>(1));
+    assert_eq!(Some(vec![]), unmapped_register_bits::<GyroConfig>(1));
+    assert_eq!(Some(vec![]), unmapped_register_bits::<AccelConfig>(1));
+    assert_eq!(Some(vec![]), unmapped_register_bits::<PowerManagement1>(1));
+    assert_eq!(Some(vec![]), unmapped_register_bits::<PowerManagement2>(1));
+    assert_eq!(Some(vec![]), unmapped_register_bits::<SignalPathReset>(1));
+    assert_eq!(Some(vec![]), unmapped_register_bits::<InterruptPinConfig>(1));
+    assert_eq!(Some(vec![]), unmapped_register_bits::<InterruptEnable>(1));
+    assert_eq!(Some(vec![]), unmapped_register_bits::<InterruptStatus>(1));
+    assert_eq!(Some(vec![]), unmapped_register_bits::<UserControl>(1));
+}
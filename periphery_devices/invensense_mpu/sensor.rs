@@ -7,29 +7,44 @@ use registers::*;
 use packed_struct::*;
 
 
-registers!(
-  chip MpuRegisters {
-    register [0x19; 1] => sample_rate_divider: u8,
-    register [0x1A; 1] => config: ConfigRegister,
-    register [0x1B; 1] => gyro_config: GyroConfig,
-    register [0x1C; 1] => accel_config: AccelConfig,
-
-    register [0x3B; 6] => accel: AcceleratorData,
-    register [0x41; 2] => temp: TemperatureData,
-    register [0x3B; 14] => acc_temp_gyro: AccTempGyroData,
-    register [0x43; 6] => gyro: GyroscopeData,
-
-    register [0x68; 1] => signal_reset: SignalPathReset,
-    register [0x6b; 1] => power1: PowerManagement1,
-    register [0x6c; 1] => power2: PowerManagement2,
-    register [0x37; 1] => int_pin_cfg: InterruptPinConfig,
-    register [0x38; 1] => interrupt_enable: InterruptEnable,
-    register [0x3A; 1] => interrupt_status: InterruptStatus,
-    register [0x6A; 1] => usr_control: UserControl,
-
-  	register [0x75; 1] => who_am_i: u8
-  }
-);
+#[register_map]
+pub struct MpuRegisters {
+    #[register(address = 0x19)]
+    sample_rate_divider: u8,
+    #[register(address = 0x1A, fields)]
+    config: ConfigRegister,
+    #[register(address = 0x1B, fields)]
+    gyro_config: GyroConfig,
+    #[register(address = 0x1C, fields)]
+    accel_config: AccelConfig,
+
+    #[register(address = 0x3B, size = 6, access = "ro")]
+    accel: AcceleratorData,
+    #[register(address = 0x41, size = 2, access = "ro")]
+    temp: TemperatureData,
+    #[register(address = 0x3B, size = 14, access = "ro")]
+    acc_temp_gyro: AccTempGyroData,
+    #[register(address = 0x43, size = 6, access = "ro")]
+    gyro: GyroscopeData,
+
+    #[register(address = 0x68, fields)]
+    signal_reset: SignalPathReset,
+    #[register(address = 0x6B, fields)]
+    power1: PowerManagement1,
+    #[register(address = 0x6C, fields)]
+    power2: PowerManagement2,
+    #[register(address = 0x37, fields)]
+    int_pin_cfg: InterruptPinConfig,
+    #[register(address = 0x38, fields)]
+    interrupt_enable: InterruptEnable,
+    #[register(address = 0x3A, access = "ro", volatile, fields)]
+    interrupt_status: InterruptStatus,
+    #[register(address = 0x6A, fields)]
+    usr_control: UserControl,
+
+    #[register(address = 0x75, access = "ro")]
+    who_am_i: u8
+}
 
 
 pub type InvensenseMpuOnI2CBus<B> = InvensenseMpu<<B as Bus>::SystemApi, <<<B as Bus>::I2C as I2CBus>::DeviceFactory as I2CBusDeviceFactory>::Registers>;
@@ -192,6 +207,45 @@ impl<S, B> InvensenseMpu<S, B> where S: SystemApi, B: DeviceRegisterBus {
         let s = self.registers().sample_rate_divider().read()?;
         Ok(config.dlpf_cfg.get_gyroscope_output_rate_hz() / (1 + s as u32))
     }
+
+    /// Average accelerometer and gyroscope readings, in g and degrees per second, with
+    /// the self-test actuation turned on or off on all the axes.
+    fn measure_self_test_response(&self, self_test_enabled: bool, samples: u16) -> Result<([f32; 3], [f32; 3]), PeripheryError> {
+        let accel_scale = AccelerometerFullScale::Scale_8g;
+        let gyro_scale = GyroFullScale::Scale_250;
+
+        self.registers().accel_config().write(&AccelConfig {
+            x_axis_self_test_enabled: self_test_enabled,
+            y_axis_self_test_enabled: self_test_enabled,
+            z_axis_self_test_enabled: self_test_enabled,
+            scale: accel_scale
+        })?;
+        self.registers().gyro_config().write(&GyroConfig {
+            x_axis_self_test_enabled: self_test_enabled,
+            y_axis_self_test_enabled: self_test_enabled,
+            z_axis_self_test_enabled: self_test_enabled,
+            scale: gyro_scale
+        })?;
+        // let the outputs settle
+        self.system.sleep_ms(250);
+
+        let mut accel = [0 as i32; 3];
+        let mut gyro = [0 as i32; 3];
+        for _ in 0..samples {
+            let a = self.get_acceleration_3_raw()?;
+            let g = self.get_angular_speed_3_raw()?;
+            accel[0] += a.x as i32; accel[1] += a.y as i32; accel[2] += a.z as i32;
+            gyro[0] += g.x as i32; gyro[1] += g.y as i32; gyro[2] += g.z as i32;
+            self.system.sleep_ms(2);
+        }
+
+        let a = accel_scale.get_lsb_per_g() as f32 * samples as f32;
+        let g = gyro_scale.get_lsb_per_deg_per_s() * samples as f32;
+        Ok((
+            [accel[0] as f32 / a, accel[1] as f32 / a, accel[2] as f32 / a],
+            [gyro[0] as f32 / g, gyro[1] as f32 / g, gyro[2] as f32 / g]
+        ))
+    }
 }
 
 
@@ -204,6 +258,14 @@ impl<S, B> Device for InvensenseMpu<S, B> where S: SystemApi, B: DeviceRegisterB
         Some(self)
     }
 
+    fn get_power_management(&self) -> Option<&PowerManagement> {
+        Some(self)
+    }
+
+    fn get_self_test(&self) -> Option<&SelfTest> {
+        Some(self)
+    }
+
     fn get_ambient_temperature_sensor(&self) -> Option<&AmbientTemperatureSensor> {
         Some(self)
     }
@@ -216,6 +278,12 @@ impl<S, B> Device for InvensenseMpu<S, B> where S: SystemApi, B: DeviceRegisterB
         "mpu".into()
     }
 
+    fn info(&self) -> DeviceInfo {
+        DeviceInfo::new("InvenSense", self.chip.get_part_number())
+            .with_variant(format!("{:?}", self.chip))
+            .with_driver_version(env!("CARGO_PKG_VERSION"))
+    }
+
     fn get_registers_cli(&self) -> Option<DeviceBusCli> {
         let mut c = DeviceBusCli::new();
         c.with_registers(self.registers());
@@ -255,6 +323,38 @@ impl<S, B> AmbientTemperatureSensor for InvensenseMpu<S, B> where S: SystemApi,
     }
 }
 
+/// The response to the self-test actuation is the difference between the outputs with
+/// the self-test enabled and disabled. The device has to be kept still during the test.
+/// The limits are coarse ones that hold for the whole family, the factory trim values
+/// aren't used. The default settings are restored afterwards.
+impl<S, B> SelfTest for InvensenseMpu<S, B> where S: SystemApi, B: DeviceRegisterBus {
+    fn self_test(&self) -> Result<SelfTestReport, PeripheryError> {
+        let samples = 50;
+        let axes = ["x", "y", "z"];
+
+        let off = self.measure_self_test_response(false, samples);
+        let on = self.measure_self_test_response(true, samples);
+        self.init_defaults()?;
+
+        let mut report = SelfTestReport::new();
+        match (off, on) {
+            (Ok((accel_off, gyro_off)), Ok((accel_on, gyro_on))) => {
+                for i in 0..3 {
+                    report.add_range(format!("accelerometer {} response [g]", axes[i]), (accel_on[i] - accel_off[i]).abs(), 0.2, 1.2);
+                }
+                for i in 0..3 {
+                    report.add_range(format!("gyroscope {} response [deg/s]", axes[i]), (gyro_on[i] - gyro_off[i]).abs(), 10.0, 150.0);
+                }
+            },
+            (Err(e), _) | (_, Err(e)) => {
+                report.add_error("self-test response", &e);
+            }
+        }
+
+        Ok(report)
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum InvenseMpuChip {
     Mpu60x0,
@@ -263,7 +363,53 @@ pub enum InvenseMpuChip {
     Icm20602
 }
 
+impl<S, B> PowerManagement for InvensenseMpu<S, B> where S: SystemApi, B: DeviceRegisterBus {
+    fn sleep(&self) -> Result<(), PeripheryError> {
+        self.registers().power1().modify(|p| {
+            p.sleep = true;
+            p.cycle = false;
+        })?;
+        Ok(())
+    }
+
+    fn wake(&self) -> Result<(), PeripheryError> {
+        self.registers().power1().modify(|p| {
+            p.sleep = false;
+        })?;
+        // the oscillator and the gyroscopes need time to start up
+        self.system.sleep_ms(50);
+        Ok(())
+    }
+
+    fn soft_reset(&self) -> Result<(), PeripheryError> {
+        self.reset_device()
+    }
+
+    /// All the axes being in standby also counts as sleeping.
+    fn power_state(&self) -> Result<PowerState, PeripheryError> {
+        if self.registers().power1().read()?.sleep {
+            return Ok(PowerState::Sleep);
+        }
+
+        let p = self.registers().power2().read()?;
+        if p.stby_xa && p.stby_ya && p.stby_za && p.stby_xg && p.stby_yg && p.stby_zg {
+            return Ok(PowerState::Sleep);
+        }
+
+        Ok(PowerState::Active)
+    }
+}
+
 impl InvenseMpuChip {
+    pub fn get_part_number(&self) -> &'static str {
+        match *self {
+            InvenseMpuChip::Mpu60x0 => "MPU-60X0",
+            InvenseMpuChip::Mpu65x0 => "MPU-65X0",
+            InvenseMpuChip::Icm20601 => "ICM-20601",
+            InvenseMpuChip::Icm20602 => "ICM-20602"
+        }
+    }
+
     pub fn from_who_am_i(id: u8) -> Option<InvenseMpuChip> {
         match id {
             0x68 => Some(InvenseMpuChip::Mpu60x0),
@@ -317,3 +463,28 @@ impl AngularSpeed3Raw {
         }
     }
 }
+
+#[test]
+#[cfg(test)]
+fn test_mpu_power_management() {
+    let bus = ExpectationBus::new();
+    bus.expect_read(0x6B, &[0x01])
+       .expect_write(0x6B, &[0x41])
+       .expect_read(0x6B, &[0x41])
+       .expect_read(0x6B, &[0x41])
+       .expect_write(0x6B, &[0x01])
+       .expect_read(0x6B, &[0x01])
+       .expect_read(0x6C, &[0x00])
+       // all the axes in standby
+       .expect_read(0x6B, &[0x01])
+       .expect_read(0x6C, &[0x3F]);
+
+    let sensor = InvensenseMpu { system: TestSystemApi, bus: bus.clone(), chip: InvenseMpuChip::Mpu60x0 };
+    sensor.sleep().unwrap();
+    assert_eq!(PowerState::Sleep, sensor.power_state().unwrap());
+    sensor.wake().unwrap();
+    assert_eq!(PowerState::Active, sensor.power_state().unwrap());
+    assert_eq!(PowerState::Sleep, sensor.power_state().unwrap());
+
+    bus.done();
+}
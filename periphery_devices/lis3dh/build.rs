@@ -0,0 +1,7 @@
+extern crate periphery_register_codegen;
+
+fn main() {
+    if let Err(e) = periphery_register_codegen::generate_registers("lis3dh.toml", "lis3dh_registers.rs") {
+        panic!("{}", e);
+    }
+}
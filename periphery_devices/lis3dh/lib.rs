@@ -0,0 +1,17 @@
+#[macro_use]
+extern crate periphery_core;
+#[macro_use]
+extern crate periphery_core_codegen;
+
+extern crate packed_struct;
+
+#[macro_use]
+extern crate packed_struct_codegen;
+
+mod sensor;
+mod registers {
+    include!(concat!(env!("OUT_DIR"), "/lis3dh_registers.rs"));
+}
+
+pub use self::sensor::*;
+pub use self::registers::*;
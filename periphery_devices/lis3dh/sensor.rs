@@ -0,0 +1,188 @@
+use periphery_core::*;
+use periphery_core::prelude::v1::*;
+
+use registers::*;
+
+pub const LIS3DH_WHO_AM_I: u8 = 0x33;
+
+pub type Lis3dhOnI2CBus<B> = Lis3dh<<B as Bus>::SystemApi, <<<B as Bus>::I2C as I2CBus>::DeviceFactory as I2CBusDeviceFactory>::Registers>;
+
+#[derive(Clone, Copy)]
+pub struct Lis3dhFactory {
+    addresses: [I2CAddress; 2]
+}
+
+impl Default for Lis3dhFactory {
+    fn default() -> Self {
+        Lis3dhFactory {
+            addresses: [
+                I2CAddress::address_7bit(0x18),
+                I2CAddress::address_7bit(0x19)
+            ]
+        }
+    }
+}
+
+impl<B> DeviceI2CDetection<Lis3dhOnI2CBus<B>, B, I2CDeviceRegisters<B>> for Lis3dhFactory
+    where B: Bus + 'static,
+{
+	fn get_addresses(&self) -> &[I2CAddress] {
+        &self.addresses
+    }
+
+	fn new(args: I2CDeviceRegisters<B>) -> Result<Lis3dhOnI2CBus<B>, PeripheryError> {
+        let sensor = Lis3dh {
+            system: args.system_api,
+            bus: args.device_bus
+        };
+
+        let id = sensor.registers().who_am_i().read()?;
+        if id != LIS3DH_WHO_AM_I {
+            return Err(PeripheryError::UnsupportedDevice);
+        }
+
+        Ok(sensor)
+    }
+}
+
+#[derive(Clone)]
+pub struct Lis3dh<S, B> where S: SystemApi, B: DeviceRegisterBus {
+    system: S,
+    bus: B
+}
+
+impl<S, B> Lis3dh<S, B> where S: SystemApi, B: DeviceRegisterBus {
+    #[inline]
+    pub fn registers(&self) -> Lis3dhRegisters<B> {
+        Lis3dhRegisters::new(&self.bus)
+    }
+
+    pub fn init_defaults(&self) -> Result<(), PeripheryError> {
+        self.registers().ctrl_reg1().write(&ControlRegister1 {
+            output_data_rate: OutputDataRate::Rate100Hz,
+            low_power_enabled: false,
+            z_enabled: true,
+            y_enabled: true,
+            x_enabled: true
+        })?;
+
+        self.registers().ctrl_reg4().write(&ControlRegister4 {
+            block_data_update: true,
+            big_endian: false,
+            full_scale: FullScale::Scale4g,
+            high_resolution: true,
+            self_test: SelfTestMode::Normal,
+            spi_3wire: false
+        })?;
+
+        Ok(())
+    }
+
+    pub fn get_acceleration_3_raw(&self) -> Result<Acceleration3Raw, PeripheryError> {
+        let data = self.registers().acceleration().read()?;
+
+        Ok(Acceleration3Raw { x: data.x, y: data.y, z: data.z })
+    }
+}
+
+impl<S, B> Device for Lis3dh<S, B> where S: SystemApi, B: DeviceRegisterBus {
+    fn description(&self) -> Cow<str> {
+        "ST LIS3DH accelerometer".into()
+    }
+
+    fn id(&self) -> Cow<str> {
+        "lis3dh".into()
+    }
+
+    fn info(&self) -> DeviceInfo {
+        DeviceInfo::new("STMicroelectronics", "LIS3DH").with_driver_version(env!("CARGO_PKG_VERSION"))
+    }
+
+    fn get_acceleration_3_sensor(&self) -> Option<&Acceleration3Sensor> {
+        Some(self)
+    }
+
+    fn get_registers_cli(&self) -> Option<DeviceBusCli> {
+        let mut c = DeviceBusCli::new();
+        c.with_registers(self.registers());
+        Some(c)
+    }
+
+    fn init_after_detection(&self) -> Result<bool, PeripheryError> {
+        self.init_defaults()?;
+        Ok(true)
+    }
+}
+
+impl<S, B> Acceleration3Sensor for Lis3dh<S, B> where S: SystemApi, B: DeviceRegisterBus {
+    fn get_acceleration_3(&self) -> Result<Acceleration3, PeripheryError> {
+        let raw = self.get_acceleration_3_raw()?;
+        let ctrl_reg1 = self.registers().ctrl_reg1().read()?;
+        let ctrl_reg4 = self.registers().ctrl_reg4().read()?;
+
+        Ok(raw.to_std_units(&ctrl_reg1, &ctrl_reg4))
+    }
+}
+
+impl FullScale {
+    /// Sensitivity in mg per digit, in the 12-bit high resolution mode.
+    pub fn get_mg_per_digit(&self) -> f32 {
+        match *self {
+            FullScale::Scale2g => 1.0,
+            FullScale::Scale4g => 2.0,
+            FullScale::Scale8g => 4.0,
+            FullScale::Scale16g => 12.0
+        }
+    }
+}
+
+/// Left-justified output of the chip.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Acceleration3Raw {
+    pub x: i16,
+    pub y: i16,
+    pub z: i16
+}
+
+impl Acceleration3Raw {
+    pub fn to_std_units(&self, ctrl_reg1: &ControlRegister1, ctrl_reg4: &ControlRegister4) -> Acceleration3 {
+        // 12 bits in the high resolution mode, 10 bits in normal and 8 in the low power mode
+        let (shift, scale) = if ctrl_reg1.low_power_enabled {
+            (8, 16.0)
+        } else if ctrl_reg4.high_resolution {
+            (4, 1.0)
+        } else {
+            (6, 4.0)
+        };
+        let scale = scale * ctrl_reg4.full_scale.get_mg_per_digit() / 1000.0;
+
+        Acceleration3::new(
+            GForce::from_g_force(scale * (self.x >> shift) as f32),
+            GForce::from_g_force(scale * (self.y >> shift) as f32),
+            GForce::from_g_force(scale * (self.z >> shift) as f32),
+        )
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn test_lis3dh_init_and_read() {
+    let bus = ExpectationBus::new();
+    bus.expect_write(0x20, &[0x57])
+       .expect_write(0x23, &[0x98]);
+
+    let sensor = Lis3dh { system: TestSystemApi, bus: bus.clone() };
+    assert_eq!(true, sensor.init_after_detection().unwrap());
+    bus.done();
+
+    // +/- 500 digits at 2 mg/digit, left-justified by 4 bits
+    bus.expect_read(0xA8, &[0x40, 0x1F, 0xC0, 0xE0, 0x00, 0x00])
+       .expect_read(0x20, &[0x57])
+       .expect_read(0x23, &[0x98]);
+
+    let a = sensor.get_acceleration_3().unwrap();
+    assert!((a.x.get_g_force() - 1.0).abs() < 0.001);
+    assert!((a.y.get_g_force() + 1.0).abs() < 0.001);
+    assert!(a.z.get_g_force().abs() < 0.001);
+    bus.done();
+}
@@ -1,5 +1,7 @@
 #[macro_use]
 extern crate periphery_core;
+#[macro_use]
+extern crate periphery_core_codegen;
 
 extern crate packed_struct;
 
@@ -33,35 +33,55 @@ impl Oversampling {
 type MsbU16 = MsbInteger<u16, packed_bits::Bits16, Integer<u16, packed_bits::Bits16>>;
 type MsbU24 = MsbInteger<u32, packed_bits::Bits24, Integer<u32, packed_bits::Bits24>>;
 
-registers!(
-  chip Ms5611Registers {
-    register [0x00; 3] => adc: MsbU24,
-
-    register [0x1E; 1] => reset: u8,
-
-  	register [0xA0; 2] => factory_data: MsbU16,
-    register [0xA2; 2] => coeff_1: MsbU16,
-    register [0xA4; 2] => coeff_2: MsbU16,
-    register [0xA6; 2] => coeff_3: MsbU16,
-    register [0xA8; 2] => coeff_4: MsbU16,
-    register [0xAA; 2] => coeff_5: MsbU16,
-    register [0xAC; 2] => coeff_6: MsbU16,
-    register [0xAE; 2] => serial_and_crc: MsbU16,
-    //register [0xAF; 1] => crc: Crc,
-
-    register [0x40; 1] => d1_osr_0: u8,
-    register [0x42; 1] => d1_osr_1: u8,
-    register [0x44; 1] => d1_osr_2: u8,
-    register [0x46; 1] => d1_osr_3: u8,
-    register [0x48; 1] => d1_osr_4: u8,
-
-    register [0x50; 1] => d2_osr_0: u8,
-    register [0x52; 1] => d2_osr_1: u8,
-    register [0x54; 1] => d2_osr_2: u8,
-    register [0x56; 1] => d2_osr_3: u8,
-    register [0x58; 1] => d2_osr_4: u8    
-  }
-);
+#[register_map]
+pub struct Ms5611Registers {
+    #[register(address = 0x00, size = 3, access = "ro")]
+    adc: MsbU24,
+
+    #[register(address = 0x1E, access = "wo")]
+    reset: u8,
+
+    #[register(address = 0xA0, size = 2, access = "ro")]
+    factory_data: MsbU16,
+    #[register(address = 0xA2, size = 2, access = "ro")]
+    coeff_1: MsbU16,
+    #[register(address = 0xA4, size = 2, access = "ro")]
+    coeff_2: MsbU16,
+    #[register(address = 0xA6, size = 2, access = "ro")]
+    coeff_3: MsbU16,
+    #[register(address = 0xA8, size = 2, access = "ro")]
+    coeff_4: MsbU16,
+    #[register(address = 0xAA, size = 2, access = "ro")]
+    coeff_5: MsbU16,
+    #[register(address = 0xAC, size = 2, access = "ro")]
+    coeff_6: MsbU16,
+    #[register(address = 0xAE, size = 2, access = "ro")]
+    serial_and_crc: MsbU16,
+    //#[register(address = 0xAF)]
+    //crc: Crc,
+
+    #[register(address = 0x40, access = "wo")]
+    d1_osr_0: u8,
+    #[register(address = 0x42, access = "wo")]
+    d1_osr_1: u8,
+    #[register(address = 0x44, access = "wo")]
+    d1_osr_2: u8,
+    #[register(address = 0x46, access = "wo")]
+    d1_osr_3: u8,
+    #[register(address = 0x48, access = "wo")]
+    d1_osr_4: u8,
+
+    #[register(address = 0x50, access = "wo")]
+    d2_osr_0: u8,
+    #[register(address = 0x52, access = "wo")]
+    d2_osr_1: u8,
+    #[register(address = 0x54, access = "wo")]
+    d2_osr_2: u8,
+    #[register(address = 0x56, access = "wo")]
+    d2_osr_3: u8,
+    #[register(address = 0x58, access = "wo")]
+    d2_osr_4: u8
+}
 
 #[derive(PackedStruct, Debug, Copy, Clone)]
 #[packed_struct(endian="msb")]
@@ -287,6 +307,10 @@ impl<S, B> Device for Ms5611<S, B> where S: SystemApi, B: DeviceRegisterBus {
         Some(self)
     }
 
+    fn get_self_test(&self) -> Option<&SelfTest> {
+        Some(self)
+    }
+
     fn description(&self) -> Cow<str> {
         "MS5611 barometer".into()
     }
@@ -300,6 +324,24 @@ impl<S, B> Device for Ms5611<S, B> where S: SystemApi, B: DeviceRegisterBus {
     fn id(&self) -> Cow<str> {
         "ms5611".into()
     }
+
+    fn info(&self) -> DeviceInfo {
+        DeviceInfo::new("TE Connectivity", "MS5611-01BA03").with_driver_version(env!("CARGO_PKG_VERSION"))
+    }
+}
+
+/// The chip has no self-test of its own, the integrity of the calibration PROM is
+/// verified with its CRC.
+impl<S, B> SelfTest for Ms5611<S, B> where S: SystemApi, B: DeviceRegisterBus {
+    fn self_test(&self) -> Result<SelfTestReport, PeripheryError> {
+        let mut report = SelfTestReport::new();
+        match self.read_calibration_data() {
+            Ok(_) => { report.add_check("PROM CRC", true); },
+            Err(e @ PeripheryError::CrcMismatch { .. }) => { report.add_error("PROM CRC", &e); },
+            Err(e) => return Err(e)
+        }
+        Ok(report)
+    }
 }
 
 impl<S, B> AmbientTemperatureSensor for Ms5611<S, B> where S: SystemApi, B: DeviceRegisterBus {
@@ -48,9 +48,10 @@ impl<B> DeviceI2CDetection<Sht3xOnI2CBus<B>, B, I2CDeviceAll<B>> for Sht3xFactor
     }
 
 	fn new(args: I2CDeviceAll<B>) -> Result<Sht3xOnI2CBus<B>, PeripheryError> {
-        let sensor = Sht3x {
+        let mut sensor = Sht3x {
             system: args.system_api,
-            bus: args.device_data
+            bus: args.device_data,
+            serial_number: None
         };
 
         sensor.bus.transmit(&[0x30, 0x41])?;
@@ -59,7 +60,9 @@ impl<B> DeviceI2CDetection<Sht3xOnI2CBus<B>, B, I2CDeviceAll<B>> for Sht3xFactor
         if r[0] != 0 || r[1] != 0 {
             return Err(PeripheryError::DeviceNotFound);
         }
-        
+
+        sensor.serial_number = sensor.read_serial_number().ok();
+
         Ok(sensor)
     }
 }
@@ -68,7 +71,9 @@ impl<B> DeviceI2CDetection<Sht3xOnI2CBus<B>, B, I2CDeviceAll<B>> for Sht3xFactor
 #[derive(Clone)]
 pub struct Sht3x<S, B> where S: SystemApi, B: DeviceDataTransfer {
     system: S,
-    bus: B
+    bus: B,
+    /// Read during the detection, so that `info` doesn't have to talk to the chip.
+    serial_number: Option<u32>
 }
 
 impl<S, B> Sht3x<S, B> where S: SystemApi, B: DeviceDataTransfer {
@@ -84,6 +89,49 @@ impl<S, B> Sht3x<S, B> where S: SystemApi, B: DeviceDataTransfer {
 
         Ok(Measurement::unpack(&buffer)?)
     }
+
+    /// The unique 32-bit serial number of the chip.
+    pub fn read_serial_number(&self) -> Result<u32, PeripheryError> {
+        self.bus.transmit(&[0x37, 0x80])?;
+
+        self.system.get_sleep()?.sleep_ms(1);
+
+        let mut buffer = [0; 6];
+        self.bus.receive(&mut buffer)?;
+
+        let mut serial_number = 0;
+        for word in buffer.chunks(3) {
+            let calculated_crc = crc8(&word[..2]);
+            if calculated_crc != word[2] {
+                return Err(PeripheryError::CrcMismatch { expected: word[2] as u16, calculated: calculated_crc as u16 });
+            }
+            serial_number = (serial_number << 16) | ((word[0] as u32) << 8) | (word[1] as u32);
+        }
+
+        Ok(serial_number)
+    }
+}
+
+/// CRC-8 with the polynomial 0x31 and the initial value 0xFF.
+fn crc8(data: &[u8]) -> u8 {
+    let mut crc: u8 = 0xFF;
+    for b in data {
+        crc ^= *b;
+        for _ in 0..8 {
+            if crc & 0x80 != 0 {
+                crc = (crc << 1) ^ 0x31;
+            } else {
+                crc <<= 1;
+            }
+        }
+    }
+    crc
+}
+
+#[test]
+fn test_crc8() {
+    // example from the datasheet
+    assert_eq!(0x92, crc8(&[0xBE, 0xEF]));
 }
 
 
@@ -103,6 +151,15 @@ impl<S, B> Device for Sht3x<S, B> where S: SystemApi, B: DeviceDataTransfer {
 	fn id(&self) -> Cow<str> {
 		"sht3x".into()
 	}
+
+	fn info(&self) -> DeviceInfo {
+		let info = DeviceInfo::new("Sensirion", "SHT3x").with_driver_version(env!("CARGO_PKG_VERSION"));
+
+		match self.serial_number {
+			Some(serial_number) => info.with_serial_number(format!("{:08X}", serial_number)),
+			None => info
+		}
+	}
 }
 
 impl<S, B> AmbientTemperatureSensor for Sht3x<S, B> where S: SystemApi, B: DeviceDataTransfer {
@@ -121,4 +178,14 @@ impl<S, B> AtmosphericHumiditySensor for Sht3x<S, B> where S: SystemApi, B: Devi
         let h = 100.0 * (m.temperature as f32 / (0xFFFF as f32));
         Ok(RelativeHumidity::from_percentage(Percentage::from_percentage(h)))
 	}
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_info_without_bus_traffic() {
+    let bus = ExpectationBus::new();
+    let sensor = Sht3x { system: TestSystemApi, bus: bus.clone(), serial_number: Some(0xBEEF1234) };
+
+    let info = sensor.info();
+    assert_eq!(Some("BEEF1234".into()), info.serial_number);
+    bus.done();
+}
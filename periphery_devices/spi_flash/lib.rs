@@ -1,5 +1,7 @@
 #[macro_use]
 extern crate periphery_core;
+#[macro_use]
+extern crate periphery_core_codegen;
 
 extern crate packed_struct;
 
@@ -71,13 +73,15 @@ pub struct Status {
     pub write_in_progress: bool
 }
 
-registers!(
-  chip FlashRegisters {
-    register [0x9F; 3] => jedec_id: JedecId,
-    register [0x05; 1] => read_status_register: Status,
-    register [0x01; 1] => write_status_register: Status
-  }
-);
+#[register_map]
+pub struct FlashRegisters {
+    #[register(address = 0x9F, size = 3, access = "ro")]
+    jedec_id: JedecId,
+    #[register(address = 0x05, access = "ro")]
+    read_status_register: Status,
+    #[register(address = 0x01, access = "wo")]
+    write_status_register: Status
+}
 
 pub struct SpiFlashFactory;
 impl<S: 'static, B: 'static> DetectableDeviceWithRegistersFactory<SpiFlash<S, B>, S, B> for SpiFlashFactory where S: SystemApi, B: Bus<S> {
@@ -1,5 +1,7 @@
 #[macro_use]
 extern crate periphery_core;
+#[macro_use]
+extern crate periphery_core_codegen;
 
 extern crate packed_struct;
 #[macro_use]
@@ -21,44 +23,66 @@ pub type Ssd1306OnI2CBus<B> =
     >;
 
 
-#[derive(PackedStruct, Copy, Clone, Default)]
+#[derive(PackedStruct, Debug, Copy, Clone, Default)]
 pub struct AddressingRange {
     pub start: u8,
     pub end: u8
 }
 
-commands!(
-  chip Ssd1306Commands {
-    command [0x81; 1] => set_contrast: u8,
-    
-    command [0xAF; 0] => display_on: EmptyReg,
-    command [0xAE; 0] => display_off: EmptyReg,
-    command [0xA5; 0] => display_all_on: EmptyReg,
-    command [0xA4; 0] => display_all_on_resume: EmptyReg,
-
-    command [0x21; 2] => column_address: AddressingRange,
-    command [0x22; 2] => page_address: AddressingRange,
-
-    command [0x8D; 1] => charge_pump: u8,
-    command [0xD5; 1] => display_clock_div: u8,
-    command [0xA8; 1] => set_multiplex: u8,
-    command [0x20; 1] => memory_mode: u8,
-    command [0xA0; 0] => segremap: u8,
-    command [0xA1; 0] => segremap_1: u8,
-
-    command [0xC0; 0] => com_scan_inc: EmptyReg,
-    command [0xC8; 0] => com_scan_dec: EmptyReg,
-    command [0xDA; 1] => comp_ins: u8,
-    command [0xD9; 1] => set_precharge: u8,
-    command [0xA6; 0] => normal_display: EmptyReg,
-    command [0xA7; 0] => invert_display: EmptyReg,
-    command [0xD3; 1] => set_display_offset: u8,
-    command [0x40; 0] => set_start_line: EmptyReg,
-    command [0xDB; 1] => set_vcom_detect: u8,
-
-    command [0x2E; 0] => stop_scroll: EmptyReg
-  }
-);
+#[command_map]
+pub struct Ssd1306Commands {
+    #[command(code = 0x81, size = 1)]
+    set_contrast: u8,
+
+    #[command(code = 0xAF)]
+    display_on: EmptyReg,
+    #[command(code = 0xAE)]
+    display_off: EmptyReg,
+    #[command(code = 0xA5)]
+    display_all_on: EmptyReg,
+    #[command(code = 0xA4)]
+    display_all_on_resume: EmptyReg,
+
+    #[command(code = 0x21, size = 2)]
+    column_address: AddressingRange,
+    #[command(code = 0x22, size = 2)]
+    page_address: AddressingRange,
+
+    #[command(code = 0x8D, size = 1)]
+    charge_pump: u8,
+    #[command(code = 0xD5, size = 1)]
+    display_clock_div: u8,
+    #[command(code = 0xA8, size = 1)]
+    set_multiplex: u8,
+    #[command(code = 0x20, size = 1)]
+    memory_mode: u8,
+    #[command(code = 0xA0)]
+    segremap: u8,
+    #[command(code = 0xA1)]
+    segremap_1: u8,
+
+    #[command(code = 0xC0)]
+    com_scan_inc: EmptyReg,
+    #[command(code = 0xC8)]
+    com_scan_dec: EmptyReg,
+    #[command(code = 0xDA, size = 1)]
+    comp_ins: u8,
+    #[command(code = 0xD9, size = 1)]
+    set_precharge: u8,
+    #[command(code = 0xA6)]
+    normal_display: EmptyReg,
+    #[command(code = 0xA7)]
+    invert_display: EmptyReg,
+    #[command(code = 0xD3, size = 1)]
+    set_display_offset: u8,
+    #[command(code = 0x40)]
+    set_start_line: EmptyReg,
+    #[command(code = 0xDB, size = 1)]
+    set_vcom_detect: u8,
+
+    #[command(code = 0x2E)]
+    stop_scroll: EmptyReg
+}
 
 
 #[derive(Clone, Copy)]
@@ -87,7 +111,8 @@ impl<B> DeviceI2CDetection<Ssd1306OnI2CBus<B>, B, I2CDeviceAll<B>> for Ssd1306Fa
 	fn new(args: I2CDeviceAll<B>) -> Result<Ssd1306OnI2CBus<B>, PeripheryError> {
         let lcd = Ssd1306 {
             bus_commands: args.device_commands,
-            bus_data: args.device_data
+            bus_data: args.device_data,
+            power_state: PowerStateCell::new()
         };
 
         // todo: somehow verify that the controller is responding
@@ -99,7 +124,9 @@ impl<B> DeviceI2CDetection<Ssd1306OnI2CBus<B>, B, I2CDeviceAll<B>> for Ssd1306Fa
 #[derive(Clone)]
 pub struct Ssd1306<C, D> where C: DeviceCommandBus, D: DeviceDataTransfer {
     bus_commands: C,
-    bus_data: D
+    bus_data: D,
+    /// The controller is write-only on I2C, the state can't be read back.
+    power_state: PowerStateCell
 }
 
 impl<C, D> Ssd1306<C, D> where C: DeviceCommandBus, D: DeviceDataTransfer {
@@ -134,6 +161,7 @@ impl<C, D> Ssd1306<C, D> where C: DeviceCommandBus, D: DeviceDataTransfer {
         c.display_all_on_resume().execute()?;
         c.normal_display().execute()?;
         c.display_on().execute()?;
+        self.power_state.set(PowerState::Active);
 
         Ok(())
     }    
@@ -237,6 +265,10 @@ impl<C, D> Device for Ssd1306<C, D> where C: DeviceCommandBus, D: DeviceDataTran
         "ssd1306".into()
     }
 
+    fn info(&self) -> DeviceInfo {
+        DeviceInfo::new("Solomon Systech", "SSD1306").with_driver_version(env!("CARGO_PKG_VERSION"))
+    }
+
 	fn init_after_detection(&self) -> Result<bool, PeripheryError> {
         self.init()?;
 		Ok(true)
@@ -245,6 +277,31 @@ impl<C, D> Device for Ssd1306<C, D> where C: DeviceCommandBus, D: DeviceDataTran
 	fn get_cli(&self) -> Option<&DeviceCli> {
 		Some(self)
 	}    
+
+	fn get_power_management(&self) -> Option<&PowerManagement> {
+		Some(self)
+	}
+}
+
+/// The display RAM is retained while the display is off.
+impl<C, D> PowerManagement for Ssd1306<C, D> where C: DeviceCommandBus, D: DeviceDataTransfer {
+    fn sleep(&self) -> Result<(), PeripheryError> {
+        self.commands().display_off().execute()?;
+        self.power_state.set(PowerState::Sleep);
+        Ok(())
+    }
+
+    fn wake(&self) -> Result<(), PeripheryError> {
+        self.commands().display_on().execute()?;
+        self.power_state.set(PowerState::Active);
+        Ok(())
+    }
+
+    /// The last state commanded by the driver. Not available before the display
+    /// is initialized or put to sleep.
+    fn power_state(&self) -> Result<PowerState, PeripheryError> {
+        self.power_state.get().ok_or(PeripheryError::DataNotAvailable)
+    }
 }
 
 impl<C, D> DeviceCli for Ssd1306<C, D> where C: DeviceCommandBus, D: DeviceDataTransfer {
@@ -295,4 +352,21 @@ fn test_buffer() {
 
     d.set_pixel(127, 31, true);
     //assert_eq!(*d.data.iter().last().unwrap(), 1);
-}
\ No newline at end of file
+}
+#[test]
+#[cfg(test)]
+fn test_power_management() {
+    let bus = ExpectationBus::new();
+    bus.expect_command(&[0xAE])
+       .expect_command(&[0xAF]);
+
+    let lcd = Ssd1306 { bus_commands: bus.clone(), bus_data: bus.clone(), power_state: PowerStateCell::new() };
+    // write-only, nothing known until the first command
+    assert!(lcd.power_state().is_err());
+    lcd.sleep().unwrap();
+    assert_eq!(PowerState::Sleep, lcd.power_state().unwrap());
+    lcd.wake().unwrap();
+    assert_eq!(PowerState::Active, lcd.power_state().unwrap());
+
+    bus.done();
+}
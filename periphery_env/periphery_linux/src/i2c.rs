@@ -60,7 +60,7 @@ impl<S> Bus for LinuxI2CBus<S> where S: SystemApi {
 	}
 
     fn get_cli_prefix(&self) -> Result<Cow<str>, PeripheryError> {
-        Ok(format!("i2c-{}", 1).into())
+        Ok(self.path.trim_left_matches("/dev/").into())
     }
 }
 
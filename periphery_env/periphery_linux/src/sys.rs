@@ -11,6 +11,10 @@ impl SystemApi for StdSystemApi {
 	fn get_debug(&self) -> Result<&SystemApiDebug, PeripheryError> {
 		Ok(self)
 	}
+
+	fn get_clock(&self) -> Result<&SystemApiClock, PeripheryError> {
+		Ok(self)
+	}
 }
 
 impl SystemApiSleep for StdSystemApi {
@@ -19,6 +23,17 @@ impl SystemApiSleep for StdSystemApi {
 	}
 }
 
+impl SystemApiClock for StdSystemApi {
+	fn get_timestamp_us(&self) -> u64 {
+		use std::time::{SystemTime, UNIX_EPOCH};
+
+		match SystemTime::now().duration_since(UNIX_EPOCH) {
+			Ok(d) => d.as_secs() * 1000000 + (d.subsec_nanos() / 1000) as u64,
+			Err(_) => 0
+		}
+	}
+}
+
 impl SystemApiDebug for StdSystemApi {
 	fn debug(&self, line: &str) {
 		println!("[DEBUG] {}", line);
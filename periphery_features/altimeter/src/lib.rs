@@ -0,0 +1,298 @@
+//! Barometric altimeter on top of any `AtmosphericPressureSensor`. The altitude is
+//! relative to a reference pressure, set like on an aircraft altimeter:
+//!
+//! * QNH, the pressure reduced to the mean sea level, the altitude is above the sea
+//! * QFE, the pressure at the airfield, the altitude is the height above the airfield
+//!
+//! With an `AmbientTemperatureSensor` on the same device the altitude is computed with
+//! the hypsometric formula, otherwise with the standard atmosphere.
+
+extern crate periphery_core;
+
+use periphery_core::prelude::v1::*;
+use periphery_core::*;
+
+/// Mean sea level pressure of the standard atmosphere.
+pub const STANDARD_PRESSURE_PA: f32 = 101325.0;
+
+/// Temperature lapse rate of the standard atmosphere, [K/m]
+const LAPSE_RATE: f32 = 0.0065;
+/// g * M / (R * L), the exponent of the barometric formula
+const BAROMETRIC_EXPONENT: f32 = 5.257;
+
+#[derive(Copy, Clone, Debug)]
+pub enum AltimeterReference {
+    /// Pressure at the mean sea level
+    Qnh(Pressure),
+    /// Pressure at the reference point, usually the airfield
+    Qfe(Pressure)
+}
+
+impl AltimeterReference {
+    pub fn standard() -> Self {
+        AltimeterReference::Qnh(Pressure::from_pascal(STANDARD_PRESSURE_PA))
+    }
+
+    pub fn get_pressure(&self) -> Pressure {
+        match *self {
+            AltimeterReference::Qnh(p) | AltimeterReference::Qfe(p) => p
+        }
+    }
+}
+
+impl Default for AltimeterReference {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+/// Altitude at the pressure, relative to the reference pressure. The temperature of
+/// the air selects the hypsometric formula, without it the standard atmosphere is used.
+pub fn pressure_to_altitude(pressure: Pressure, reference: Pressure, temperature: Option<Temperature>) -> Distance {
+    let ratio = reference.get_pascal() / pressure.get_pascal();
+
+    let m = match temperature {
+        Some(t) => (ratio.powf(1.0 / BAROMETRIC_EXPONENT) - 1.0) * t.get_kelvin() / LAPSE_RATE,
+        None => 44330.0 * (1.0 - (1.0 / ratio).powf(0.190295))
+    };
+
+    Distance::from_meters(m)
+}
+
+/// The reference pressure at which the pressure is read as the altitude. Inverse of
+/// `pressure_to_altitude`.
+pub fn reference_pressure_at_altitude(pressure: Pressure, altitude: Distance, temperature: Option<Temperature>) -> Pressure {
+    let h = altitude.get_meters();
+
+    let p = match temperature {
+        Some(t) => pressure.get_pascal() * (h * LAPSE_RATE / t.get_kelvin() + 1.0).powf(BAROMETRIC_EXPONENT),
+        None => pressure.get_pascal() / (1.0 - h / 44330.0).powf(1.0 / 0.190295)
+    };
+
+    Pressure::from_pascal(p)
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct AltimeterReading {
+    /// The filtered altitude
+    pub altitude: Distance,
+    /// The altitude of this measurement alone
+    pub raw_altitude: Distance,
+    pub pressure: Pressure,
+    pub temperature: Option<Temperature>,
+    pub reference: AltimeterReference
+}
+
+impl Display for AltimeterReading {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let reference = match self.reference {
+            AltimeterReference::Qnh(p) => format!("QNH {:.1} hPa", p.get_hecto_pascal()),
+            AltimeterReference::Qfe(p) => format!("QFE {:.1} hPa", p.get_hecto_pascal())
+        };
+
+        write!(f, "Altitude: {:.2} m ({}, {})", self.altitude.get_meters(), reference, self.pressure)
+    }
+}
+
+/// Keeps the reference and the filter state. The sensors are passed to every read, so
+/// the altimeter can outlive any borrow of the device.
+pub struct Altimeter {
+    reference: AltimeterReference,
+    temperature_compensation: bool,
+    smoothing: f32,
+    filtered: Option<f32>
+}
+
+impl Altimeter {
+    pub fn new(reference: AltimeterReference) -> Self {
+        Altimeter {
+            reference: reference,
+            temperature_compensation: true,
+            smoothing: 1.0,
+            filtered: None
+        }
+    }
+
+    /// Weight of the new altitude in the exponential filter, between 0 and 1. The
+    /// default of 1 disables the filter.
+    pub fn with_smoothing(mut self, smoothing: f32) -> Self {
+        self.smoothing = clamp(smoothing, 0.001, 1.0);
+        self
+    }
+
+    /// Use the device's temperature sensor, if it has one. On by default.
+    pub fn with_temperature_compensation(mut self, enabled: bool) -> Self {
+        self.temperature_compensation = enabled;
+        self
+    }
+
+    pub fn get_reference(&self) -> AltimeterReference {
+        self.reference
+    }
+
+    pub fn set_reference(&mut self, reference: AltimeterReference) {
+        self.reference = reference;
+        self.reset_filter();
+    }
+
+    pub fn reset_filter(&mut self) {
+        self.filtered = None;
+    }
+
+    /// Sets the QNH so that the current reading is the known altitude, for example the
+    /// elevation of the airfield.
+    pub fn calibrate_to_altitude(&mut self, device: &Device, altitude: Distance) -> Result<AltimeterReference, PeripheryError> {
+        let (pressure, temperature) = self.measure(device)?;
+        let reference = AltimeterReference::Qnh(reference_pressure_at_altitude(pressure, altitude, temperature));
+        self.set_reference(reference);
+        Ok(reference)
+    }
+
+    /// Sets the current pressure as the QFE, the heights are relative to this spot.
+    pub fn set_qfe_here(&mut self, device: &Device) -> Result<AltimeterReference, PeripheryError> {
+        let (pressure, _) = self.measure(device)?;
+        let reference = AltimeterReference::Qfe(pressure);
+        self.set_reference(reference);
+        Ok(reference)
+    }
+
+    pub fn read(&mut self, device: &Device) -> Result<AltimeterReading, PeripheryError> {
+        let (pressure, temperature) = self.measure(device)?;
+        Ok(self.update(pressure, temperature))
+    }
+
+    /// Computes and filters the altitude of a measurement, for sensors that are read
+    /// elsewhere or recorded data.
+    pub fn update(&mut self, pressure: Pressure, temperature: Option<Temperature>) -> AltimeterReading {
+        let raw = pressure_to_altitude(pressure, self.reference.get_pressure(), temperature).get_meters();
+
+        let filtered = match self.filtered {
+            Some(f) => f + self.smoothing * (raw - f),
+            None => raw
+        };
+        self.filtered = Some(filtered);
+
+        AltimeterReading {
+            altitude: Distance::from_meters(filtered),
+            raw_altitude: Distance::from_meters(raw),
+            pressure: pressure,
+            temperature: temperature,
+            reference: self.reference
+        }
+    }
+
+    fn measure(&self, device: &Device) -> Result<(Pressure, Option<Temperature>), PeripheryError> {
+        let pressure_sensor = device.get_atmospheric_pressure_sensor().ok_or(PeripheryError::NotImplemented)?;
+        let pressure = pressure_sensor.get_atmospheric_pressure()?.get_pressure();
+
+        let temperature = match (self.temperature_compensation, device.get_ambient_temperature_sensor()) {
+            (true, Some(sensor)) => Some(sensor.get_ambient_temperature()?.get_temperature()),
+            _ => None
+        };
+
+        Ok((pressure, temperature))
+    }
+}
+
+impl Default for Altimeter {
+    fn default() -> Self {
+        Self::new(AltimeterReference::standard())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct Barometer {
+        pascal: Mutex<f32>
+    }
+
+    impl Barometer {
+        fn new(pascal: f32) -> Self {
+            Barometer { pascal: Mutex::new(pascal) }
+        }
+
+        fn set(&self, pascal: f32) {
+            *self.pascal.lock().unwrap() = pascal;
+        }
+    }
+
+    impl Device for Barometer {
+        fn get_atmospheric_pressure_sensor(&self) -> Option<&AtmosphericPressureSensor> {
+            Some(self)
+        }
+
+        fn get_ambient_temperature_sensor(&self) -> Option<&AmbientTemperatureSensor> {
+            Some(self)
+        }
+
+        fn description(&self) -> Cow<str> {
+            "Test barometer".into()
+        }
+
+        fn id(&self) -> Cow<str> {
+            "barometer".into()
+        }
+    }
+
+    impl AtmosphericPressureSensor for Barometer {
+        fn get_atmospheric_pressure(&self) -> Result<AtmosphericPressure, PeripheryError> {
+            Ok(AtmosphericPressure::from_pressure(Pressure::from_pascal(*self.pascal.lock().unwrap())))
+        }
+    }
+
+    impl AmbientTemperatureSensor for Barometer {
+        fn get_ambient_temperature(&self) -> Result<AmbientTemperature, PeripheryError> {
+            Ok(AmbientTemperature::from_temperature(Temperature::from_degrees_celsius(15.0)))
+        }
+    }
+
+    fn assert_close(expected: f32, value: f32, tolerance: f32) {
+        assert!((expected - value).abs() <= tolerance, "expected {}, got {}", expected, value);
+    }
+
+    #[test]
+    fn test_formulas() {
+        let p0 = Pressure::from_pascal(STANDARD_PRESSURE_PA);
+        let isa = Some(Temperature::from_degrees_celsius(15.0));
+
+        // about 1000 m in the standard atmosphere
+        let p = Pressure::from_pascal(89875.0);
+        assert_close(1000.0, pressure_to_altitude(p, p0, None).get_meters(), 2.0);
+        // the hypsometric formula uses the temperature at the sensor
+        assert_close(1000.0, pressure_to_altitude(p, p0, Some(Temperature::from_degrees_celsius(8.5))).get_meters(), 5.0);
+        assert!(pressure_to_altitude(p, p0, Some(Temperature::from_degrees_celsius(30.0))).get_meters() > 1050.0);
+
+        for t in &[None, isa] {
+            let reference = reference_pressure_at_altitude(p, Distance::from_meters(500.0), *t);
+            assert_close(500.0, pressure_to_altitude(p, reference, *t).get_meters(), 0.1);
+        }
+    }
+
+    #[test]
+    fn test_altimeter() {
+        let device = Barometer::new(95000.0);
+
+        let mut altimeter = Altimeter::default().with_temperature_compensation(false);
+        let qnh = altimeter.calibrate_to_altitude(&device, Distance::from_meters(300.0)).unwrap();
+        assert_close(300.0, altimeter.read(&device).unwrap().altitude.get_meters(), 0.1);
+
+        // the same airfield, QFE
+        altimeter.set_qfe_here(&device).unwrap();
+        assert_close(0.0, altimeter.read(&device).unwrap().altitude.get_meters(), 0.01);
+
+        // climbing about 100 m with a filter
+        altimeter.set_reference(qnh);
+        let mut altimeter = altimeter.with_smoothing(0.5);
+        device.set(93900.0);
+        let first = altimeter.read(&device).unwrap();
+        assert_close(395.0, first.altitude.get_meters(), 5.0);
+        device.set(94000.0);
+        let second = altimeter.read(&device).unwrap();
+        assert!(second.altitude.get_meters() > second.raw_altitude.get_meters());
+        assert_close((first.raw_altitude.get_meters() + second.raw_altitude.get_meters()) / 2.0, second.altitude.get_meters(), 0.01);
+    }
+}
@@ -0,0 +1,227 @@
+use periphery_core::prelude::v1::*;
+use periphery_core::*;
+
+use super::*;
+
+use std::io::Read;
+
+const MAGIC: &'static [u8; 4] = b"PDSR";
+const VERSION: u8 = 1;
+
+/// Compact little-endian format. The schema header is the magic `PDSR`, the version,
+/// the device, the stream id, CLI id, description, polling rate, start timestamp and
+/// the channels, each with its type, label and unit. Every sample is the timestamp
+/// followed by the values: `f32`, `i64`, a byte for booleans and length-prefixed UTF-8
+/// strings. Samples that don't match the channels are rejected, as the reader relies
+/// on the schema to split them.
+pub struct BinaryRecorder<W> {
+    writer: W,
+    channels: Vec<DataStreamChannelType>
+}
+
+impl<W: Write> BinaryRecorder<W> {
+    pub fn new(mut writer: W, header: &RecordingHeader) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        write_str(&mut writer, &header.device)?;
+        writer.write_all(&(header.stream.id.0 as u32).to_le_bytes())?;
+        write_str(&mut writer, &header.stream.cli_id)?;
+        write_str(&mut writer, &header.stream.description)?;
+        writer.write_all(&(header.stream.poll_every_ms as u32).to_le_bytes())?;
+        writer.write_all(&header.started_us.to_le_bytes())?;
+
+        writer.write_all(&(header.stream.channels.len() as u16).to_le_bytes())?;
+        for channel in &header.stream.channels {
+            writer.write_all(&[type_to_byte(channel.channel_type)])?;
+            write_str(&mut writer, &channel.label)?;
+            write_str(&mut writer, channel.unit.as_ref().map(|u| u.as_ref()).unwrap_or(""))?;
+        }
+
+        Ok(BinaryRecorder {
+            writer: writer,
+            channels: header.stream.channels.iter().map(|c| c.channel_type).collect()
+        })
+    }
+}
+
+impl<W: Write> DataStreamRecorder for BinaryRecorder<W> {
+    fn record(&mut self, sample: &DataStreamPolled) -> io::Result<()> {
+        if sample.values.len() != self.channels.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "The sample doesn't match the number of channels"));
+        }
+        for (value, &channel_type) in sample.values.iter().zip(self.channels.iter()) {
+            if value.get_type() != channel_type {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "The sample doesn't match the channel types"));
+            }
+            if let DataStreamValue::String(ref v) = *value {
+                check_str_len(v)?;
+            }
+        }
+
+        self.writer.write_all(&sample.timestamp_us.to_le_bytes())?;
+        for value in &sample.values {
+            match *value {
+                DataStreamValue::F32(v) => self.writer.write_all(&v.to_bits().to_le_bytes())?,
+                DataStreamValue::I64(v) => self.writer.write_all(&v.to_le_bytes())?,
+                DataStreamValue::Bool(v) => self.writer.write_all(&[v as u8])?,
+                DataStreamValue::String(ref v) => write_str(&mut self.writer, v)?
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reads the recordings of `BinaryRecorder`.
+pub struct BinaryReader<R> {
+    reader: R,
+    header: RecordingHeader
+}
+
+impl<R: Read> BinaryReader<R> {
+    /// Reads and validates the schema header.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u8(&mut reader)? != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Not a data stream recording"));
+        }
+
+        let device = read_str(&mut reader)?;
+        let id = read_u32(&mut reader)?;
+        let cli_id = read_str(&mut reader)?;
+        let description = read_str(&mut reader)?;
+        let poll_every_ms = read_u32(&mut reader)?;
+        let started_us = read_u64(&mut reader)?;
+
+        let mut channels = vec![];
+        for _ in 0..read_u16(&mut reader)? {
+            let channel_type = type_from_byte(read_u8(&mut reader)?)?;
+            let label = read_str(&mut reader)?;
+            let unit = read_str(&mut reader)?;
+            channels.push(DataStreamChannel {
+                label: label.into(),
+                channel_type: channel_type,
+                unit: if unit.len() > 0 { Some(unit.into()) } else { None }
+            });
+        }
+
+        let stream = DataStream {
+            id: DataStreamId(id as usize),
+            cli_id: cli_id.into(),
+            description: description.into(),
+            poll_every_ms: poll_every_ms as usize,
+            channels: channels
+        };
+
+        Ok(BinaryReader {
+            reader: reader,
+            header: RecordingHeader::new(&device, stream, started_us)
+        })
+    }
+
+    pub fn get_header(&self) -> &RecordingHeader {
+        &self.header
+    }
+
+    /// `None` at the end of the recording.
+    pub fn read_sample(&mut self) -> io::Result<Option<DataStreamPolled>> {
+        let mut timestamp = [0; 8];
+        match self.reader.read_exact(&mut timestamp) {
+            Ok(()) => (),
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e)
+        }
+
+        let mut values = Vec::with_capacity(self.header.stream.channels.len());
+        for channel in &self.header.stream.channels {
+            let value = match channel.channel_type {
+                DataStreamChannelType::F32 => DataStreamValue::F32(f32::from_bits(read_u32(&mut self.reader)?)),
+                DataStreamChannelType::I64 => DataStreamValue::I64(read_u64(&mut self.reader)? as i64),
+                DataStreamChannelType::Bool => DataStreamValue::Bool(read_u8(&mut self.reader)? != 0),
+                DataStreamChannelType::String => DataStreamValue::String(read_str(&mut self.reader)?.into())
+            };
+            values.push(value);
+        }
+
+        Ok(Some(DataStreamPolled::new(u64::from_le_bytes(timestamp), values)))
+    }
+}
+
+impl<R: Read> Iterator for BinaryReader<R> {
+    type Item = io::Result<DataStreamPolled>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_sample() {
+            Ok(Some(sample)) => Some(Ok(sample)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e))
+        }
+    }
+}
+
+fn type_to_byte(channel_type: DataStreamChannelType) -> u8 {
+    match channel_type {
+        DataStreamChannelType::F32 => 0,
+        DataStreamChannelType::I64 => 1,
+        DataStreamChannelType::Bool => 2,
+        DataStreamChannelType::String => 3
+    }
+}
+
+fn type_from_byte(b: u8) -> io::Result<DataStreamChannelType> {
+    match b {
+        0 => Ok(DataStreamChannelType::F32),
+        1 => Ok(DataStreamChannelType::I64),
+        2 => Ok(DataStreamChannelType::Bool),
+        3 => Ok(DataStreamChannelType::String),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "Unknown channel type"))
+    }
+}
+
+fn check_str_len(s: &str) -> io::Result<()> {
+    if s.len() > u16::max_value() as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "The string is longer than 65535 bytes"));
+    }
+    Ok(())
+}
+
+fn write_str<W: Write>(writer: &mut W, s: &str) -> io::Result<()> {
+    check_str_len(s)?;
+    writer.write_all(&(s.len() as u16).to_le_bytes())?;
+    writer.write_all(s.as_bytes())
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut b = [0; 1];
+    reader.read_exact(&mut b)?;
+    Ok(b[0])
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> io::Result<u16> {
+    let mut b = [0; 2];
+    reader.read_exact(&mut b)?;
+    Ok(u16::from_le_bytes(b))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut b = [0; 4];
+    reader.read_exact(&mut b)?;
+    Ok(u32::from_le_bytes(b))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut b = [0; 8];
+    reader.read_exact(&mut b)?;
+    Ok(u64::from_le_bytes(b))
+}
+
+fn read_str<R: Read>(reader: &mut R) -> io::Result<String> {
+    let len = read_u16(reader)? as usize;
+    let mut b = vec![0; len];
+    reader.read_exact(&mut b)?;
+    String::from_utf8(b).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid UTF-8 string"))
+}
@@ -0,0 +1,72 @@
+use periphery_core::prelude::v1::*;
+use periphery_core::*;
+
+use super::*;
+
+/// One row per sample, the first column is the timestamp in microseconds. The header
+/// row contains the channel labels with their units.
+pub struct CsvRecorder<W> {
+    writer: W
+}
+
+impl<W: Write> CsvRecorder<W> {
+    pub fn new(mut writer: W, header: &RecordingHeader) -> io::Result<Self> {
+        let mut row = vec!["timestamp_us".to_string()];
+        for channel in &header.stream.channels {
+            row.push(escape(&channel.to_string()));
+        }
+        writeln!(writer, "{}", row.join(","))?;
+
+        Ok(CsvRecorder {
+            writer: writer
+        })
+    }
+}
+
+impl<W: Write> DataStreamRecorder for CsvRecorder<W> {
+    fn record(&mut self, sample: &DataStreamPolled) -> io::Result<()> {
+        let mut row = vec![sample.timestamp_us.to_string()];
+        for value in &sample.values {
+            row.push(escape(&value.to_string()));
+        }
+        writeln!(self.writer, "{}", row.join(","))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn escape(field: &str) -> String {
+    if field.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace("\"", "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Splits a CSV line into fields, handling the quoted ones.
+pub fn parse_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            },
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                fields.push(field);
+                field = String::new();
+            },
+            c => field.push(c)
+        }
+    }
+    fields.push(field);
+
+    fields
+}
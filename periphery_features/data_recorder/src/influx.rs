@@ -0,0 +1,53 @@
+use periphery_core::prelude::v1::*;
+use periphery_core::*;
+
+use super::*;
+
+/// InfluxDB line protocol. The measurement is the stream's CLI id, the device is a tag
+/// and the channels are the fields. Timestamps are in nanoseconds, the default
+/// precision of the protocol.
+pub struct InfluxLineRecorder<W> {
+    writer: W,
+    series: String,
+    fields: Vec<String>
+}
+
+impl<W: Write> InfluxLineRecorder<W> {
+    pub fn new(writer: W, header: &RecordingHeader) -> io::Result<Self> {
+        Ok(InfluxLineRecorder {
+            writer: writer,
+            series: format!("{},device={}", escape_key(&header.stream.cli_id), escape_key(&header.device)),
+            fields: header.stream.channels.iter().map(|c| escape_key(&c.label)).collect()
+        })
+    }
+}
+
+impl<W: Write> DataStreamRecorder for InfluxLineRecorder<W> {
+    fn record(&mut self, sample: &DataStreamPolled) -> io::Result<()> {
+        let fields: Vec<_> = self.fields.iter().zip(sample.values.iter()).filter_map(|(field, value)| {
+            let value = match *value {
+                DataStreamValue::F32(v) if v.is_finite() => v.to_string(),
+                DataStreamValue::F32(_) => return None,
+                DataStreamValue::I64(v) => format!("{}i", v),
+                DataStreamValue::Bool(v) => v.to_string(),
+                DataStreamValue::String(ref v) => format!("\"{}\"", v.replace("\\", "\\\\").replace("\"", "\\\""))
+            };
+            Some(format!("{}={}", field, value))
+        }).collect();
+
+        // a line without fields is invalid
+        if fields.len() == 0 {
+            return Ok(());
+        }
+
+        writeln!(self.writer, "{} {} {}", self.series, fields.join(","), sample.timestamp_us * 1000)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn escape_key(s: &str) -> String {
+    s.replace(",", "\\,").replace("=", "\\=").replace(" ", "\\ ")
+}
@@ -0,0 +1,56 @@
+use periphery_core::prelude::v1::*;
+use periphery_core::*;
+use periphery_core::utils::json::JsonStr;
+
+use super::*;
+
+/// JSON Lines. The first line describes the stream and its channels, every following
+/// line is a sample, `{"timestamp_us":1500,"values":[0.5,-3,true,"text"]}`.
+pub struct JsonLinesRecorder<W> {
+    writer: W
+}
+
+impl<W: Write> JsonLinesRecorder<W> {
+    pub fn new(mut writer: W, header: &RecordingHeader) -> io::Result<Self> {
+        let channels: Vec<_> = header.stream.channels.iter().map(|c| {
+            format!("{{\"label\":{},\"type\":\"{}\",\"unit\":{}}}",
+                JsonStr(&c.label),
+                channel_type_name(c.channel_type),
+                c.unit.as_ref().map(|u| JsonStr(u).to_string()).unwrap_or("null".into())
+            )
+        }).collect();
+
+        writeln!(writer, "{{\"device\":{},\"stream\":{},\"description\":{},\"poll_every_ms\":{},\"started_us\":{},\"channels\":[{}]}}",
+            JsonStr(&header.device),
+            JsonStr(&header.stream.cli_id),
+            JsonStr(&header.stream.description),
+            header.stream.poll_every_ms,
+            header.started_us,
+            channels.join(",")
+        )?;
+
+        Ok(JsonLinesRecorder {
+            writer: writer
+        })
+    }
+}
+
+impl<W: Write> DataStreamRecorder for JsonLinesRecorder<W> {
+    fn record(&mut self, sample: &DataStreamPolled) -> io::Result<()> {
+        let values: Vec<_> = sample.values.iter().map(|v| {
+            match *v {
+                DataStreamValue::F32(v) if v.is_finite() => v.to_string(),
+                DataStreamValue::F32(_) => "null".to_string(),
+                DataStreamValue::I64(v) => v.to_string(),
+                DataStreamValue::Bool(v) => v.to_string(),
+                DataStreamValue::String(ref v) => JsonStr(v).to_string()
+            }
+        }).collect();
+
+        writeln!(self.writer, "{{\"timestamp_us\":{},\"values\":[{}]}}", sample.timestamp_us, values.join(","))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,316 @@
+//! Recorders that write polled data streams to files. Every format starts with the
+//! description of the stream's channels, so the recordings are self-describing.
+//!
+//! * `csv`, one row per sample, channels with units in the header
+//! * `jsonl`, JSON Lines with a header object
+//! * `bin`, a compact binary format with a schema header, see `BinaryReader`
+//! * `influx`, InfluxDB line protocol
+//!
+//! The CSV and binary recordings can be played back as virtual devices, see
+//! `PlaybackDevice`.
+
+extern crate periphery_core;
+
+use periphery_core::prelude::v1::*;
+use periphery_core::*;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+mod csv;
+mod jsonl;
+mod binary;
+mod influx;
+mod playback;
+
+pub use self::csv::*;
+pub use self::jsonl::*;
+pub use self::binary::*;
+pub use self::influx::*;
+pub use self::playback::*;
+
+/// Describes the recorded stream, written at the start of the recording.
+#[derive(Debug, Clone)]
+pub struct RecordingHeader {
+    /// The unique id of the device
+    pub device: String,
+    pub stream: DataStream,
+    /// Timestamp of the start of the recording, in microseconds
+    pub started_us: u64
+}
+
+impl RecordingHeader {
+    pub fn new(device: &str, stream: DataStream, started_us: u64) -> Self {
+        RecordingHeader {
+            device: device.to_string(),
+            stream: stream,
+            started_us: started_us
+        }
+    }
+}
+
+pub trait DataStreamRecorder {
+    fn record(&mut self, sample: &DataStreamPolled) -> io::Result<()>;
+    fn flush(&mut self) -> io::Result<()>;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RecordingFormat {
+    Csv,
+    JsonLines,
+    Binary,
+    InfluxLineProtocol
+}
+
+impl RecordingFormat {
+    pub fn all() -> &'static [RecordingFormat] {
+        &[RecordingFormat::Csv, RecordingFormat::JsonLines, RecordingFormat::Binary, RecordingFormat::InfluxLineProtocol]
+    }
+
+    pub fn get_extension(&self) -> &'static str {
+        match *self {
+            RecordingFormat::Csv => "csv",
+            RecordingFormat::JsonLines => "jsonl",
+            RecordingFormat::Binary => "bin",
+            RecordingFormat::InfluxLineProtocol => "influx"
+        }
+    }
+
+    /// Writes the header and returns the recorder for the samples.
+    pub fn create<W: Write + Send + 'static>(&self, writer: W, header: &RecordingHeader) -> io::Result<Box<DataStreamRecorder + Send>> {
+        Ok(match *self {
+            RecordingFormat::Csv => Box::new(CsvRecorder::new(writer, header)?),
+            RecordingFormat::JsonLines => Box::new(JsonLinesRecorder::new(writer, header)?),
+            RecordingFormat::Binary => Box::new(BinaryRecorder::new(writer, header)?),
+            RecordingFormat::InfluxLineProtocol => Box::new(InfluxLineRecorder::new(writer, header)?)
+        })
+    }
+
+    pub fn create_file<P: AsRef<Path>>(&self, path: P, header: &RecordingHeader) -> io::Result<Box<DataStreamRecorder + Send>> {
+        let file = File::create(path)?;
+        self.create(BufWriter::new(file), header)
+    }
+}
+
+/// Format names are the file extensions.
+impl FromStr for RecordingFormat {
+    type Err = PeripheryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::all().iter().find(|f| f.get_extension() == s).cloned().ok_or(PeripheryError::ParseError)
+    }
+}
+
+/// The type names used in the headers of the text formats.
+pub fn channel_type_name(channel_type: DataStreamChannelType) -> &'static str {
+    match channel_type {
+        DataStreamChannelType::F32 => "f32",
+        DataStreamChannelType::I64 => "i64",
+        DataStreamChannelType::Bool => "bool",
+        DataStreamChannelType::String => "string"
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub fn test_header() -> RecordingHeader {
+        let stream = DataStream {
+            id: DataStreamId(1004),
+            cli_id: "acceleration_3".into(),
+            description: "Acceleration".into(),
+            poll_every_ms: 10,
+            channels: vec![
+                DataStreamChannel::new("x", DataStreamChannelType::F32).with_unit("g"),
+                DataStreamChannel::new("count", DataStreamChannelType::I64),
+                DataStreamChannel::new("ready", DataStreamChannelType::Bool),
+                DataStreamChannel::new("note", DataStreamChannelType::String)
+            ]
+        };
+
+        RecordingHeader::new("i2c-1/0x68/mpu", stream, 1000)
+    }
+
+    pub fn test_sample() -> DataStreamPolled {
+        DataStreamPolled::new(1500, vec![
+            DataStreamValue::F32(0.5),
+            DataStreamValue::I64(-3),
+            DataStreamValue::Bool(true),
+            DataStreamValue::String("a \"b\", c".into())
+        ])
+    }
+
+    /// Records the test sample into memory.
+    pub fn record(format: RecordingFormat) -> String {
+        let buffer = SharedBuffer::default();
+        {
+            let mut recorder = format.create(buffer.clone(), &test_header()).unwrap();
+            recorder.record(&test_sample()).unwrap();
+            recorder.flush().unwrap();
+        }
+        let data = buffer.0.lock().unwrap().clone();
+        String::from_utf8_lossy(&data).into_owned()
+    }
+
+    #[derive(Clone, Default)]
+    pub struct SharedBuffer(pub Arc<::std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_recording_format() {
+        assert_eq!(RecordingFormat::JsonLines, "jsonl".parse().unwrap());
+        assert!("xml".parse::<RecordingFormat>().is_err());
+    }
+
+    #[test]
+    fn test_csv() {
+        let csv = record(RecordingFormat::Csv);
+        assert_eq!("timestamp_us,x [g],count,ready,note\n1500,0.5,-3,true,\"a \"\"b\"\", c\"\n", csv);
+    }
+
+    #[test]
+    fn test_jsonl() {
+        let jsonl = record(RecordingFormat::JsonLines);
+        let lines: Vec<_> = jsonl.lines().collect();
+        assert_eq!(2, lines.len());
+        assert_eq!(r#"{"device":"i2c-1/0x68/mpu","stream":"acceleration_3","description":"Acceleration","poll_every_ms":10,"started_us":1000,"channels":[{"label":"x","type":"f32","unit":"g"},{"label":"count","type":"i64","unit":null},{"label":"ready","type":"bool","unit":null},{"label":"note","type":"string","unit":null}]}"#, lines[0]);
+        assert_eq!(r#"{"timestamp_us":1500,"values":[0.5,-3,true,"a \"b\", c"]}"#, lines[1]);
+    }
+
+    #[test]
+    fn test_influx() {
+        let influx = record(RecordingFormat::InfluxLineProtocol);
+        assert_eq!("acceleration_3,device=i2c-1/0x68/mpu x=0.5,count=-3i,ready=true,note=\"a \\\"b\\\", c\" 1500000\n", influx);
+    }
+
+    #[test]
+    fn test_binary() {
+        let buffer = SharedBuffer::default();
+        {
+            let mut recorder = RecordingFormat::Binary.create(buffer.clone(), &test_header()).unwrap();
+            recorder.record(&test_sample()).unwrap();
+            recorder.record(&test_sample()).unwrap();
+            recorder.flush().unwrap();
+        }
+        let data = buffer.0.lock().unwrap().clone();
+
+        let mut reader = BinaryReader::new(&data[..]).unwrap();
+        let header = reader.get_header().clone();
+        assert_eq!("i2c-1/0x68/mpu", header.device);
+        assert_eq!(1000, header.started_us);
+        assert_eq!(test_header().stream.channels, header.stream.channels);
+        assert_eq!(Some(test_sample()), reader.read_sample().unwrap());
+        assert_eq!(Some(test_sample()), reader.read_sample().unwrap());
+        assert_eq!(None, reader.read_sample().unwrap());
+    }
+
+    #[test]
+    fn test_binary_rejects_mismatched_samples() {
+        let mut recorder = RecordingFormat::Binary.create(SharedBuffer::default(), &test_header()).unwrap();
+
+        let mut sample = test_sample();
+        sample.values.pop();
+        assert_eq!(io::ErrorKind::InvalidInput, recorder.record(&sample).unwrap_err().kind());
+
+        let mut sample = test_sample();
+        sample.values[1] = DataStreamValue::F32(-3.0);
+        assert_eq!(io::ErrorKind::InvalidInput, recorder.record(&sample).unwrap_err().kind());
+
+        let mut sample = test_sample();
+        sample.values[3] = DataStreamValue::String("é".repeat(40000).into());
+        assert_eq!(io::ErrorKind::InvalidInput, recorder.record(&sample).unwrap_err().kind());
+
+        recorder.record(&test_sample()).unwrap();
+    }
+
+    #[test]
+    fn test_playback_csv() {
+        let csv = "num,ms,up,down,left,right\n1,17,4,1,2,5\n2,27,4,2,0,3\n";
+        let recording = Recording::read_csv(csv.as_bytes(), "i2c-0/0x39/apds9960", "gestures_raw_fixed_gain").unwrap();
+        assert_eq!(2, recording.samples.len());
+        assert_eq!(10000, recording.get_duration_us());
+        assert_eq!(DataStreamChannelType::I64, recording.header.stream.channels[0].channel_type);
+        assert_eq!(vec![DataStreamValue::I64(4), DataStreamValue::I64(2), DataStreamValue::I64(0), DataStreamValue::I64(3)], recording.samples[1].values);
+
+        // the recorded CSV reads back with the units
+        let recorded = record(RecordingFormat::Csv);
+        let recording = Recording::read_csv(recorded.as_bytes(), "i2c-1/0x68/mpu", "acceleration_3").unwrap();
+        assert_eq!(Some("g".into()), recording.header.stream.channels[0].unit);
+        assert_eq!(test_sample(), recording.samples[0]);
+    }
+
+    #[test]
+    fn test_playback_out_of_order() {
+        let csv = "timestamp_us,x\n2000,1\n1000,2\n";
+        let err = Recording::read_csv(csv.as_bytes(), "i2c-1/0x68/mpu", "x").unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+
+        let buffer = SharedBuffer::default();
+        {
+            let mut recorder = RecordingFormat::Binary.create(buffer.clone(), &test_header()).unwrap();
+            let mut sample = test_sample();
+            recorder.record(&sample).unwrap();
+            sample.timestamp_us = 1000;
+            recorder.record(&sample).unwrap();
+        }
+        let data = buffer.0.lock().unwrap().clone();
+        assert_eq!(io::ErrorKind::InvalidData, Recording::read_binary(&data[..]).unwrap_err().kind());
+
+        // recordings built in code aren't checked, but still play back
+        let mut late = test_sample();
+        late.timestamp_us = 1000;
+        let recording = Recording { header: test_header(), samples: vec![test_sample(), late] };
+        assert_eq!(0, recording.get_duration_us());
+        let device = PlaybackDevice::new(recording, PlaybackSpeed::RealTime);
+        let mut poller = device.get_poller(DataStreamId(1004)).unwrap();
+        assert_eq!(2, poller.poll(&device, &TestClock).unwrap().len());
+    }
+
+    #[test]
+    fn test_playback_device() {
+        let mut header = test_header();
+        header.stream = SensorStreamKind::Acceleration3.get_info();
+        let buffer = SharedBuffer::default();
+        {
+            let mut recorder = RecordingFormat::Binary.create(buffer.clone(), &header).unwrap();
+            recorder.record(&DataStreamPolled::from_f32(1000, &[0.0, 0.0, 1.0])).unwrap();
+            recorder.record(&DataStreamPolled::from_f32(2000, &[0.5, 0.0, 1.0])).unwrap();
+        }
+        let data = buffer.0.lock().unwrap().clone();
+        let recording = Recording::read_binary(&data[..]).unwrap();
+
+        let device = PlaybackDevice::new(recording.clone(), PlaybackSpeed::AsFastAsPossible);
+        assert!(device.get_ambient_light_sensor().is_none());
+        let sensor = device.get_acceleration_3_sensor().unwrap();
+        assert_eq!(1.0, sensor.get_acceleration_3().unwrap().get_z().get_g_force());
+        assert_eq!(0.5, sensor.get_acceleration_3().unwrap().get_x().get_g_force());
+        assert!(sensor.get_acceleration_3().is_err());
+
+        let device = PlaybackDevice::new(recording, PlaybackSpeed::AsFastAsPossible).with_looping();
+        let streams = device.get_data_streams().unwrap();
+        let mut poller = streams.get_poller(SensorStreamKind::Acceleration3.get_id()).unwrap();
+        let clock = TestClock;
+        let timestamps: Vec<_> = (0..3).map(|_| poller.poll(&device, &clock).unwrap()[0].timestamp_us).collect();
+        assert_eq!(vec![1000, 2000, 1000], timestamps);
+    }
+
+    struct TestClock;
+    impl SystemApiClock for TestClock {
+        fn get_timestamp_us(&self) -> u64 {
+            0
+        }
+    }
+}
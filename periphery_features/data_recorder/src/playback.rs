@@ -0,0 +1,438 @@
+use periphery_core::prelude::v1::*;
+use periphery_core::*;
+
+use super::*;
+
+use std::io::{Read, BufRead, BufReader};
+use std::fs::File;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// A recorded data stream, loaded into memory. The loaded samples are in the order of
+/// their timestamps.
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub header: RecordingHeader,
+    pub samples: Vec<DataStreamPolled>
+}
+
+impl Recording {
+    pub fn read_binary<R: Read>(reader: R) -> io::Result<Recording> {
+        let reader = BinaryReader::new(reader)?;
+        let header = reader.get_header().clone();
+        let samples = reader.collect::<io::Result<Vec<_>>>()?;
+        check_timestamps(&samples)?;
+
+        Ok(Recording {
+            header: header,
+            samples: samples
+        })
+    }
+
+    /// CSV recordings don't describe the device and the stream, so they have to be
+    /// provided. The channel types are guessed from the values. Older recordings with
+    /// the `num,ms` columns instead of the timestamp are also supported.
+    pub fn read_csv<R: BufRead>(reader: R, device: &str, stream: &str) -> io::Result<Recording> {
+        let mut lines = reader.lines();
+        let header = match lines.next() {
+            Some(line) => parse_csv_line(&line?),
+            None => return Err(invalid_data("Empty recording"))
+        };
+
+        let (skip, timestamp_in_ms) = match (header.get(0).map(|s| s.as_str()), header.get(1).map(|s| s.as_str())) {
+            (Some("timestamp_us"), _) => (1, false),
+            (Some("num"), Some("ms")) => (2, true),
+            _ => return Err(invalid_data("Unknown recording header"))
+        };
+
+        let mut timestamps = vec![];
+        let mut rows = vec![];
+        for line in lines {
+            let line = line?;
+            if line.trim().len() == 0 {
+                continue;
+            }
+
+            let mut fields = parse_csv_line(&line);
+            if fields.len() != header.len() {
+                return Err(invalid_data("Wrong number of fields"));
+            }
+
+            let timestamp: u64 = fields[skip - 1].parse().map_err(|_| invalid_data("Invalid timestamp"))?;
+            timestamps.push(if timestamp_in_ms { timestamp * 1000 } else { timestamp });
+            rows.push(fields.split_off(skip));
+        }
+
+        let channels: Vec<_> = header[skip..].iter().enumerate().map(|(i, h)| {
+            let (label, unit) = parse_channel_header(h);
+            DataStreamChannel {
+                label: label.into(),
+                channel_type: guess_channel_type(rows.iter().map(|r| r[i].as_str())),
+                unit: unit.map(|u| u.into())
+            }
+        }).collect();
+
+        let samples: Vec<_> = timestamps.into_iter().zip(rows.into_iter()).map(|(timestamp_us, row)| {
+            let values = row.into_iter().zip(channels.iter()).map(|(v, c)| parse_value(v, c.channel_type)).collect();
+            DataStreamPolled::new(timestamp_us, values)
+        }).collect();
+        check_timestamps(&samples)?;
+
+        let poll_every_ms = match (samples.first(), samples.last()) {
+            (Some(first), Some(last)) if samples.len() > 1 => {
+                max(1, (last.timestamp_us.saturating_sub(first.timestamp_us) / 1000) as usize / (samples.len() - 1))
+            },
+            _ => 1
+        };
+
+        let stream = DataStream {
+            id: DataStreamId(0),
+            cli_id: stream.to_string().into(),
+            description: stream.to_string().into(),
+            poll_every_ms: poll_every_ms,
+            channels: channels
+        };
+        let started_us = samples.first().map(|s| s.timestamp_us).unwrap_or(0);
+
+        Ok(Recording {
+            header: RecordingHeader::new(device, stream, started_us),
+            samples: samples
+        })
+    }
+
+    pub fn open_binary<P: AsRef<Path>>(path: P) -> io::Result<Recording> {
+        Self::read_binary(BufReader::new(File::open(path)?))
+    }
+
+    pub fn open_csv<P: AsRef<Path>>(path: P, device: &str, stream: &str) -> io::Result<Recording> {
+        Self::read_csv(BufReader::new(File::open(path)?), device, stream)
+    }
+
+    pub fn get_duration_us(&self) -> u64 {
+        match (self.samples.first(), self.samples.last()) {
+            (Some(first), Some(last)) => last.timestamp_us.saturating_sub(first.timestamp_us),
+            _ => 0
+        }
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn check_timestamps(samples: &[DataStreamPolled]) -> io::Result<()> {
+    if samples.windows(2).any(|w| w[1].timestamp_us < w[0].timestamp_us) {
+        return Err(invalid_data("The timestamps are out of order"));
+    }
+    Ok(())
+}
+
+/// `x [g]` is the label `x` with the unit `g`.
+fn parse_channel_header(h: &str) -> (String, Option<String>) {
+    let h = h.trim();
+    if h.ends_with(']') {
+        if let Some(i) = h.rfind(" [") {
+            return (h[..i].to_string(), Some(h[i+2..h.len()-1].to_string()));
+        }
+    }
+    (h.to_string(), None)
+}
+
+fn guess_channel_type<'a, I: Iterator<Item=&'a str> + Clone>(values: I) -> DataStreamChannelType {
+    if values.clone().all(|v| v.parse::<i64>().is_ok()) {
+        DataStreamChannelType::I64
+    } else if values.clone().all(|v| v.parse::<f32>().is_ok()) {
+        DataStreamChannelType::F32
+    } else if values.clone().all(|v| v == "true" || v == "false") {
+        DataStreamChannelType::Bool
+    } else {
+        DataStreamChannelType::String
+    }
+}
+
+fn parse_value(v: String, channel_type: DataStreamChannelType) -> DataStreamValue {
+    match channel_type {
+        DataStreamChannelType::I64 => DataStreamValue::I64(v.parse().unwrap_or(0)),
+        DataStreamChannelType::F32 => DataStreamValue::F32(v.parse().unwrap_or(0.0)),
+        DataStreamChannelType::Bool => DataStreamValue::Bool(v == "true"),
+        DataStreamChannelType::String => DataStreamValue::String(v.into())
+    }
+}
+
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PlaybackSpeed {
+    /// The samples become available at their recorded times, relative to the first read
+    RealTime,
+    /// Every read returns the next sample
+    AsFastAsPossible
+}
+
+#[derive(Clone)]
+struct PlaybackPosition {
+    index: usize,
+    started: Option<Instant>
+}
+
+impl PlaybackPosition {
+    fn new() -> Self {
+        PlaybackPosition {
+            index: 0,
+            started: None
+        }
+    }
+
+    /// The samples that are due, advances the position.
+    fn advance(&mut self, recording: &Recording, speed: PlaybackSpeed, looped: bool) -> Vec<DataStreamPolled> {
+        if looped && self.index >= recording.samples.len() {
+            self.index = 0;
+            self.started = None;
+        }
+
+        let first = match recording.samples.first() {
+            Some(s) => s.timestamp_us,
+            None => return vec![]
+        };
+
+        match speed {
+            PlaybackSpeed::RealTime => {
+                let started = *self.started.get_or_insert_with(Instant::now);
+                let elapsed = started.elapsed();
+                let elapsed_us = elapsed.as_secs() * 1000000 + (elapsed.subsec_nanos() / 1000) as u64;
+
+                let mut due = vec![];
+                while let Some(sample) = recording.samples.get(self.index) {
+                    if sample.timestamp_us.saturating_sub(first) > elapsed_us {
+                        break;
+                    }
+                    due.push(sample.clone());
+                    self.index += 1;
+                }
+                due
+            },
+            PlaybackSpeed::AsFastAsPossible => {
+                match recording.samples.get(self.index) {
+                    Some(sample) => {
+                        self.index += 1;
+                        vec![sample.clone()]
+                    },
+                    None => vec![]
+                }
+            }
+        }
+    }
+
+    /// The latest due sample, for the sensor reads.
+    fn current(&mut self, recording: &Recording, speed: PlaybackSpeed, looped: bool) -> Option<DataStreamPolled> {
+        let mut due = self.advance(recording, speed, looped);
+        match due.pop() {
+            Some(sample) => Some(sample),
+            None if speed == PlaybackSpeed::RealTime && self.index > 0 && self.index <= recording.samples.len() => {
+                Some(recording.samples[self.index - 1].clone())
+            },
+            None => None
+        }
+    }
+}
+
+/// A virtual device that plays back a recorded data stream. Recordings of the generic
+/// sensor streams are exposed as the matching sensors, every recording is also
+/// available as a data stream. Higher level features can then run on captured data.
+/// The samples keep their recorded timestamps.
+pub struct PlaybackDevice {
+    recording: Arc<Recording>,
+    sensor: Option<SensorStreamKind>,
+    speed: PlaybackSpeed,
+    looped: bool,
+    position: Mutex<PlaybackPosition>
+}
+
+impl PlaybackDevice {
+    pub fn new(recording: Recording, speed: PlaybackSpeed) -> Self {
+        let sensor = SensorStreamKind::all().iter().find(|k| k.get_info().cli_id == recording.header.stream.cli_id).cloned();
+
+        PlaybackDevice {
+            recording: Arc::new(recording),
+            sensor: sensor,
+            speed: speed,
+            looped: false,
+            position: Mutex::new(PlaybackPosition::new())
+        }
+    }
+
+    /// Start from the beginning after the last sample.
+    pub fn with_looping(mut self) -> Self {
+        self.looped = true;
+        self
+    }
+
+    pub fn get_recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn rewind(&self) {
+        if let Ok(mut position) = self.position.lock() {
+            *position = PlaybackPosition::new();
+        }
+    }
+
+    fn sensor(&self, kind: SensorStreamKind) -> bool {
+        self.sensor == Some(kind)
+    }
+
+    fn read_values(&self) -> Result<Vec<f32>, PeripheryError> {
+        let mut position = self.position.lock().map_err(|_| PeripheryError::LockingError)?;
+        let sample = position.current(&self.recording, self.speed, self.looped).ok_or(PeripheryError::DataNotAvailable)?;
+
+        sample.values.iter().map(|v| v.as_f32().ok_or(PeripheryError::UnsupportedFieldValue)).collect()
+    }
+
+    fn read_value(&self) -> Result<f32, PeripheryError> {
+        self.read_values()?.get(0).cloned().ok_or(PeripheryError::DataNotAvailable)
+    }
+
+    fn read_value_3(&self) -> Result<(f32, f32, f32), PeripheryError> {
+        let v = self.read_values()?;
+        if v.len() < 3 {
+            return Err(PeripheryError::DataNotAvailable);
+        }
+        Ok((v[0], v[1], v[2]))
+    }
+}
+
+impl Device for PlaybackDevice {
+    fn get_ambient_light_sensor(&self) -> Option<&AmbientLightSensor> {
+        if self.sensor(SensorStreamKind::AmbientLight) { Some(self) } else { None }
+    }
+    fn get_ambient_temperature_sensor(&self) -> Option<&AmbientTemperatureSensor> {
+        if self.sensor(SensorStreamKind::AmbientTemperature) { Some(self) } else { None }
+    }
+    fn get_atmospheric_pressure_sensor(&self) -> Option<&AtmosphericPressureSensor> {
+        if self.sensor(SensorStreamKind::AtmosphericPressure) { Some(self) } else { None }
+    }
+    fn get_atmospheric_humidity_sensor(&self) -> Option<&AtmosphericHumiditySensor> {
+        if self.sensor(SensorStreamKind::AtmosphericHumidity) { Some(self) } else { None }
+    }
+    fn get_acceleration_3_sensor(&self) -> Option<&Acceleration3Sensor> {
+        if self.sensor(SensorStreamKind::Acceleration3) { Some(self) } else { None }
+    }
+    fn get_magnetic_field_3_sensor(&self) -> Option<&MagneticField3Sensor> {
+        if self.sensor(SensorStreamKind::MagneticField3) { Some(self) } else { None }
+    }
+    fn get_angular_speed_3_sensor(&self) -> Option<&AngularSpeed3Sensor> {
+        if self.sensor(SensorStreamKind::AngularSpeed3) { Some(self) } else { None }
+    }
+
+    fn get_data_streams(&self) -> Option<&DataStreams> {
+        Some(self)
+    }
+
+    fn description(&self) -> Cow<str> {
+        format!("Playback of {} {}", self.recording.header.device, self.recording.header.stream.cli_id).into()
+    }
+
+    fn id(&self) -> Cow<str> {
+        "playback".into()
+    }
+
+    fn info(&self) -> DeviceInfo {
+        DeviceInfo {
+            part_number: Some("playback".into()),
+            variant: Some(self.recording.header.device.clone().into()),
+            driver_version: Some(env!("CARGO_PKG_VERSION").into()),
+            .. Default::default()
+        }
+    }
+}
+
+impl AmbientLightSensor for PlaybackDevice {
+    fn get_ambient_light(&self) -> Result<Illuminance, PeripheryError> {
+        Ok(Illuminance::from_lux(self.read_value()?))
+    }
+}
+
+impl AmbientTemperatureSensor for PlaybackDevice {
+    fn get_ambient_temperature(&self) -> Result<AmbientTemperature, PeripheryError> {
+        Ok(AmbientTemperature::from_temperature(Temperature::from_degrees_celsius(self.read_value()?)))
+    }
+}
+
+impl AtmosphericPressureSensor for PlaybackDevice {
+    fn get_atmospheric_pressure(&self) -> Result<AtmosphericPressure, PeripheryError> {
+        Ok(AtmosphericPressure::from_pressure(Pressure::from_pascal(self.read_value()?)))
+    }
+}
+
+impl AtmosphericHumiditySensor for PlaybackDevice {
+    fn get_relative_atmospheric_humidity(&self) -> Result<RelativeHumidity, PeripheryError> {
+        Ok(RelativeHumidity::from_percentage(Percentage::from_percentage(self.read_value()?)))
+    }
+}
+
+impl Acceleration3Sensor for PlaybackDevice {
+    fn get_acceleration_3(&self) -> Result<Acceleration3, PeripheryError> {
+        let (x, y, z) = self.read_value_3()?;
+        Ok(Acceleration3::new(GForce::from_g_force(x), GForce::from_g_force(y), GForce::from_g_force(z)))
+    }
+}
+
+impl MagneticField3Sensor for PlaybackDevice {
+    fn get_magnetic_field_3(&self) -> Result<MagneticField3, PeripheryError> {
+        let (x, y, z) = self.read_value_3()?;
+        Ok(MagneticField3 {
+            x: MagneticFieldStrength::from_gauss(x),
+            y: MagneticFieldStrength::from_gauss(y),
+            z: MagneticFieldStrength::from_gauss(z)
+        })
+    }
+}
+
+impl AngularSpeed3Sensor for PlaybackDevice {
+    fn get_angular_speed_3(&self) -> Result<AngularSpeed3, PeripheryError> {
+        let (x, y, z) = self.read_value_3()?;
+        Ok(AngularSpeed3 {
+            x: AngularSpeed::from_degrees_per_second(x),
+            y: AngularSpeed::from_degrees_per_second(y),
+            z: AngularSpeed::from_degrees_per_second(z)
+        })
+    }
+}
+
+impl DataStreams for PlaybackDevice {
+    fn get_stream_infos(&self) -> Vec<DataStream> {
+        vec![self.recording.header.stream.clone()]
+    }
+
+    /// Every poller plays back the recording from the start, independently of the
+    /// sensor reads.
+    fn get_poller(&self, stream: DataStreamId) -> Result<Box<DataStreamPoller + Send + Sync>, PeripheryError> {
+        if stream != self.recording.header.stream.id {
+            return Err(PeripheryError::NotImplemented);
+        }
+
+        Ok(Box::new(PlaybackPoller {
+            recording: self.recording.clone(),
+            speed: self.speed,
+            looped: self.looped,
+            position: PlaybackPosition::new()
+        }))
+    }
+}
+
+pub struct PlaybackPoller {
+    recording: Arc<Recording>,
+    speed: PlaybackSpeed,
+    looped: bool,
+    position: PlaybackPosition
+}
+
+impl DataStreamPoller for PlaybackPoller {
+    fn get_info(&self) -> DataStream {
+        self.recording.header.stream.clone()
+    }
+
+    fn poll(&mut self, _device: &Device, _clock: &SystemApiClock) -> Result<Vec<DataStreamPolled>, PeripheryError> {
+        Ok(self.position.advance(&self.recording, self.speed, self.looped))
+    }
+}
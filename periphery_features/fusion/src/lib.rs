@@ -0,0 +1,368 @@
+//! Attitude and heading reference system, fuses the gyroscope with the accelerometer
+//! and optionally the magnetometer into the orientation of the device. Without the
+//! magnetometer the heading is integrated from the gyroscope alone and drifts.
+//!
+//! The filter is fed with `Ahrs::update`, so it runs just as well on recorded or
+//! synthetic data, or with `Ahrs::read` from the sensors of a device. `AttitudePoller`
+//! exposes it as a data stream, timed by the samples of the gyroscope stream.
+
+extern crate periphery_core;
+
+use periphery_core::prelude::v1::*;
+
+mod madgwick;
+mod mahony;
+mod stream;
+
+pub use self::stream::*;
+
+use self::madgwick::madgwick_update;
+use self::mahony::mahony_correct;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FusionFilter {
+    /// Gradient descent, `beta` is the weight of the accelerometer and magnetometer
+    Madgwick { beta: f32 },
+    /// Complementary filter, the proportional and integral gains of the correction
+    Mahony { kp: f32, ki: f32 }
+}
+
+impl FusionFilter {
+    pub fn madgwick() -> Self {
+        FusionFilter::Madgwick { beta: 0.1 }
+    }
+
+    pub fn mahony() -> Self {
+        FusionFilter::Mahony { kp: 0.5, ki: 0.0 }
+    }
+}
+
+impl Default for FusionFilter {
+    fn default() -> Self {
+        Self::madgwick()
+    }
+}
+
+/// Aerospace sequence, yaw around Z, then pitch around Y and roll around X. [degrees]
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct EulerAngles {
+    pub roll: f32,
+    pub pitch: f32,
+    pub yaw: f32
+}
+
+impl EulerAngles {
+    pub fn from_quaternion(q: &Quaternion) -> EulerAngles {
+        let (w, x, y, z) = (q.w, q.x, q.y, q.z);
+
+        let roll = (2.0 * (w * x + y * z)).atan2(1.0 - 2.0 * (x * x + y * y));
+        let pitch = clamp(2.0 * (w * y - z * x), -1.0, 1.0).asin();
+        let yaw = (2.0 * (w * z + x * y)).atan2(1.0 - 2.0 * (y * y + z * z));
+
+        EulerAngles {
+            roll: roll.to_degrees(),
+            pitch: pitch.to_degrees(),
+            yaw: yaw.to_degrees()
+        }
+    }
+}
+
+impl Display for EulerAngles {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Roll: {:.2} deg, pitch: {:.2} deg, yaw: {:.2} deg", self.roll, self.pitch, self.yaw)
+    }
+}
+
+pub struct Ahrs {
+    filter: FusionFilter,
+    use_magnetometer: bool,
+    quaternion: Quaternion,
+    /// Integral of the error, for Mahony
+    integral: [f32; 3]
+}
+
+impl Ahrs {
+    pub fn new(filter: FusionFilter) -> Self {
+        Ahrs {
+            filter: filter,
+            use_magnetometer: true,
+            quaternion: Quaternion::identity(),
+            integral: [0.0; 3]
+        }
+    }
+
+    /// Use the magnetometer of the device, if it has one. On by default.
+    pub fn with_magnetometer(mut self, enabled: bool) -> Self {
+        self.use_magnetometer = enabled;
+        self
+    }
+
+    pub fn get_filter(&self) -> FusionFilter {
+        self.filter
+    }
+
+    /// Changes the filter or its gains, keeping the current orientation.
+    pub fn set_filter(&mut self, filter: FusionFilter) {
+        self.filter = filter;
+        self.integral = [0.0; 3];
+    }
+
+    /// Rotation from the earth frame to the sensor frame.
+    pub fn get_quaternion(&self) -> Quaternion {
+        self.quaternion
+    }
+
+    pub fn get_euler_angles(&self) -> EulerAngles {
+        EulerAngles::from_quaternion(&self.quaternion)
+    }
+
+    pub fn reset(&mut self) {
+        self.quaternion = Quaternion::identity();
+        self.integral = [0.0; 3];
+    }
+
+    /// One step of the filter, `dt` is the time since the previous measurements.
+    pub fn update(&mut self, angular_speed: AngularSpeed3, acceleration: Acceleration3, magnetic_field: Option<MagneticField3>, dt: TimeSpan) -> Quaternion {
+        let dt = dt.get_seconds();
+        let g = [
+            angular_speed.get_x().get_radians_per_second(),
+            angular_speed.get_y().get_radians_per_second(),
+            angular_speed.get_z().get_radians_per_second()
+        ];
+        let a = acceleration.to_array();
+        let m = magnetic_field.map(|m| m.to_array());
+
+        let q_dot = match self.filter {
+            FusionFilter::Madgwick { beta } => madgwick_update(&self.quaternion, beta, g, a, m),
+            FusionFilter::Mahony { kp, ki } => {
+                let g = mahony_correct(&self.quaternion, kp, ki, &mut self.integral, dt, g, a, m);
+                let q_dot = self.quaternion * Quaternion::new(0.0, g[0], g[1], g[2]);
+                Quaternion::new(q_dot.w * 0.5, q_dot.x * 0.5, q_dot.y * 0.5, q_dot.z * 0.5)
+            }
+        };
+
+        let q = self.quaternion;
+        self.quaternion = Quaternion::new(q.w + q_dot.w * dt, q.x + q_dot.x * dt, q.y + q_dot.y * dt, q.z + q_dot.z * dt).normalize();
+        self.quaternion
+    }
+
+    /// Reads the sensors of the device and updates the filter.
+    pub fn read(&mut self, device: &Device, dt: TimeSpan) -> Result<Quaternion, PeripheryError> {
+        let gyro = device.get_angular_speed_3_sensor().ok_or(PeripheryError::NotImplemented)?;
+        let angular_speed = gyro.get_angular_speed_3()?;
+
+        self.read_with_angular_speed(device, angular_speed, dt)
+    }
+
+    /// Updates the filter with a gyroscope sample that was already read, the other
+    /// sensors are read from the device.
+    pub fn read_with_angular_speed(&mut self, device: &Device, angular_speed: AngularSpeed3, dt: TimeSpan) -> Result<Quaternion, PeripheryError> {
+        let acc = device.get_acceleration_3_sensor().ok_or(PeripheryError::NotImplemented)?;
+        let acceleration = acc.get_acceleration_3()?;
+        let magnetic_field = match (self.use_magnetometer, device.get_magnetic_field_3_sensor()) {
+            (true, Some(mag)) => Some(mag.get_magnetic_field_3()?),
+            _ => None
+        };
+
+        Ok(self.update(angular_speed, acceleration, magnetic_field, dt))
+    }
+
+    /// The device has the sensors the filter needs.
+    pub fn is_available(device: &Device) -> bool {
+        device.get_angular_speed_3_sensor().is_some() && device.get_acceleration_3_sensor().is_some()
+    }
+}
+
+impl Default for Ahrs {
+    fn default() -> Self {
+        Self::new(FusionFilter::default())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 0.01;
+
+    fn assert_close(expected: f32, value: f32, tolerance: f32) {
+        assert!((expected - value).abs() <= tolerance, "expected {}, got {}", expected, value);
+    }
+
+    fn filters() -> Vec<FusionFilter> {
+        vec![FusionFilter::madgwick(), FusionFilter::Madgwick { beta: 0.5 }, FusionFilter::mahony(), FusionFilter::Mahony { kp: 1.0, ki: 0.1 }]
+    }
+
+    /// Gravity in the sensor frame, for a sensor rolled around X.
+    fn rolled_gravity(roll_degrees: f32) -> Acceleration3 {
+        let r = roll_degrees.to_radians();
+        Acceleration3::from_array([0.0, r.sin(), r.cos()])
+    }
+
+    /// Earth's magnetic field, pointing north and down, in the frame of a level sensor
+    /// with the heading.
+    fn heading_field(yaw_degrees: f32) -> MagneticField3 {
+        let r = yaw_degrees.to_radians();
+        MagneticField3::from_array([0.2 * r.cos(), -0.2 * r.sin(), 0.4])
+    }
+
+    fn still() -> AngularSpeed3 {
+        AngularSpeed3::default()
+    }
+
+    #[test]
+    fn test_level() {
+        for filter in filters() {
+            let mut ahrs = Ahrs::new(filter);
+            for _ in 0..100 {
+                ahrs.update(still(), rolled_gravity(0.0), Some(heading_field(0.0)), TimeSpan::from_seconds(DT));
+            }
+
+            let e = ahrs.get_euler_angles();
+            assert_close(0.0, e.roll, 0.01);
+            assert_close(0.0, e.pitch, 0.01);
+            assert_close(0.0, e.yaw, 0.01);
+        }
+    }
+
+    #[test]
+    fn test_converges_to_tilt() {
+        for filter in filters() {
+            let mut ahrs = Ahrs::new(filter);
+            for _ in 0..5000 {
+                ahrs.update(still(), rolled_gravity(30.0), None, TimeSpan::from_seconds(DT));
+            }
+
+            let e = ahrs.get_euler_angles();
+            assert_close(30.0, e.roll, 0.5);
+            assert_close(0.0, e.pitch, 0.5);
+        }
+    }
+
+    #[test]
+    fn test_gyro_integration() {
+        // 90 deg/s around Z for one second, the accelerometer can't see the rotation
+        for filter in filters() {
+            let mut ahrs = Ahrs::new(filter);
+            let w = AngularSpeed3::from_array([0.0, 0.0, 90.0]);
+            for _ in 0..100 {
+                ahrs.update(w, rolled_gravity(0.0), None, TimeSpan::from_seconds(DT));
+            }
+
+            let e = ahrs.get_euler_angles();
+            assert_close(90.0, e.yaw, 0.5);
+            assert_close(0.0, e.roll, 0.1);
+        }
+    }
+
+    #[test]
+    fn test_heading() {
+        for filter in filters() {
+            // the gyroscope and the magnetometer agree on the turn
+            let mut ahrs = Ahrs::new(filter);
+            let w = AngularSpeed3::from_array([0.0, 0.0, 30.0]);
+            for i in 0..100 {
+                let yaw = 30.0 * (i + 1) as f32 * DT;
+                ahrs.update(w, rolled_gravity(0.0), Some(heading_field(yaw)), TimeSpan::from_seconds(DT));
+            }
+            assert_close(30.0, ahrs.get_euler_angles().yaw, 1.0);
+
+            // and the magnetometer alone finds the heading
+            let mut ahrs = Ahrs::new(filter);
+            for _ in 0..20000 {
+                ahrs.update(still(), rolled_gravity(0.0), Some(heading_field(45.0)), TimeSpan::from_seconds(DT));
+            }
+            assert_close(45.0, ahrs.get_euler_angles().yaw, 1.0);
+        }
+    }
+
+    struct TestDevice;
+
+    impl Device for TestDevice {
+        fn get_acceleration_3_sensor(&self) -> Option<&Acceleration3Sensor> {
+            Some(self)
+        }
+
+        fn get_angular_speed_3_sensor(&self) -> Option<&AngularSpeed3Sensor> {
+            Some(self)
+        }
+
+        fn description(&self) -> Cow<str> {
+            "Test IMU".into()
+        }
+
+        fn id(&self) -> Cow<str> {
+            "imu".into()
+        }
+    }
+
+    impl Acceleration3Sensor for TestDevice {
+        fn get_acceleration_3(&self) -> Result<Acceleration3, PeripheryError> {
+            Ok(rolled_gravity(0.0))
+        }
+    }
+
+    impl AngularSpeed3Sensor for TestDevice {
+        fn get_angular_speed_3(&self) -> Result<AngularSpeed3, PeripheryError> {
+            Ok(AngularSpeed3::from_array([0.0, 0.0, 90.0]))
+        }
+    }
+
+    /// Two gyroscope samples per poll, 10 ms apart, like a FIFO burst read.
+    struct BurstPoller {
+        timestamp_us: u64
+    }
+
+    impl DataStreamPoller for BurstPoller {
+        fn get_info(&self) -> DataStream {
+            SensorStreamKind::AngularSpeed3.get_info()
+        }
+
+        fn poll(&mut self, _device: &Device, _clock: &SystemApiClock) -> Result<Vec<DataStreamPolled>, PeripheryError> {
+            let mut ret = vec![];
+            for _ in 0..2 {
+                self.timestamp_us += 10000;
+                ret.push(DataStreamPolled::from_f32(self.timestamp_us, &[0.0, 0.0, 90.0]));
+            }
+            Ok(ret)
+        }
+    }
+
+    /// Stopped, the time steps have to come from the samples.
+    struct TestClock;
+
+    impl SystemApiClock for TestClock {
+        fn get_timestamp_us(&self) -> u64 {
+            0
+        }
+    }
+
+    #[test]
+    fn test_attitude_poller_sample_timestamps() {
+        let device = TestDevice;
+        let mut poller = AttitudePoller::new(Ahrs::default(), Box::new(BurstPoller { timestamp_us: 0 }));
+
+        let mut polled = vec![];
+        for _ in 0..50 {
+            polled.extend(poller.poll(&device, &TestClock).unwrap());
+        }
+
+        // 100 samples, one second at 90 deg/s
+        assert_eq!(100, polled.len());
+        assert_eq!(1000000, polled[99].timestamp_us);
+        assert_close(90.0, polled[99].values[6].as_f32().unwrap(), 0.5);
+    }
+
+    #[test]
+    fn test_fusion_data_streams() {
+        let device = TestDevice;
+        let streams = FusionDataStreams::new(&device).with_filter(FusionFilter::Mahony { kp: 1.0, ki: 0.1 });
+        assert!(streams.get_stream_infos().iter().any(|s| s.id == ATTITUDE_STREAM_ID));
+
+        let mut poller = streams.get_poller(ATTITUDE_STREAM_ID).unwrap();
+        let polled = poller.poll(&device, &TestClock).unwrap();
+        assert_eq!(1, polled.len());
+        assert_eq!(attitude_stream_info().channels.len(), polled[0].values.len());
+    }
+}
@@ -0,0 +1,118 @@
+//! Madgwick's gradient descent orientation filter, from "An efficient orientation filter
+//! for inertial and inertial/magnetic sensor arrays", 2010.
+
+use periphery_core::prelude::v1::*;
+
+/// Gyro in rad/s, the accelerometer and the magnetometer in any scale. Returns the
+/// rate of change of the quaternion.
+pub fn madgwick_update(q: &Quaternion, beta: f32, g: [f32; 3], a: [f32; 3], m: Option<[f32; 3]>) -> Quaternion {
+    let (q0, q1, q2, q3) = (q.w, q.x, q.y, q.z);
+    let (gx, gy, gz) = (g[0], g[1], g[2]);
+
+    // rate of change from the gyroscope
+    let mut q_dot = [
+        0.5 * (-q1 * gx - q2 * gy - q3 * gz),
+        0.5 * (q0 * gx + q2 * gz - q3 * gy),
+        0.5 * (q0 * gy - q1 * gz + q3 * gx),
+        0.5 * (q0 * gz + q1 * gy - q2 * gx)
+    ];
+
+    let a = match normalize(a) {
+        Some(a) => a,
+        None => return to_quaternion(q_dot)
+    };
+    let (ax, ay, az) = (a[0], a[1], a[2]);
+
+    let s = match m.and_then(normalize) {
+        Some(m) => {
+            let (mx, my, mz) = (m[0], m[1], m[2]);
+
+            let _2q0mx = 2.0 * q0 * mx;
+            let _2q0my = 2.0 * q0 * my;
+            let _2q0mz = 2.0 * q0 * mz;
+            let _2q1mx = 2.0 * q1 * mx;
+            let _2q0 = 2.0 * q0;
+            let _2q1 = 2.0 * q1;
+            let _2q2 = 2.0 * q2;
+            let _2q3 = 2.0 * q3;
+            let _2q0q2 = 2.0 * q0 * q2;
+            let _2q2q3 = 2.0 * q2 * q3;
+            let q0q0 = q0 * q0;
+            let q0q1 = q0 * q1;
+            let q0q2 = q0 * q2;
+            let q0q3 = q0 * q3;
+            let q1q1 = q1 * q1;
+            let q1q2 = q1 * q2;
+            let q1q3 = q1 * q3;
+            let q2q2 = q2 * q2;
+            let q2q3 = q2 * q3;
+            let q3q3 = q3 * q3;
+
+            // reference direction of the earth's magnetic field
+            let hx = mx * q0q0 - _2q0my * q3 + _2q0mz * q2 + mx * q1q1 + _2q1 * my * q2 + _2q1 * mz * q3 - mx * q2q2 - mx * q3q3;
+            let hy = _2q0mx * q3 + my * q0q0 - _2q0mz * q1 + _2q1mx * q2 - my * q1q1 + my * q2q2 + _2q2 * mz * q3 - my * q3q3;
+            let _2bx = (hx * hx + hy * hy).sqrt();
+            let _2bz = -_2q0mx * q2 + _2q0my * q1 + mz * q0q0 + _2q1mx * q3 - mz * q1q1 + _2q2 * my * q3 - mz * q2q2 + mz * q3q3;
+            let _4bx = 2.0 * _2bx;
+            let _4bz = 2.0 * _2bz;
+
+            let fa = [2.0 * q1q3 - _2q0q2 - ax, 2.0 * q0q1 + _2q2q3 - ay, 1.0 - 2.0 * q1q1 - 2.0 * q2q2 - az];
+            let fm = [
+                _2bx * (0.5 - q2q2 - q3q3) + _2bz * (q1q3 - q0q2) - mx,
+                _2bx * (q1q2 - q0q3) + _2bz * (q0q1 + q2q3) - my,
+                _2bx * (q0q2 + q1q3) + _2bz * (0.5 - q1q1 - q2q2) - mz
+            ];
+
+            [
+                -_2q2 * fa[0] + _2q1 * fa[1] - _2bz * q2 * fm[0] + (-_2bx * q3 + _2bz * q1) * fm[1] + _2bx * q2 * fm[2],
+                _2q3 * fa[0] + _2q0 * fa[1] - 4.0 * q1 * fa[2] + _2bz * q3 * fm[0] + (_2bx * q2 + _2bz * q0) * fm[1] + (_2bx * q3 - _4bz * q1) * fm[2],
+                -_2q0 * fa[0] + _2q3 * fa[1] - 4.0 * q2 * fa[2] + (-_4bx * q2 - _2bz * q0) * fm[0] + (_2bx * q1 + _2bz * q3) * fm[1] + (_2bx * q0 - _4bz * q2) * fm[2],
+                _2q1 * fa[0] + _2q2 * fa[1] + (-_4bx * q3 + _2bz * q1) * fm[0] + (-_2bx * q0 + _2bz * q2) * fm[1] + _2bx * q1 * fm[2]
+            ]
+        },
+        None => {
+            let _2q0 = 2.0 * q0;
+            let _2q1 = 2.0 * q1;
+            let _2q2 = 2.0 * q2;
+            let _2q3 = 2.0 * q3;
+            let _4q0 = 4.0 * q0;
+            let _4q1 = 4.0 * q1;
+            let _4q2 = 4.0 * q2;
+            let _8q1 = 8.0 * q1;
+            let _8q2 = 8.0 * q2;
+            let q0q0 = q0 * q0;
+            let q1q1 = q1 * q1;
+            let q2q2 = q2 * q2;
+            let q3q3 = q3 * q3;
+
+            [
+                _4q0 * q2q2 + _2q2 * ax + _4q0 * q1q1 - _2q1 * ay,
+                _4q1 * q3q3 - _2q3 * ax + 4.0 * q0q0 * q1 - _2q0 * ay - _4q1 + _8q1 * q1q1 + _8q1 * q2q2 + _4q1 * az,
+                4.0 * q0q0 * q2 + _2q0 * ax + _4q2 * q3q3 - _2q3 * ay - _4q2 + _8q2 * q1q1 + _8q2 * q2q2 + _4q2 * az,
+                4.0 * q1q1 * q3 - _2q1 * ax + 4.0 * q2q2 * q3 - _2q2 * ay
+            ]
+        }
+    };
+
+    // the gradient step, towards the measured gravity and magnetic field
+    let n = (s[0] * s[0] + s[1] * s[1] + s[2] * s[2] + s[3] * s[3]).sqrt();
+    if n > 0.0 {
+        for i in 0..4 {
+            q_dot[i] -= beta * s[i] / n;
+        }
+    }
+
+    to_quaternion(q_dot)
+}
+
+pub fn normalize(v: [f32; 3]) -> Option<[f32; 3]> {
+    let n = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if n == 0.0 || !n.is_finite() {
+        return None;
+    }
+    Some([v[0] / n, v[1] / n, v[2] / n])
+}
+
+fn to_quaternion(q: [f32; 4]) -> Quaternion {
+    Quaternion::new(q[0], q[1], q[2], q[3])
+}
@@ -0,0 +1,72 @@
+//! Mahony's complementary filter, from "Nonlinear Complementary Filters on the Special
+//! Orthogonal Group", 2008. A PI controller corrects the gyroscope with the error
+//! to the measured gravity and magnetic field.
+
+use periphery_core::prelude::v1::*;
+
+use madgwick::normalize;
+
+/// Returns the gyro rates with the correction applied, in rad/s. Updates the integral
+/// of the error, kept by the caller.
+pub fn mahony_correct(q: &Quaternion, kp: f32, ki: f32, integral: &mut [f32; 3], dt: f32, g: [f32; 3], a: [f32; 3], m: Option<[f32; 3]>) -> [f32; 3] {
+    let a = match normalize(a) {
+        Some(a) => a,
+        None => return g
+    };
+    let (q0, q1, q2, q3) = (q.w, q.x, q.y, q.z);
+    let (ax, ay, az) = (a[0], a[1], a[2]);
+
+    let q0q0 = q0 * q0;
+    let q0q1 = q0 * q1;
+    let q0q2 = q0 * q2;
+    let q0q3 = q0 * q3;
+    let q1q1 = q1 * q1;
+    let q1q2 = q1 * q2;
+    let q1q3 = q1 * q3;
+    let q2q2 = q2 * q2;
+    let q2q3 = q2 * q3;
+    let q3q3 = q3 * q3;
+
+    // estimated direction of gravity, halved
+    let vx = q1q3 - q0q2;
+    let vy = q0q1 + q2q3;
+    let vz = q0q0 - 0.5 + q3q3;
+
+    // error between the estimated and the measured gravity
+    let mut e = [ay * vz - az * vy, az * vx - ax * vz, ax * vy - ay * vx];
+
+    if let Some(m) = m.and_then(normalize) {
+        let (mx, my, mz) = (m[0], m[1], m[2]);
+
+        // reference direction of the earth's magnetic field
+        let hx = 2.0 * (mx * (0.5 - q2q2 - q3q3) + my * (q1q2 - q0q3) + mz * (q1q3 + q0q2));
+        let hy = 2.0 * (mx * (q1q2 + q0q3) + my * (0.5 - q1q1 - q3q3) + mz * (q2q3 - q0q1));
+        let bx = (hx * hx + hy * hy).sqrt();
+        let bz = 2.0 * (mx * (q1q3 - q0q2) + my * (q2q3 + q0q1) + mz * (0.5 - q1q1 - q2q2));
+
+        // estimated direction of the magnetic field, halved
+        let wx = bx * (0.5 - q2q2 - q3q3) + bz * (q1q3 - q0q2);
+        let wy = bx * (q1q2 - q0q3) + bz * (q0q1 + q2q3);
+        let wz = bx * (q0q2 + q1q3) + bz * (0.5 - q1q1 - q2q2);
+
+        e[0] += my * wz - mz * wy;
+        e[1] += mz * wx - mx * wz;
+        e[2] += mx * wy - my * wx;
+    }
+
+    let mut corrected = g;
+    for i in 0..3 {
+        // the estimates above are halved
+        let error = 2.0 * e[i];
+
+        if ki > 0.0 {
+            integral[i] += ki * error * dt;
+        } else {
+            integral[i] = 0.0;
+        }
+
+        corrected[i] += kp * error + integral[i];
+    }
+
+    corrected
+}
@@ -0,0 +1,128 @@
+use periphery_core::prelude::v1::*;
+
+use super::*;
+
+/// Above the generic sensor streams of `periphery_core`.
+pub const ATTITUDE_STREAM_ID: DataStreamId = DataStreamId(2000);
+
+pub fn attitude_stream_info() -> DataStream {
+    DataStream {
+        id: ATTITUDE_STREAM_ID,
+        cli_id: "attitude".into(),
+        description: "Attitude, fused from the gyroscope, accelerometer and magnetometer".into(),
+        poll_every_ms: 10,
+        channels: vec![
+            DataStreamChannel::new("qw", DataStreamChannelType::F32),
+            DataStreamChannel::new("qx", DataStreamChannelType::F32),
+            DataStreamChannel::new("qy", DataStreamChannelType::F32),
+            DataStreamChannel::new("qz", DataStreamChannelType::F32),
+            DataStreamChannel::new("roll", DataStreamChannelType::F32).with_unit("°"),
+            DataStreamChannel::new("pitch", DataStreamChannelType::F32).with_unit("°"),
+            DataStreamChannel::new("yaw", DataStreamChannelType::F32).with_unit("°")
+        ]
+    }
+}
+
+/// Runs the filter on every sample of the gyroscope stream, with the time step from the
+/// sample timestamps, so burst reads and recordings keep their timing. The accelerometer
+/// and the magnetometer are read along.
+pub struct AttitudePoller {
+    ahrs: Ahrs,
+    angular_speed: Box<DataStreamPoller + Send + Sync>,
+    last_timestamp_us: Option<u64>
+}
+
+impl AttitudePoller {
+    /// `angular_speed` polls the gyroscope of the device, in °/s.
+    pub fn new(ahrs: Ahrs, angular_speed: Box<DataStreamPoller + Send + Sync>) -> Self {
+        AttitudePoller {
+            ahrs: ahrs,
+            angular_speed: angular_speed,
+            last_timestamp_us: None
+        }
+    }
+
+    pub fn get_ahrs(&mut self) -> &mut Ahrs {
+        &mut self.ahrs
+    }
+}
+
+impl DataStreamPoller for AttitudePoller {
+    fn get_info(&self) -> DataStream {
+        attitude_stream_info()
+    }
+
+    fn poll(&mut self, device: &Device, clock: &SystemApiClock) -> Result<Vec<DataStreamPolled>, PeripheryError> {
+        let mut ret = vec![];
+
+        for sample in self.angular_speed.poll(device, clock)? {
+            let w = match (sample.values.get(0), sample.values.get(1), sample.values.get(2)) {
+                (Some(x), Some(y), Some(z)) => match (x.as_f32(), y.as_f32(), z.as_f32()) {
+                    (Some(x), Some(y), Some(z)) => AngularSpeed3::from_array([x, y, z]),
+                    _ => return Err(PeripheryError::UnsupportedFieldValue)
+                },
+                _ => return Err(PeripheryError::DataNotAvailable)
+            };
+
+            let dt = match self.last_timestamp_us {
+                Some(last) => TimeSpan::from_micro_seconds(sample.timestamp_us.saturating_sub(last) as f32),
+                None => TimeSpan::from_milli_seconds(self.angular_speed.get_info().poll_every_ms as f32)
+            };
+
+            let q = self.ahrs.read_with_angular_speed(device, w, dt)?;
+            self.last_timestamp_us = Some(sample.timestamp_us);
+
+            let e = EulerAngles::from_quaternion(&q);
+            ret.push(DataStreamPolled::from_f32(sample.timestamp_us, &[q.w, q.x, q.y, q.z, e.roll, e.pitch, e.yaw]));
+        }
+
+        Ok(ret)
+    }
+}
+
+/// The streams of the device, with the attitude stream when it has a gyroscope and an
+/// accelerometer.
+pub struct FusionDataStreams<'a> {
+    device: &'a Device,
+    streams: DeviceDataStreams<'a>,
+    filter: FusionFilter
+}
+
+impl<'a> FusionDataStreams<'a> {
+    pub fn new(device: &'a Device) -> Self {
+        FusionDataStreams {
+            device: device,
+            streams: DeviceDataStreams::new(device),
+            filter: FusionFilter::default()
+        }
+    }
+
+    /// The filter and its gains for the attitude stream.
+    pub fn with_filter(mut self, filter: FusionFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+}
+
+impl<'a> DataStreams for FusionDataStreams<'a> {
+    fn get_stream_infos(&self) -> Vec<DataStream> {
+        let mut streams = self.streams.get_stream_infos();
+        if Ahrs::is_available(self.device) {
+            streams.push(attitude_stream_info());
+        }
+        streams
+    }
+
+    fn get_poller(&self, stream: DataStreamId) -> Result<Box<DataStreamPoller + Send + Sync>, PeripheryError> {
+        if stream == ATTITUDE_STREAM_ID && Ahrs::is_available(self.device) {
+            // the native gyroscope stream of the driver, if it has one
+            let cli_id = SensorStreamKind::AngularSpeed3.get_info().cli_id;
+            let gyro = self.streams.get_stream_infos().into_iter().find(|s| s.cli_id == cli_id).ok_or(PeripheryError::NotImplemented)?;
+            let angular_speed = self.streams.get_poller(gyro.id)?;
+
+            return Ok(Box::new(AttitudePoller::new(Ahrs::new(self.filter), angular_speed)));
+        }
+
+        self.streams.get_poller(stream)
+    }
+}
@@ -124,11 +124,7 @@ pub enum GyroOffsetMeasurementStatus {
 impl GyroOffsetMeasurement {
     pub fn new(n: usize) -> GyroOffsetMeasurement {
         GyroOffsetMeasurement {
-            m: AngularSpeed3 {
-                x: AngularSpeed::from_degrees_per_second(0.0),
-                y: AngularSpeed::from_degrees_per_second(0.0),
-                z: AngularSpeed::from_degrees_per_second(0.0)
-            },
+            m: AngularSpeed3::default(),
 
             n: n,
             i: 0
@@ -140,11 +136,7 @@ impl GyroOffsetMeasurement {
             return GyroOffsetMeasurementStatus::Done;
         }
 
-        self.m = AngularSpeed3 {
-            x: AngularSpeed::from_degrees_per_second(self.m.x.get_degrees_per_second() + measurement.x.get_degrees_per_second()),
-            y: AngularSpeed::from_degrees_per_second(self.m.y.get_degrees_per_second() + measurement.y.get_degrees_per_second()),
-            z: AngularSpeed::from_degrees_per_second(self.m.z.get_degrees_per_second() + measurement.z.get_degrees_per_second())
-        };
+        self.m = self.m + measurement;
         self.i += 1;
         
         if self.i >= self.n {
@@ -156,11 +148,7 @@ impl GyroOffsetMeasurement {
 
     pub fn to_filter(&self) -> GyroOffsetStaticCompensation {
         GyroOffsetStaticCompensation {
-            offset: AngularSpeed3 {
-                x: AngularSpeed::from_degrees_per_second(self.m.x.get_degrees_per_second() / (self.i as f32)),
-                y: AngularSpeed::from_degrees_per_second(self.m.y.get_degrees_per_second() / (self.i as f32)),
-                z: AngularSpeed::from_degrees_per_second(self.m.z.get_degrees_per_second() / (self.i as f32))
-            }
+            offset: self.m / (self.i as f32)
         }
     }
 }
@@ -181,10 +169,6 @@ impl GyroOffsetStaticCompensation {
     }
 
     pub fn apply(&self, measurement: AngularSpeed3) -> AngularSpeed3 {
-        AngularSpeed3 {
-            x: AngularSpeed::from_degrees_per_second(measurement.x.get_degrees_per_second() - self.offset.x.get_degrees_per_second()),
-            y: AngularSpeed::from_degrees_per_second(measurement.y.get_degrees_per_second() - self.offset.y.get_degrees_per_second()),
-            z: AngularSpeed::from_degrees_per_second(measurement.z.get_degrees_per_second() - self.offset.z.get_degrees_per_second())
-        }
+        measurement - self.offset
     }
 }
\ No newline at end of file
@@ -0,0 +1,246 @@
+//! The register description, as deserialized from TOML.
+
+use super::CodegenError;
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ChipDescription {
+	/// Name of the generated `#[register_map]` chip struct.
+	pub chip: String,
+	pub doc: Option<String>,
+	#[serde(default)]
+	pub enums: Vec<EnumDescription>,
+	#[serde(default)]
+	pub registers: Vec<RegisterDescription>
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EnumDescription {
+	pub name: String,
+	pub doc: Option<String>,
+	pub variants: Vec<VariantDescription>
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct VariantDescription {
+	pub name: String,
+	pub value: u8,
+	pub doc: Option<String>
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RegisterDescription {
+	pub name: String,
+	pub address: u8,
+	/// Defaults to 1 byte.
+	pub size_bytes: Option<usize>,
+	/// `ro`, `wo` or `rw`, the default.
+	pub access: Option<String>,
+	/// Changed by the chip itself, left out of the snapshots and the register cache.
+	#[serde(default)]
+	pub volatile: bool,
+	pub doc: Option<String>,
+	/// An existing type for the register, like `u8`. Exclusive with `fields`.
+	#[serde(rename = "type")]
+	pub ty: Option<String>,
+	/// Name of the generated struct. Derived from the register's name if missing.
+	#[serde(rename = "struct")]
+	pub struct_name: Option<String>,
+	/// Byte order of multi-byte fields, `msb` or `lsb`.
+	pub endian: Option<String>,
+	#[serde(default)]
+	pub fields: Vec<FieldDescription>
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FieldDescription {
+	pub name: String,
+	/// A single bit (`"3"`) or a range (`"7..4"`), with the least significant bit of the
+	/// last byte numbered 0. Fields without bits are packed one after another.
+	pub bits: Option<String>,
+	/// `bool`, an integer type or the name of one of the enums.
+	#[serde(rename = "type")]
+	pub ty: String,
+	pub doc: Option<String>
+}
+
+impl ChipDescription {
+	pub fn from_toml(input: &str) -> Result<ChipDescription, CodegenError> {
+		::toml::from_str(input).map_err(|e| CodegenError::Toml(format!("{}", e)))
+	}
+
+	pub fn find_enum(&self, name: &str) -> Option<&EnumDescription> {
+		self.enums.iter().find(|e| e.name == name)
+	}
+
+	/// Check the description for errors that would otherwise show up as confusing
+	/// compiler errors in the generated code.
+	pub fn validate(&self) -> Result<(), CodegenError> {
+		for e in &self.enums {
+			if e.variants.is_empty() {
+				return Err(invalid(format!("enum {} has no variants", e.name)));
+			}
+			for (i, v) in e.variants.iter().enumerate() {
+				if e.variants[..i].iter().any(|o| o.name == v.name || o.value == v.value) {
+					return Err(invalid(format!("enum {} has a duplicate variant {}", e.name, v.name)));
+				}
+			}
+		}
+
+		for (i, r) in self.registers.iter().enumerate() {
+			if self.registers[..i].iter().any(|o| o.name == r.name) {
+				return Err(invalid(format!("duplicate register {}", r.name)));
+			}
+
+			match r.access.as_deref() {
+				None | Some("ro") | Some("wo") | Some("rw") => (),
+				Some(a) => { return Err(invalid(format!("register {} has an unknown access mode {}", r.name, a))); }
+			}
+
+			match r.endian.as_deref() {
+				None | Some("msb") | Some("lsb") => (),
+				Some(a) => { return Err(invalid(format!("register {} has an unknown endianness {}", r.name, a))); }
+			}
+
+			match (&r.ty, r.fields.len()) {
+				(&Some(_), 0) => (),
+				(&None, 0) => { return Err(invalid(format!("register {} needs either a type or fields", r.name))); },
+				(&Some(_), _) => { return Err(invalid(format!("register {} can't have both a type and fields", r.name))); },
+				(&None, _) => self.validate_fields(r)?
+			}
+		}
+
+		Ok(())
+	}
+
+	fn validate_fields(&self, r: &RegisterDescription) -> Result<(), CodegenError> {
+		let size_bits = r.size_bytes() * 8;
+		let mut used = vec![false; size_bits];
+
+		for (i, f) in r.fields.iter().enumerate() {
+			if r.fields[..i].iter().any(|o| o.name == f.name) {
+				return Err(invalid(format!("register {} has a duplicate field {}", r.name, f.name)));
+			}
+
+			let kind = FieldKind::new(self, f).ok_or_else(|| invalid(format!("field {}.{} has an unknown type {}", r.name, f.name, f.ty)))?;
+
+			if let FieldKind::Integer { width } = kind {
+				if width > 8 && r.endian.is_none() {
+					return Err(invalid(format!("register {} needs an endianness for the field {}", r.name, f.name)));
+				}
+			}
+
+			let (high, low) = match f.bits {
+				Some(ref bits) => parse_bits(bits).ok_or_else(|| invalid(format!("field {}.{} has invalid bits {}", r.name, f.name, bits)))?,
+				None => {
+					if let FieldKind::Enum(_) = kind {
+						return Err(invalid(format!("enum field {}.{} needs its bits", r.name, f.name)));
+					}
+					continue;
+				}
+			};
+
+			if high >= size_bits {
+				return Err(invalid(format!("field {}.{} doesn't fit into {} bytes", r.name, f.name, r.size_bytes())));
+			}
+
+			let width = high - low + 1;
+			if width > kind.max_width() {
+				return Err(invalid(format!("field {}.{} is {} bits wide, too wide for {}", r.name, f.name, width, f.ty)));
+			}
+			if f.ty.starts_with("i") && width != kind.max_width() {
+				return Err(invalid(format!("signed field {}.{} has to use all the bits of {}", r.name, f.name, f.ty)));
+			}
+			if let FieldKind::Enum(e) = kind {
+				if e.variants.iter().any(|v| (v.value as usize) >> width != 0) {
+					return Err(invalid(format!("enum {} doesn't fit into field {}.{}", e.name, r.name, f.name)));
+				}
+			}
+
+			for u in &mut used[low..(high + 1)] {
+				if *u {
+					return Err(invalid(format!("field {}.{} overlaps with another field", r.name, f.name)));
+				}
+				*u = true;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+impl RegisterDescription {
+	pub fn size_bytes(&self) -> usize {
+		self.size_bytes.unwrap_or(1)
+	}
+
+	/// The Rust type of the register.
+	pub fn type_name(&self) -> String {
+		if let Some(ref ty) = self.ty {
+			return ty.clone();
+		}
+		if let Some(ref s) = self.struct_name {
+			return s.clone();
+		}
+
+		let mut ret = String::new();
+		for part in self.name.split('_') {
+			let mut chars = part.chars();
+			if let Some(c) = chars.next() {
+				ret.extend(c.to_uppercase());
+				ret.extend(chars);
+			}
+		}
+		ret
+	}
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum FieldKind<'a> {
+	Bool,
+	Integer { width: usize },
+	Enum(&'a EnumDescription)
+}
+
+impl<'a> FieldKind<'a> {
+	pub fn new(chip: &'a ChipDescription, field: &FieldDescription) -> Option<FieldKind<'a>> {
+		match field.ty.as_str() {
+			"bool" => Some(FieldKind::Bool),
+			"u8" | "i8" => Some(FieldKind::Integer { width: 8 }),
+			"u16" | "i16" => Some(FieldKind::Integer { width: 16 }),
+			"u32" | "i32" => Some(FieldKind::Integer { width: 32 }),
+			ty => chip.find_enum(ty).map(FieldKind::Enum)
+		}
+	}
+
+	pub fn max_width(&self) -> usize {
+		match *self {
+			FieldKind::Bool => 1,
+			FieldKind::Integer { width } => width,
+			FieldKind::Enum(_) => 8
+		}
+	}
+}
+
+/// Parse `"3"` or `"7..4"` into the highest and the lowest bit.
+pub fn parse_bits(bits: &str) -> Option<(usize, usize)> {
+	let mut parts = bits.split("..");
+	let a = parts.next()?.trim().parse::<usize>().ok()?;
+	let b = match parts.next() {
+		Some(b) => b.trim().parse::<usize>().ok()?,
+		None => a
+	};
+	if parts.next().is_some() {
+		return None;
+	}
+
+	Some(if a >= b { (a, b) } else { (b, a) })
+}
+
+fn invalid(msg: String) -> CodegenError {
+	CodegenError::Invalid(msg)
+}
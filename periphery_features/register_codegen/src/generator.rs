@@ -0,0 +1,275 @@
+//! Emits the Rust source for a validated chip description.
+
+use std::fmt::Write;
+
+use super::description::*;
+
+/// Generate the enums, the packed register structs with their field tables and the
+/// `#[register_map]` chip. The description has to be validated first.
+pub fn generate(chip: &ChipDescription) -> String {
+	let mut s = String::new();
+
+	writeln!(s, "// Generated by periphery_register_codegen from a register description. Do not edit.").unwrap();
+	if let Some(ref doc) = chip.doc {
+		for line in doc.lines() {
+			writeln!(s, "// {}", line).unwrap();
+		}
+	}
+	writeln!(s).unwrap();
+	writeln!(s, "use periphery_core::*;").unwrap();
+	writeln!(s, "use periphery_core::prelude::v1::*;").unwrap();
+	writeln!(s).unwrap();
+	writeln!(s, "use packed_struct::prelude::*;").unwrap();
+	writeln!(s).unwrap();
+
+	for e in &chip.enums {
+		generate_enum(&mut s, e);
+	}
+
+	for r in &chip.registers {
+		if !r.fields.is_empty() {
+			generate_struct(&mut s, chip, r);
+		}
+	}
+
+	if !chip.enums.is_empty() {
+		let names: Vec<&str> = chip.enums.iter().map(|e| e.name.as_str()).collect();
+		writeln!(s, "register_field_enums!({});", names.join(", ")).unwrap();
+		writeln!(s).unwrap();
+	}
+
+	generate_chip(&mut s, chip);
+
+	s
+}
+
+fn generate_doc(s: &mut String, indent: &str, doc: &Option<String>) {
+	if let Some(ref doc) = *doc {
+		for line in doc.lines() {
+			writeln!(s, "{}/// {}", indent, line).unwrap();
+		}
+	}
+}
+
+fn generate_enum(s: &mut String, e: &EnumDescription) {
+	generate_doc(s, "", &e.doc);
+	writeln!(s, "#[derive(Debug, Copy, Clone, PartialEq, Eq, PrimitiveEnum_u8)]").unwrap();
+	writeln!(s, "pub enum {} {{", e.name).unwrap();
+	for (i, v) in e.variants.iter().enumerate() {
+		generate_doc(s, "    ", &v.doc);
+		let separator = if i + 1 < e.variants.len() { "," } else { "" };
+		writeln!(s, "    {} = {}{}", v.name, v.value, separator).unwrap();
+	}
+	writeln!(s, "}}").unwrap();
+	writeln!(s).unwrap();
+}
+
+fn generate_struct(s: &mut String, chip: &ChipDescription, r: &RegisterDescription) {
+	let has_bits = r.fields.iter().any(|f| f.bits.is_some());
+
+	let mut attrs = vec![format!("size_bytes=\"{}\"", r.size_bytes())];
+	if has_bits {
+		attrs.push("bit_numbering=\"lsb0\"".into());
+	}
+	if let Some(ref endian) = r.endian {
+		attrs.push(format!("endian=\"{}\"", endian));
+	}
+
+	// the field table is generated from the struct definition itself
+	writeln!(s, "register_fields! {{").unwrap();
+	generate_doc(s, "    ", &r.doc);
+	writeln!(s, "    #[derive(PackedStruct, Debug, Copy, Clone)]").unwrap();
+	writeln!(s, "    #[packed_struct({})]", attrs.join(", ")).unwrap();
+	writeln!(s, "    pub struct {} {{", r.type_name()).unwrap();
+
+	for (i, f) in r.fields.iter().enumerate() {
+		let kind = FieldKind::new(chip, f).expect("Unknown field type");
+		let bits = f.bits.as_ref().and_then(|b| parse_bits(b));
+
+		generate_doc(s, "        ", &f.doc);
+
+		let ty = match (kind, bits) {
+			(FieldKind::Enum(_), Some((high, low))) => {
+				writeln!(s, "        #[packed_field(bits=\"{}\", ty=\"enum\")]", format_bits(high, low)).unwrap();
+				f.ty.clone()
+			},
+			(FieldKind::Integer { width }, Some((high, low))) if high - low + 1 < width => {
+				writeln!(s, "        #[packed_field(bits=\"{}\")]", format_bits(high, low)).unwrap();
+				format!("Integer<{}, packed_bits::Bits{}>", f.ty, high - low + 1)
+			},
+			(_, Some((high, low))) => {
+				writeln!(s, "        #[packed_field(bits=\"{}\")]", format_bits(high, low)).unwrap();
+				f.ty.clone()
+			},
+			(_, None) => f.ty.clone()
+		};
+
+		let separator = if i + 1 < r.fields.len() { "," } else { "" };
+		writeln!(s, "        pub {}: {}{}", f.name, ty, separator).unwrap();
+	}
+
+	writeln!(s, "    }}").unwrap();
+	writeln!(s, "}}").unwrap();
+	writeln!(s).unwrap();
+}
+
+fn format_bits(high: usize, low: usize) -> String {
+	if high == low {
+		format!("{}", high)
+	} else {
+		format!("{}..{}", high, low)
+	}
+}
+
+fn generate_chip(s: &mut String, chip: &ChipDescription) {
+	writeln!(s, "#[register_map]").unwrap();
+	writeln!(s, "pub struct {} {{", chip.chip).unwrap();
+
+	for (i, r) in chip.registers.iter().enumerate() {
+		generate_doc(s, "    ", &r.doc);
+
+		let mut attrs = vec![format!("address = 0x{:02X}", r.address)];
+		if r.size_bytes() != 1 {
+			attrs.push(format!("size = {}", r.size_bytes()));
+		}
+		if let Some(ref access) = r.access {
+			attrs.push(format!("access = \"{}\"", access));
+		}
+		if r.volatile {
+			attrs.push("volatile".into());
+		}
+		if !r.fields.is_empty() {
+			attrs.push("fields".into());
+		}
+
+		let separator = if i + 1 < chip.registers.len() { "," } else { "" };
+		writeln!(s, "    #[register({})]", attrs.join(", ")).unwrap();
+		writeln!(s, "    {}: {}{}", r.name, r.type_name(), separator).unwrap();
+	}
+
+	writeln!(s, "}}").unwrap();
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::super::*;
+
+	const DESCRIPTION: &str = r#"
+chip = "TestRegisters"
+
+[[enums]]
+name = "FullScale"
+doc = "Accelerometer range"
+variants = [
+    { name = "Scale2g", value = 0 },
+    { name = "Scale4g", value = 1 },
+    { name = "Scale8g", value = 2 },
+    { name = "Scale16g", value = 3 }
+]
+
+[[registers]]
+name = "who_am_i"
+address = 0x0F
+access = "ro"
+type = "u8"
+
+[[registers]]
+name = "status"
+address = 0x27
+access = "ro"
+volatile = true
+type = "u8"
+
+[[registers]]
+name = "ctrl_reg4"
+address = 0x23
+doc = "Control register 4"
+fields = [
+    { name = "block_data_update", bits = "7", type = "bool" },
+    { name = "full_scale", bits = "5..4", type = "FullScale" },
+    { name = "self_test", bits = "2..1", type = "u8" }
+]
+
+[[registers]]
+name = "out"
+address = 0xA8
+size_bytes = 6
+access = "ro"
+struct = "AccelerationData"
+endian = "lsb"
+fields = [
+    { name = "x", type = "i16" },
+    { name = "y", type = "i16" },
+    { name = "z", type = "i16" }
+]
+"#;
+
+	#[test]
+	fn test_generate() {
+		let code = generate_from_toml(DESCRIPTION).unwrap();
+
+		assert!(code.contains("/// Accelerometer range\n#[derive(Debug, Copy, Clone, PartialEq, Eq, PrimitiveEnum_u8)]\npub enum FullScale {\n    Scale2g = 0,\n"));
+		assert!(code.contains("register_fields! {\n    /// Control register 4\n    #[derive(PackedStruct, Debug, Copy, Clone)]\n    #[packed_struct(size_bytes=\"1\", bit_numbering=\"lsb0\")]\n    pub struct CtrlReg4 {\n        #[packed_field(bits=\"7\")]\n        pub block_data_update: bool,\n"));
+		assert!(code.contains("        #[packed_field(bits=\"5..4\", ty=\"enum\")]\n        pub full_scale: FullScale,\n"));
+		assert!(code.contains("        #[packed_field(bits=\"2..1\")]\n        pub self_test: Integer<u8, packed_bits::Bits2>\n    }\n}\n"));
+		assert!(code.contains("    #[packed_struct(size_bytes=\"6\", endian=\"lsb\")]\n    pub struct AccelerationData {\n        pub x: i16,\n"));
+		assert!(code.contains("register_field_enums!(FullScale);"));
+		assert!(code.contains("#[register_map]\npub struct TestRegisters {\n"));
+		assert!(code.contains("    #[register(address = 0x0F, access = \"ro\")]\n    who_am_i: u8,\n"));
+		assert!(code.contains("    #[register(address = 0x27, access = \"ro\", volatile)]\n    status: u8,\n"));
+		assert!(code.contains("    /// Control register 4\n    #[register(address = 0x23, fields)]\n    ctrl_reg4: CtrlReg4,\n"));
+		assert!(code.contains("    #[register(address = 0xA8, size = 6, access = \"ro\", fields)]\n    out: AccelerationData\n}\n"));
+	}
+
+	#[test]
+	fn test_validation() {
+		let overlapping = r#"
+chip = "TestRegisters"
+
+[[registers]]
+name = "ctrl"
+address = 0x20
+fields = [
+    { name = "a", bits = "3..0", type = "u8" },
+    { name = "b", bits = "4..3", type = "u8" }
+]
+"#;
+		assert!(generate_from_toml(overlapping).is_err());
+
+		let too_wide = r#"
+chip = "TestRegisters"
+
+[[registers]]
+name = "ctrl"
+address = 0x20
+fields = [
+    { name = "a", bits = "8..0", type = "u8" }
+]
+"#;
+		assert!(generate_from_toml(too_wide).is_err());
+
+		let unknown_type = r#"
+chip = "TestRegisters"
+
+[[registers]]
+name = "ctrl"
+address = 0x20
+fields = [
+    { name = "a", bits = "1..0", type = "Missing" }
+]
+"#;
+		assert!(generate_from_toml(unknown_type).is_err());
+
+		let unknown_access = r#"
+chip = "TestRegisters"
+
+[[registers]]
+name = "id"
+address = 0x0F
+access = "readonly"
+type = "u8"
+"#;
+		assert!(generate_from_toml(unknown_access).is_err());
+	}
+}
@@ -0,0 +1,102 @@
+//! Generates the register definitions of a chip from a compact TOML description:
+//! the packed register structs, their enums, the field editing glue and the
+//! `#[register_map]` chip with its CLI. Meant to be called from a device crate's
+//! `build.rs`, the crate has to depend on `periphery_core_codegen`.
+//!
+//! ```toml
+//! chip = "Lis3dhRegisters"
+//!
+//! [[enums]]
+//! name = "FullScale"
+//! variants = [
+//!     { name = "Scale2g", value = 0 },
+//!     { name = "Scale4g", value = 1 }
+//! ]
+//!
+//! [[registers]]
+//! name = "who_am_i"
+//! address = 0x0F
+//! access = "ro"
+//! type = "u8"
+//!
+//! [[registers]]
+//! name = "ctrl_reg4"
+//! address = 0x23
+//! struct = "ControlRegister4"
+//! fields = [
+//!     { name = "block_data_update", bits = "7", type = "bool" },
+//!     { name = "full_scale", bits = "5..4", type = "FullScale" }
+//! ]
+//!
+//! [[registers]]
+//! name = "status"
+//! address = 0x27
+//! access = "ro"
+//! volatile = true
+//! type = "u8"
+//! ```
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
+
+mod description;
+mod generator;
+
+pub use self::description::*;
+pub use self::generator::*;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum CodegenError {
+	Toml(String),
+	Invalid(String),
+	Io(String)
+}
+
+impl fmt::Display for CodegenError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			CodegenError::Toml(ref e) => write!(f, "Error parsing the register description: {}", e),
+			CodegenError::Invalid(ref e) => write!(f, "Invalid register description: {}", e),
+			CodegenError::Io(ref e) => write!(f, "I/O error: {}", e)
+		}
+	}
+}
+
+impl From<::std::io::Error> for CodegenError {
+	fn from(err: ::std::io::Error) -> Self {
+		CodegenError::Io(format!("{}", err))
+	}
+}
+
+/// Parse the description and generate the Rust source of the register definitions.
+pub fn generate_from_toml(input: &str) -> Result<String, CodegenError> {
+	let chip = ChipDescription::from_toml(input)?;
+	chip.validate()?;
+	Ok(generate(&chip))
+}
+
+/// For `build.rs`: read the description file, relative to the crate's manifest, and
+/// write the generated code to `OUT_DIR`. Include it with
+/// `include!(concat!(env!("OUT_DIR"), "/<output>"));`.
+pub fn generate_registers(description: &str, output: &str) -> Result<(), CodegenError> {
+	let out_dir = ::std::env::var("OUT_DIR").map_err(|_| CodegenError::Io("OUT_DIR is not set".into()))?;
+
+	let mut input = String::new();
+	File::open(description)?.read_to_string(&mut input)?;
+
+	let code = generate_from_toml(&input)?;
+
+	let mut file = File::create(Path::new(&out_dir).join(output))?;
+	file.write_all(code.as_bytes())?;
+
+	println!("cargo:rerun-if-changed={}", description);
+
+	Ok(())
+}
@@ -1,5 +1,7 @@
 extern crate crc;
 
+extern crate periphery_core;
+
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
@@ -106,8 +108,8 @@ fn test_dec_fusb302() {
                 if pdo.supply_kind == SupplyKind::FixedSupply {
                     let pdo_fixed = PowerDataObjectFixed::unpack_from_slice(&data_bytes).unwrap();
                     println!("pdo fixed: {:#?}", pdo_fixed);
-                    println!("Max current: {:.2} A", pdo_fixed.get_maximum_current_milli_amperes() as f32 / 1000.0);
-                    println!("Voltage: {:.2} V", pdo_fixed.get_voltage_milli_volts() as f32 / 1000.0);
+                    println!("Max current: {:.2} A", pdo_fixed.get_maximum_current().get_amperes());
+                    println!("Voltage: {:.2} V", pdo_fixed.get_voltage().get_volts());
                 }
             }
         }
@@ -197,8 +199,8 @@ fn test_dec_lecroy() {
             if pdo.supply_kind == SupplyKind::FixedSupply {
                 let pdo_fixed = PowerDataObjectFixed::unpack_from_slice(&data_bytes).unwrap();
                 println!("pdo fixed: {:#?}", pdo_fixed);
-                println!("Max current: {:.2} A", pdo_fixed.get_maximum_current_milli_amperes() as f32 / 1000.0);
-                println!("Voltage: {:.2} V", pdo_fixed.get_voltage_milli_volts() as f32 / 1000.0);
+                println!("Max current: {:.2} A", pdo_fixed.get_maximum_current().get_amperes());
+                println!("Voltage: {:.2} V", pdo_fixed.get_voltage().get_volts());
             }
         }
 
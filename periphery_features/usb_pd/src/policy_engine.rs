@@ -192,7 +192,7 @@ impl<Env: Environment> FsmState<PolicyEngine<Env>> for SinkEvaluateCapability {
                 let obj_pos = i + 1;
                 match obj {
                     &PowerDataKind::Fixed(fixed) => {
-                        let supply_volts = fixed.get_voltage_milli_volts() as f32 / 1000.0;
+                        let supply_volts = fixed.get_voltage().get_volts();
                         if (supply_volts - requested_voltage_volts).abs() <= max_delta_volts {
                             println!("found matching voltage");
 
@@ -1,5 +1,7 @@
 use packed_struct::prelude::*;
 
+use periphery_core::units::*;
+
 #[derive(PackedStruct, Debug, Copy, Clone, Serialize)]
 #[packed_struct(size_bytes="4")]
 pub struct Crc {
@@ -181,12 +183,12 @@ pub struct PowerDataObjectFixed {
 }
 
 impl PowerDataObjectFixed {
-    pub fn get_voltage_milli_volts(&self) -> u32 {
-        *self.voltage as u32 * 50
+    pub fn get_voltage(&self) -> Voltage {
+        Voltage::from_milli_volts(*self.voltage as f32 * 50.0)
     }
 
-    pub fn get_maximum_current_milli_amperes(&self) -> u32 {
-        *self.maximum_current as u32 * 10
+    pub fn get_maximum_current(&self) -> Current {
+        Current::from_milli_amperes(*self.maximum_current as f32 * 10.0)
     }
 }
 
@@ -205,16 +207,16 @@ pub struct PowerDataObjectVariable {
 }
 
 impl PowerDataObjectVariable {
-    pub fn get_maximum_voltage_milli_volts(&self) -> u32 {
-        *self.maximum_voltage as u32 * 50
+    pub fn get_maximum_voltage(&self) -> Voltage {
+        Voltage::from_milli_volts(*self.maximum_voltage as f32 * 50.0)
     }
 
-    pub fn get_minimum_voltage_milli_volts(&self) -> u32 {
-        *self.minimum_voltage as u32 * 50
+    pub fn get_minimum_voltage(&self) -> Voltage {
+        Voltage::from_milli_volts(*self.minimum_voltage as f32 * 50.0)
     }
 
-    pub fn get_maximum_current_milli_amperes(&self) -> u32 {
-        *self.maximum_current as u32 * 10
+    pub fn get_maximum_current(&self) -> Current {
+        Current::from_milli_amperes(*self.maximum_current as f32 * 10.0)
     }
 }
 
@@ -232,16 +234,16 @@ pub struct PowerDataObjectBattery {
 }
 
 impl PowerDataObjectBattery {
-    pub fn get_maximum_voltage_milli_volts(&self) -> u32 {
-        *self.maximum_voltage as u32 * 50
+    pub fn get_maximum_voltage(&self) -> Voltage {
+        Voltage::from_milli_volts(*self.maximum_voltage as f32 * 50.0)
     }
 
-    pub fn get_minimum_voltage_milli_volts(&self) -> u32 {
-        *self.minimum_voltage as u32 * 50
+    pub fn get_minimum_voltage(&self) -> Voltage {
+        Voltage::from_milli_volts(*self.minimum_voltage as f32 * 50.0)
     }
 
-    pub fn get_maximum_allowable_power_milli_watts(&self) -> u32 {
-        *self.maximum_allowable_power as u32 * 250
+    pub fn get_maximum_allowable_power(&self) -> ElectricalPower {
+        ElectricalPower::from_milli_watts(*self.maximum_allowable_power as f32 * 250.0)
     }
 }
 
@@ -270,12 +272,12 @@ pub struct SinkPowerDataObjectFixed {
 }
 
 impl SinkPowerDataObjectFixed {
-    pub fn get_voltage_milli_volts(&self) -> u32 {
-        *self.voltage as u32 * 50
+    pub fn get_voltage(&self) -> Voltage {
+        Voltage::from_milli_volts(*self.voltage as f32 * 50.0)
     }
 
-    pub fn get_operational_current_milli_amperes(&self) -> u32 {
-        *self.operational_current as u32 * 10
+    pub fn get_operational_current(&self) -> Current {
+        Current::from_milli_amperes(*self.operational_current as f32 * 10.0)
     }
 }
 
@@ -293,16 +295,16 @@ pub struct SinkPowerDataObjectVariable {
 }
 
 impl SinkPowerDataObjectVariable {
-    pub fn get_maximum_voltage_milli_volts(&self) -> u32 {
-        *self.maximum_voltage as u32 * 50
+    pub fn get_maximum_voltage(&self) -> Voltage {
+        Voltage::from_milli_volts(*self.maximum_voltage as f32 * 50.0)
     }
 
-    pub fn get_minimum_voltage_milli_volts(&self) -> u32 {
-        *self.minimum_voltage as u32 * 50
+    pub fn get_minimum_voltage(&self) -> Voltage {
+        Voltage::from_milli_volts(*self.minimum_voltage as f32 * 50.0)
     }
 
-    pub fn get_operational_current_milli_amperes(&self) -> u32 {
-        *self.operational_current as u32 * 10
+    pub fn get_operational_current(&self) -> Current {
+        Current::from_milli_amperes(*self.operational_current as f32 * 10.0)
     }
 }
 
@@ -321,16 +323,16 @@ pub struct SinkPowerDataObjectBattery {
 }
 
 impl SinkPowerDataObjectBattery {
-    pub fn get_maximum_voltage_milli_volts(&self) -> u32 {
-        *self.maximum_voltage as u32 * 50
+    pub fn get_maximum_voltage(&self) -> Voltage {
+        Voltage::from_milli_volts(*self.maximum_voltage as f32 * 50.0)
     }
 
-    pub fn get_minimum_voltage_milli_volts(&self) -> u32 {
-        *self.minimum_voltage as u32 * 50
+    pub fn get_minimum_voltage(&self) -> Voltage {
+        Voltage::from_milli_volts(*self.minimum_voltage as f32 * 50.0)
     }
 
-    pub fn get_operational_power_milli_watts(&self) -> u32 {
-        *self.operational_power as u32 * 250
+    pub fn get_operational_power(&self) -> ElectricalPower {
+        ElectricalPower::from_milli_watts(*self.operational_power as f32 * 250.0)
     }
 }
 
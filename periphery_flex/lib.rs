@@ -10,6 +10,7 @@ pub mod devices {
 	pub extern crate bmp280;
 	pub extern crate hmc5883;
 	pub extern crate invensense_mpu;
+	pub extern crate lis3dh;
 	pub extern crate ms5611;
 	pub extern crate sht3x;
 	//pub extern crate spi_flash;
@@ -27,11 +28,51 @@ use self::core::*;
 use self::core::prelude::v1::*;
 
 pub struct DetectedDevices<B> where B: Bus + 'static {
-	devices: Vec<DeviceKind<B>>
+	devices: Vec<DetectedDevice<B>>
+}
+
+/// A detected device with its unique id, `bus/address/device`.
+pub struct DetectedDevice<B> where B: Bus + 'static {
+	id: DeviceId,
+	device: DeviceKind<B>
+}
+
+impl<B> DetectedDevice<B> where B: Bus + 'static {
+	pub fn get_id(&self) -> &DeviceId {
+		&self.id
+	}
+
+	pub fn set_alias(&mut self, alias: &str) -> Result<(), PeripheryError> {
+		self.id.set_alias(alias)
+	}
+
+	pub fn get_device(&self) -> &Device {
+		self.device.get_device()
+	}
+
+	/// The device's info, with the bus and the address.
+	pub fn info(&self) -> DeviceInfo {
+		self.id.complete_info(self.get_device().info())
+	}
+
+	pub fn get_kind(&self) -> &DeviceKind<B> {
+		&self.device
+	}
+
+	pub fn into_identified(self) -> IdentifiedDevice {
+		IdentifiedDevice::new(self.id, self.device.into_boxed())
+	}
+}
+
+impl<B, D> DeviceKindGetImpl<D> for DetectedDevice<B> where B: Bus + 'static, DeviceKind<B>: DeviceKindGetImpl<D> {
+	fn get_device_impl(&self) -> Option<&D> {
+		self.device.get_device_impl()
+	}
 }
 
 pub struct SensorOnDevice<'a, T: 'a> where T: ?Sized {
 	pub device: &'a Device,
+	pub device_id: &'a DeviceId,
 	pub sensor: &'a T
 }
 
@@ -39,9 +80,9 @@ pub struct SensorOnDevice<'a, T: 'a> where T: ?Sized {
 macro_rules! find_sensor {
 	($function: ident, $getter: ident, $T: ty) => {
 		pub fn $function(&self, filter: &DeviceFilter) -> Option<SensorOnDevice<$T>> {
-			for device in self.get_devices() {
-				let device = device.get_device();
-				if !filter.matches(device) { continue; }
+			for detected in self.get_devices() {
+				let device = detected.get_device();
+				if !filter.matches(detected.get_id(), device) { continue; }
 
 				if let Some(s) = device.$getter() {
 					if let Some(measurement_quality_at_least) = filter.measurement_quality_at_least {
@@ -50,7 +91,7 @@ macro_rules! find_sensor {
 						}
 					}
 
-					return Some(SensorOnDevice { device: device, sensor: s });
+					return Some(SensorOnDevice { device: device, device_id: detected.get_id(), sensor: s });
 				}
 			}
 
@@ -60,10 +101,61 @@ macro_rules! find_sensor {
 }
 
 impl<B> DetectedDevices<B> where B: Bus + 'static {
-	pub fn get_devices(&self) -> &[DeviceKind<B>] {
+	pub fn get_devices(&self) -> &[DetectedDevice<B>] {
 		&self.devices
 	}
 
+	pub fn find_device(&self, id: &str) -> Option<&DetectedDevice<B>> {
+		self.devices.iter().find(|d| d.get_id().matches(id))
+	}
+
+	pub fn find_devices(&self, filter: &DeviceFilter) -> Vec<&DetectedDevice<B>> {
+		self.devices.iter().filter(|d| filter.matches(d.get_id(), d.get_device())).collect()
+	}
+
+	/// Put every device that supports power management to sleep. Returns the devices
+	/// that failed.
+	pub fn sleep_all(&self) -> Vec<(DeviceId, PeripheryError)> {
+		self.for_each_power_management(|p| p.sleep())
+	}
+
+	pub fn wake_all(&self) -> Vec<(DeviceId, PeripheryError)> {
+		self.for_each_power_management(|p| p.wake())
+	}
+
+	fn for_each_power_management<F>(&self, action: F) -> Vec<(DeviceId, PeripheryError)> where F: Fn(&PowerManagement) -> Result<(), PeripheryError> {
+		let mut failed = vec![];
+		for device in &self.devices {
+			if let Some(power) = device.get_device().get_power_management() {
+				if let Err(e) = action(power) {
+					failed.push((device.get_id().clone(), e));
+				}
+			}
+		}
+		failed
+	}
+
+	/// Ids and infos of all the detected devices.
+	pub fn inventory(&self) -> Vec<(DeviceId, DeviceInfo)> {
+		self.devices.iter().map(|d| (d.get_id().clone(), d.info())).collect()
+	}
+
+	/// Assign an alias to the device with this path or alias.
+	pub fn set_alias(&mut self, id: &str, alias: &str) -> Result<(), PeripheryError> {
+		if self.devices.iter().any(|d| d.get_id().matches(alias) && !d.get_id().matches(id)) {
+			return Err(PeripheryError::UnsupportedFieldValue);
+		}
+
+		match self.devices.iter_mut().find(|d| d.get_id().matches(id)) {
+			Some(device) => device.set_alias(alias),
+			None => Err(PeripheryError::DeviceNotFound)
+		}
+	}
+
+	pub fn into_identified(self) -> Vec<IdentifiedDevice> {
+		self.devices.into_iter().map(|d| d.into_identified()).collect()
+	}
+
 	// todo: more filters
 
 	find_sensor!(find_ambient_light_sensor, get_ambient_light_sensor, AmbientLightSensor);
@@ -76,11 +168,14 @@ impl<B> DetectedDevices<B> where B: Bus + 'static {
 pub struct DeviceFilter {
 	pub description_contains: Option<Cow<'static, str>>,
 	pub id_contains: Option<Cow<'static, str>>,
+	pub manufacturer_contains: Option<Cow<'static, str>>,
+	pub part_number_contains: Option<Cow<'static, str>>,
+	pub bus_kind: Option<DeviceBusKind>,
 	pub measurement_quality_at_least: Option<MeasurementQuality>
 }
 
 impl DeviceFilter {
-	fn matches(&self, device: &Device) -> bool {
+	fn matches(&self, id: &DeviceId, device: &Device) -> bool {
 		if let Some(ref description_contains) = self.description_contains {
 			let description_contains: &str = &description_contains;
 			if !device.description().contains(description_contains) {
@@ -90,7 +185,23 @@ impl DeviceFilter {
 		
 		if let Some(ref id_contains) = self.id_contains {
 			let id_contains: &str = &id_contains;
-			if !device.id().contains(id_contains) {
+			if !id.get_path().contains(id_contains) && !id.get_id().contains(id_contains) {
+				return false;
+			}
+		}
+
+		if self.manufacturer_contains.is_some() || self.part_number_contains.is_some() || self.bus_kind.is_some() {
+			let info = id.complete_info(device.info());
+
+			if !info_contains(&info.manufacturer, &self.manufacturer_contains) {
+				return false;
+			}
+
+			if !info_contains(&info.part_number, &self.part_number_contains) {
+				return false;
+			}
+
+			if self.bus_kind.is_some() && info.bus_kind != self.bus_kind {
 				return false;
 			}
 		}
@@ -99,11 +210,23 @@ impl DeviceFilter {
 	}
 }
 
+/// Case insensitive, a missing value never matches a filter.
+fn info_contains(value: &Option<Cow<'static, str>>, filter: &Option<Cow<'static, str>>) -> bool {
+	match (value, filter) {
+		(_, &None) => true,
+		(&Some(ref value), &Some(ref filter)) => value.to_lowercase().contains(&filter.to_lowercase()),
+		(&None, &Some(_)) => false
+	}
+}
+
 impl Default for DeviceFilter {
 	fn default() -> Self {
 		DeviceFilter {
 			description_contains: None,
 			id_contains: None,
+			manufacturer_contains: None,
+			part_number_contains: None,
+			bus_kind: None,
 			measurement_quality_at_least: None
 		}
 	}
@@ -115,8 +238,8 @@ pub trait DeviceKindGetImpl<D> {
 }
 
 
-pub fn devices_detect_all<B>(bus: B) -> Vec<Box<Device + Send + Sync>> where B: Bus + 'static {
-	devices_detect_all_castable(bus).devices.into_iter().map(|d| d.into_boxed()).collect()
+pub fn devices_detect_all<B>(bus: B) -> Vec<IdentifiedDevice> where B: Bus + 'static {
+	devices_detect_all_castable(bus).into_identified()
 }
 
 
@@ -125,27 +248,27 @@ pub fn devices_detect_all_castable<B>(bus: B) -> DetectedDevices<B> where B: Bus
 
 	{
 		let f: devices::bmp180::Bmp180Factory = Default::default();
-		if let Ok(d) = f.find_all_devices(bus.clone()) {
-			for device in d {
-				devices.push(device.into());
+		if let Ok(d) = f.find_all_devices_identified(bus.clone()) {
+			for (id, device) in d {
+				devices.push(DetectedDevice { id: id, device: device.into() });
 			}
 		}
 	}
 
 	{
 		let f: devices::bmp280::Bmp280Factory = Default::default();
-		if let Ok(d) = f.find_all_devices(bus.clone()) {
-			for device in d {
-				devices.push(device.into());
+		if let Ok(d) = f.find_all_devices_identified(bus.clone()) {
+			for (id, device) in d {
+				devices.push(DetectedDevice { id: id, device: device.into() });
 			}
 		}
 	}	
 
 	{
 		let f: devices::ssd1306::Ssd1306Factory = Default::default();
-		if let Ok(d) = f.find_all_devices(bus.clone()) {
-			for device in d {
-				devices.push(device.into());
+		if let Ok(d) = f.find_all_devices_identified(bus.clone()) {
+			for (id, device) in d {
+				devices.push(DetectedDevice { id: id, device: device.into() });
 			}
 		}
 	}
@@ -153,9 +276,9 @@ pub fn devices_detect_all_castable<B>(bus: B) -> DetectedDevices<B> where B: Bus
 	/*
 	{
 		let f: devices::apds_9960::Apds9960Factory = Default::default();
-		if let Ok(d) = f.find_all_devices(bus.clone()) {
-			for device in d {
-				devices.push(device.into());
+		if let Ok(d) = f.find_all_devices_identified(bus.clone()) {
+			for (id, device) in d {
+				devices.push(DetectedDevice { id: id, device: device.into() });
 			}
 		}
 	}
@@ -163,45 +286,54 @@ pub fn devices_detect_all_castable<B>(bus: B) -> DetectedDevices<B> where B: Bus
 
 	{
 		let f: devices::sht3x::Sht3xFactory = Default::default();
-		if let Ok(d) = f.find_all_devices(bus.clone()) {
-			for device in d {
-				devices.push(device.into());
+		if let Ok(d) = f.find_all_devices_identified(bus.clone()) {
+			for (id, device) in d {
+				devices.push(DetectedDevice { id: id, device: device.into() });
 			}
 		}
 	}
 
 	{
 		let f: devices::hmc5883::Hmc5883Factory = Default::default();
-		if let Ok(d) = f.find_all_devices(bus.clone()) {
-			for device in d {
-				devices.push(device.into());
+		if let Ok(d) = f.find_all_devices_identified(bus.clone()) {
+			for (id, device) in d {
+				devices.push(DetectedDevice { id: id, device: device.into() });
 			}
 		}
 	}
 
 	{
 		let f: devices::ms5611::Ms5611Factory = Default::default();
-		if let Ok(d) = f.find_all_devices(bus.clone()) {
-			for device in d {
-				devices.push(device.into());
+		if let Ok(d) = f.find_all_devices_identified(bus.clone()) {
+			for (id, device) in d {
+				devices.push(DetectedDevice { id: id, device: device.into() });
 			}
 		}
 	}
 
 	{
 		let f: devices::invensense_mpu::InvensenseMpuFactory = Default::default();
-		if let Ok(d) = f.find_all_devices(bus.clone()) {
-			for device in d {
-				devices.push(device.into());
+		if let Ok(d) = f.find_all_devices_identified(bus.clone()) {
+			for (id, device) in d {
+				devices.push(DetectedDevice { id: id, device: device.into() });
 			}
 		}
 	}	
 
+	{
+		let f: devices::lis3dh::Lis3dhFactory = Default::default();
+		if let Ok(d) = f.find_all_devices_identified(bus.clone()) {
+			for (id, device) in d {
+				devices.push(DetectedDevice { id: id, device: device.into() });
+			}
+		}
+	}
+
 	{
 		let f: devices::fusb302::Fusb302Factory = Default::default();
-		if let Ok(d) = f.find_all_devices(bus.clone()) {
-			for device in d {
-				devices.push(device.into());
+		if let Ok(d) = f.find_all_devices_identified(bus.clone()) {
+			for (id, device) in d {
+				devices.push(DetectedDevice { id: id, device: device.into() });
 			}
 		}
 	}		
@@ -270,6 +402,7 @@ devices! {
 	id Bmp180I2C: devices::bmp180::Bmp180OnI2CBus<B>,
 	id Bmp280I2C: devices::bmp280::Bmp280OnI2CBus<B>,
 	id InvensenseMpu: devices::invensense_mpu::InvensenseMpuOnI2CBus<B>,
+	id Lis3dhI2C: devices::lis3dh::Lis3dhOnI2CBus<B>,
 	id Ms5611I2C: devices::ms5611::Ms5611OnI2CBus<B>,
 	id Hmc5883I2C: devices::hmc5883::Hmc5883OnI2CBus<B>,
 	//id SpiFlash: devices::spi_flash::SpiFlash<S, B>,
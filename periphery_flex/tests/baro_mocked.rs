@@ -17,6 +17,10 @@ impl SystemApi for StdSystemApi {
 	fn get_debug(&self) -> Result<&SystemApiDebug, PeripheryError> {
 		Ok(self)
 	}
+
+	fn get_clock(&self) -> Result<&SystemApiClock, PeripheryError> {
+		Ok(self)
+	}
 }
 
 impl SystemApiSleep for StdSystemApi {
@@ -25,6 +29,12 @@ impl SystemApiSleep for StdSystemApi {
     }
 }
 
+impl SystemApiClock for StdSystemApi {
+    fn get_timestamp_us(&self) -> u64 {
+        1500
+    }
+}
+
 impl SystemApiDebug for StdSystemApi {
     fn debug(&self, line: &str) {
         println!("Bus debug: {}", line);
@@ -111,6 +121,15 @@ impl I2CBusDeviceFactory for MockBusI2CDeviceFactory {
     }
 }
 
+/// Calibration and raw measurements of the BMP280 datasheet example, 25.08 °C and
+/// 100653 Pa.
+const BMP280_CALIBRATION: [u8; 24] = [
+    0x70, 0x6B, 0x43, 0x67, 0x18, 0xFC, 0x7D, 0x8E, 0x43, 0xD6, 0xD0, 0x0B,
+    0x27, 0x0B, 0x8C, 0x00, 0xF9, 0xFF, 0x8C, 0x3C, 0xF8, 0xC6, 0x70, 0x17
+];
+const BMP280_PRESSURE: [u8; 3] = [0x65, 0x5A, 0xC0];
+const BMP280_TEMPERATURE: [u8; 3] = [0x7E, 0xED, 0x00];
+
 #[derive(Clone)]
 pub struct I2CDeviceRegisterBus(I2CAddress);
 impl DeviceRegisterBus for I2CDeviceRegisterBus {
@@ -120,6 +139,15 @@ impl DeviceRegisterBus for I2CDeviceRegisterBus {
                 (0xD0, 1) => {
                     data[0] = 0x58; return Ok(());
                 },
+                (0x88, 24) => {
+                    data.copy_from_slice(&BMP280_CALIBRATION); return Ok(());
+                },
+                (0xF7, 3) => {
+                    data.copy_from_slice(&BMP280_PRESSURE); return Ok(());
+                },
+                (0xFA, 3) => {
+                    data.copy_from_slice(&BMP280_TEMPERATURE); return Ok(());
+                },
                 (_, _) => ()
             }
         }
@@ -149,11 +177,40 @@ fn test_mocked_bmp280() {
     {        
         let detected = devices_detect_all_castable(bus);
         assert_eq!(detected.get_devices().len(), 1);
+        assert_eq!("bus/0x76/bmp280", detected.get_devices()[0].get_id().get_id());
+
+        let info = detected.get_devices()[0].info();
+        assert_eq!(Some("BMP280".into()), info.part_number);
+        assert_eq!(Some(DeviceBusKind::I2C), info.bus_kind);
+        assert_eq!(Some("0x76".into()), info.address);
+
+        let filter = DeviceFilter { manufacturer_contains: Some("bosch".into()), ..Default::default() };
+        assert_eq!(1, detected.find_devices(&filter).len());
+        let filter = DeviceFilter { bus_kind: Some(DeviceBusKind::Spi), ..Default::default() };
+        assert_eq!(0, detected.find_devices(&filter).len());
 
         let bmp280_device: &Bmp280<_> = detected.get_devices()[0].get_device_impl().unwrap();
         assert!(bmp280_device.description().contains("BMP280"));
 
-        // will fail
-        bmp280_device.read_calibration_coefficients();
+        let data_streams = DeviceDataStreams::new(bmp280_device);
+        let streams = data_streams.get_stream_infos();
+        let cli_ids: Vec<_> = streams.iter().map(|s| s.cli_id.to_string()).collect();
+        assert_eq!(vec!["ambient_temperature", "atmospheric_pressure"], cli_ids);
+        assert_eq!(Some("Pa".into()), streams[1].channels[0].unit);
+        let mut poller = data_streams.get_poller(streams[1].id).unwrap();
+        let polled = poller.poll(bmp280_device, system_api.get_clock().unwrap()).unwrap();
+        assert_eq!(1, polled.len());
+        assert_eq!(1500, polled[0].timestamp_us);
+        match polled[0].values[..] {
+            [DataStreamValue::F32(pressure)] => assert!((pressure - 100653.0).abs() < 1.0),
+            ref values => panic!("Unexpected values {:?}", values)
+        }
+
+        let mut poller = data_streams.get_poller(streams[0].id).unwrap();
+        let polled = poller.poll(bmp280_device, system_api.get_clock().unwrap()).unwrap();
+        match polled[0].values[..] {
+            [DataStreamValue::F32(temperature)] => assert!((temperature - 25.08).abs() < 0.01),
+            ref values => panic!("Unexpected values {:?}", values)
+        }
     }
 }